@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::build_target::include_directories::{IncludeDirectories, IncludeDirectory, IncludeType};
+use crate::build_target::pkg_config::{PkgConfig, PkgConfigError, PkgConfigLibrary};
+use crate::build_target::{DependencySource, LibraryType};
+
+/// The file written by the yambs generator for Conan.
+pub const CONAN_DEPENDENCIES_FILE_NAME: &str = "yambsdeps.json";
+
+#[derive(Debug, Error)]
+pub enum ConanError {
+    #[error("Failed to read {0:?}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse {0:?}")]
+    Parse(PathBuf, #[source] serde_json::Error),
+    #[error("Package {0} is not listed in {1:?}")]
+    PackageNotFound(String, PathBuf),
+    #[error("Could not find library {0} of package {1} in any of {2:?}")]
+    LibraryNotFound(String, String, Vec<PathBuf>),
+    #[error("Could not find {0} or {1} in {2:?}. Generate them with the yambs or PkgConfigDeps generator of Conan.")]
+    NoGeneratedFiles(String, String, PathBuf),
+    #[error(transparent)]
+    PkgConfig(#[from] PkgConfigError),
+}
+
+/// A package as described by the yambs generator for Conan, with the fields of its `cpp_info`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ConanPackage {
+    pub version: Option<String>,
+    pub includedirs: Vec<PathBuf>,
+    pub libdirs: Vec<PathBuf>,
+    pub libs: Vec<String>,
+    pub system_libs: Vec<String>,
+    pub defines: Vec<String>,
+    pub cflags: Vec<String>,
+    pub cxxflags: Vec<String>,
+    pub sharedlinkflags: Vec<String>,
+    pub exelinkflags: Vec<String>,
+    /// Other packages of the file this package depends on.
+    pub requires: Vec<String>,
+}
+
+/// The contents of `yambsdeps.json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ConanDependencies {
+    #[serde(default)]
+    pub packages: BTreeMap<String, ConanPackage>,
+}
+
+impl ConanDependencies {
+    pub fn from_file(path: &Path) -> Result<Self, ConanError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ConanError::Read(path.to_path_buf(), err))?;
+        serde_json::from_str(&content).map_err(|err| ConanError::Parse(path.to_path_buf(), err))
+    }
+}
+
+/// A Conan package and everything it requires, ready to be compiled and linked with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConanTarget {
+    pub name: String,
+    pub version: Option<String>,
+    pub include_directories: IncludeDirectories,
+    pub defines: Vec<String>,
+    pub c_flags: Vec<String>,
+    pub cxx_flags: Vec<String>,
+    /// Libraries of the packages, in link order.
+    pub libraries: Vec<PathBuf>,
+    /// System libraries of the packages.
+    pub link_flags: Vec<String>,
+    /// Flags executables are linked with.
+    pub executable_link_flags: Vec<String>,
+    /// Flags shared libraries are linked with.
+    pub shared_link_flags: Vec<String>,
+    /// The file generated by Conan that the package was read from.
+    #[serde(default)]
+    pub dependencies_file: PathBuf,
+}
+
+impl ConanTarget {
+    /// Collects `name` and all packages it requires from `dependencies`. Required packages come
+    /// after the packages requiring them, so static libraries are linked in the right order.
+    pub fn from_dependencies(
+        name: &str,
+        dependencies: &ConanDependencies,
+        file: &Path,
+    ) -> Result<Self, ConanError> {
+        let mut order = Vec::new();
+        visit(name, dependencies, file, &mut Vec::new(), &mut order)?;
+
+        let mut include_directories = IncludeDirectories::new();
+        let mut target = Self {
+            name: name.to_string(),
+            version: dependencies.packages[name].version.clone(),
+            include_directories: IncludeDirectories::new(),
+            defines: Vec::new(),
+            c_flags: Vec::new(),
+            cxx_flags: Vec::new(),
+            libraries: Vec::new(),
+            link_flags: Vec::new(),
+            executable_link_flags: Vec::new(),
+            shared_link_flags: Vec::new(),
+            dependencies_file: file.to_path_buf(),
+        };
+        for package_name in order.iter().rev() {
+            let package = &dependencies.packages[package_name.as_str()];
+            for include_directory in &package.includedirs {
+                include_directories.add(IncludeDirectory {
+                    include_type: IncludeType::System,
+                    path: include_directory.clone(),
+                });
+            }
+            for library in &package.libs {
+                let found_library = package
+                    .libdirs
+                    .iter()
+                    .find_map(|libdir| PkgConfigLibrary::find(library, None, libdir, None))
+                    .ok_or_else(|| {
+                        ConanError::LibraryNotFound(
+                            library.clone(),
+                            package_name.clone(),
+                            package.libdirs.clone(),
+                        )
+                    })?;
+                target.libraries.push(found_library.path());
+            }
+            extend_unique(&mut target.defines, &package.defines);
+            extend_unique(&mut target.c_flags, &package.cflags);
+            extend_unique(&mut target.cxx_flags, &package.cxxflags);
+            extend_unique(&mut target.executable_link_flags, &package.exelinkflags);
+            extend_unique(&mut target.shared_link_flags, &package.sharedlinkflags);
+            extend_unique(
+                &mut target.link_flags,
+                &package
+                    .system_libs
+                    .iter()
+                    .map(|library| format!("-l{}", library))
+                    .collect::<Vec<String>>(),
+            );
+        }
+        target.include_directories = include_directories;
+        Ok(target)
+    }
+
+    /// The flags a target of `library_type`, or an executable if `None`, is linked with.
+    pub fn link_flags_for(&self, library_type: Option<&LibraryType>) -> Vec<String> {
+        let mut flags = match library_type {
+            None => self.executable_link_flags.clone(),
+            Some(LibraryType::Dynamic) => self.shared_link_flags.clone(),
+            Some(LibraryType::Static) => Vec::new(),
+        };
+        flags.extend(self.link_flags.iter().cloned());
+        flags
+    }
+
+    /// The files the package was resolved from: the generated file and the libraries found.
+    pub fn resolved_from(&self) -> Vec<PathBuf> {
+        std::iter::once(self.dependencies_file.clone())
+            .chain(self.libraries.iter().cloned())
+            .collect()
+    }
+
+    /// The libraries of the package and its system libraries.
+    pub fn link_arguments(&self) -> Vec<String> {
+        self.libraries
+            .iter()
+            .map(|library| library.display().to_string())
+            .chain(self.link_flags.iter().cloned())
+            .collect()
+    }
+}
+
+/// Resolves the package `name` from the files Conan generated in `generators_directory`.
+///
+/// The file of the yambs generator is preferred. Without it, the `.pc` file of the
+/// `PkgConfigDeps` generator is used.
+pub fn find_dependency(
+    name: &str,
+    generators_directory: &Path,
+    pkg_config: &mut PkgConfig,
+) -> Result<DependencySource, ConanError> {
+    let dependencies_file = generators_directory.join(CONAN_DEPENDENCIES_FILE_NAME);
+    let pc_file_name = format!("{}.pc", name);
+    if dependencies_file.is_file() {
+        let dependencies = ConanDependencies::from_file(&dependencies_file)?;
+        let target = ConanTarget::from_dependencies(name, &dependencies, &dependencies_file)?;
+        Ok(DependencySource::FromConan(target))
+    } else if generators_directory.join(&pc_file_name).is_file() {
+        pkg_config.add_search_path(generators_directory);
+        let target = pkg_config.find_target(name, &BTreeMap::new(), None)?;
+        Ok(DependencySource::FromPkgConfig(target))
+    } else {
+        Err(ConanError::NoGeneratedFiles(
+            CONAN_DEPENDENCIES_FILE_NAME.to_string(),
+            pc_file_name,
+            generators_directory.to_path_buf(),
+        ))
+    }
+}
+
+/// Depth first traversal giving the packages in postorder, so every package comes after the
+/// packages it requires.
+fn visit(
+    name: &str,
+    dependencies: &ConanDependencies,
+    file: &Path,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), ConanError> {
+    if order.iter().any(|visited| visited == name) || stack.iter().any(|active| active == name) {
+        return Ok(());
+    }
+    let package = dependencies
+        .packages
+        .get(name)
+        .ok_or_else(|| ConanError::PackageNotFound(name.to_string(), file.to_path_buf()))?;
+    stack.push(name.to_string());
+    // Visiting the required packages in reverse keeps their order in the result.
+    for required in package.requires.iter().rev() {
+        visit(required, dependencies, file, stack, order)?;
+    }
+    stack.pop();
+    order.push(name.to_string());
+    Ok(())
+}
+
+fn extend_unique(values: &mut Vec<String>, new_values: &[String]) {
+    for value in new_values {
+        if !values.contains(value) {
+            values.push(value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn create_file(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// Generated files for openssl, which requires zlib, in a fake Conan cache.
+    fn generators_directory(directory: &Path) -> PathBuf {
+        let openssl = directory.join("p").join("openssl");
+        let zlib = directory.join("p").join("zlib");
+        create_file(&openssl.join("lib").join("libssl.a"), "");
+        create_file(&openssl.join("lib").join("libcrypto.a"), "");
+        create_file(&zlib.join("lib").join("libz.a"), "");
+        let generators = directory.join("build").join("generators");
+        create_file(
+            &generators.join(CONAN_DEPENDENCIES_FILE_NAME),
+            &serde_json::json!({
+                "packages": {
+                    "openssl": {
+                        "version": "3.2.1",
+                        "includedirs": [openssl.join("include")],
+                        "libdirs": [openssl.join("lib")],
+                        "libs": ["ssl", "crypto"],
+                        "system_libs": ["dl", "pthread"],
+                        "defines": ["OPENSSL_API_COMPAT=10101"],
+                        "exelinkflags": ["-Wl,--as-needed"],
+                        "sharedlinkflags": ["-Wl,-z,defs"],
+                        "requires": ["zlib"]
+                    },
+                    "zlib": {
+                        "version": "1.3.1",
+                        "includedirs": [zlib.join("include")],
+                        "libdirs": [zlib.join("lib")],
+                        "libs": ["z"],
+                        "cxxflags": ["-fPIC"]
+                    }
+                }
+            })
+            .to_string(),
+        );
+        generators
+    }
+
+    #[test]
+    fn package_includes_required_packages_in_link_order() {
+        let dir = tempdir::TempDir::new("conan").unwrap();
+        let generators = generators_directory(dir.path());
+        let mut pkg_config = PkgConfig::isolated();
+
+        let target = match find_dependency("openssl", &generators, &mut pkg_config).unwrap() {
+            DependencySource::FromConan(target) => target,
+            source => panic!("Unexpected dependency source {:?}", source),
+        };
+        let openssl = dir.path().join("p").join("openssl");
+        let zlib = dir.path().join("p").join("zlib");
+        assert_eq!(target.version, Some("3.2.1".to_string()));
+        assert_eq!(
+            target.include_directories.iter().collect::<Vec<_>>(),
+            vec![
+                &IncludeDirectory {
+                    include_type: IncludeType::System,
+                    path: openssl.join("include"),
+                },
+                &IncludeDirectory {
+                    include_type: IncludeType::System,
+                    path: zlib.join("include"),
+                },
+            ]
+        );
+        assert_eq!(
+            target.libraries,
+            vec![
+                openssl.join("lib").join("libssl.a"),
+                openssl.join("lib").join("libcrypto.a"),
+                zlib.join("lib").join("libz.a"),
+            ]
+        );
+        assert_eq!(target.link_flags, vec!["-ldl", "-lpthread"]);
+        assert_eq!(
+            target.link_flags_for(None),
+            vec!["-Wl,--as-needed", "-ldl", "-lpthread"]
+        );
+        assert_eq!(
+            target.link_flags_for(Some(&LibraryType::Dynamic)),
+            vec!["-Wl,-z,defs", "-ldl", "-lpthread"]
+        );
+        assert_eq!(
+            target.link_flags_for(Some(&LibraryType::Static)),
+            vec!["-ldl", "-lpthread"]
+        );
+        assert_eq!(target.defines, vec!["OPENSSL_API_COMPAT=10101"]);
+        assert_eq!(target.cxx_flags, vec!["-fPIC"]);
+        assert_eq!(
+            target.resolved_from()[0],
+            generators.join(CONAN_DEPENDENCIES_FILE_NAME)
+        );
+    }
+
+    #[test]
+    fn missing_library_is_an_error() {
+        let dir = tempdir::TempDir::new("conan").unwrap();
+        let generators = generators_directory(dir.path());
+        std::fs::remove_file(dir.path().join("p").join("zlib").join("lib").join("libz.a")).unwrap();
+
+        assert!(matches!(
+            find_dependency("zlib", &generators, &mut PkgConfig::isolated()),
+            Err(ConanError::LibraryNotFound(..))
+        ));
+    }
+
+    #[test]
+    fn pkg_config_deps_output_is_used_without_yambs_generator() {
+        let dir = tempdir::TempDir::new("conan").unwrap();
+        let zlib = dir.path().join("p").join("zlib");
+        create_file(&zlib.join("lib").join("libz.a"), "");
+        create_file(
+            &dir.path().join("generators").join("zlib.pc"),
+            &format!(
+                "prefix={}\nlibdir=${{prefix}}/lib\nincludedir=${{prefix}}/include\nName: zlib\nDescription: zlib\nVersion: 1.3.1\nLibs: -L${{libdir}} -lz\nCflags: -I${{includedir}}\n",
+                zlib.display()
+            ),
+        );
+
+        let source = find_dependency(
+            "zlib",
+            &dir.path().join("generators"),
+            &mut PkgConfig::isolated(),
+        )
+        .unwrap();
+        match source {
+            DependencySource::FromPkgConfig(target) => {
+                assert_eq!(target.version, Some("1.3.1".to_string()))
+            }
+            source => panic!("Unexpected dependency source {:?}", source),
+        }
+    }
+
+    #[test]
+    fn missing_generated_files_is_an_error() {
+        let dir = tempdir::TempDir::new("conan").unwrap();
+        assert!(matches!(
+            find_dependency("zlib", dir.path(), &mut PkgConfig::isolated()),
+            Err(ConanError::NoGeneratedFiles(..))
+        ));
+    }
+}
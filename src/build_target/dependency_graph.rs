@@ -0,0 +1,621 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::build_target::target_registry::TargetRegistry;
+use crate::build_target::{BuildTarget, DependencySource, TargetType};
+use crate::generator::targets::ObjectTarget;
+
+/// What a node of the dependency graph is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeKind {
+    Executable,
+    Source,
+    HeaderOnly,
+    PkgConfig,
+    LibraryFiles,
+    Conan,
+}
+
+impl std::fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Executable => write!(f, "executable"),
+            Self::Source => write!(f, "source"),
+            Self::HeaderOnly => write!(f, "header-only"),
+            Self::PkgConfig => write!(f, "pkg-config"),
+            Self::LibraryFiles => write!(f, "library-files"),
+            Self::Conan => write!(f, "conan"),
+        }
+    }
+}
+
+/// A target of the registry, or a dependency that is not built from source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraphNode {
+    pub name: String,
+    pub kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_directory: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_directory: Option<PathBuf>,
+    /// The libraries the node is linked with, as paths or linker flags.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub libraries: Vec<String>,
+}
+
+impl GraphNode {
+    fn from_target(target: &BuildTarget, output_directory: &Path) -> Self {
+        match target.target_type {
+            TargetType::Executable(ref executable) => Self {
+                name: executable.to_string(),
+                kind: NodeKind::Executable,
+                library_type: None,
+                manifest_directory: Some(target.manifest.directory.clone()),
+                include_directory: None,
+                libraries: Vec::new(),
+            },
+            TargetType::Library(ref library) => Self {
+                name: library.name.clone(),
+                kind: NodeKind::Source,
+                library_type: Some(library.ty.to_string()),
+                manifest_directory: Some(target.manifest.directory.clone()),
+                include_directory: None,
+                libraries: vec![output_directory
+                    .join(crate::generator::artifact(target))
+                    .display()
+                    .to_string()],
+            },
+        }
+    }
+
+    /// The node of a dependency that is not built from source.
+    fn from_dependency(source: &DependencySource) -> Option<Self> {
+        match source {
+            DependencySource::FromSource(_) => None,
+            DependencySource::FromHeaderOnly(ref header_only) => Some(Self {
+                name: header_only.name.clone(),
+                kind: NodeKind::HeaderOnly,
+                library_type: None,
+                manifest_directory: None,
+                include_directory: Some(header_only.include_directory.path.clone()),
+                libraries: Vec::new(),
+            }),
+            DependencySource::FromPkgConfig(ref pkg_config_target) => Some(Self {
+                name: pkg_config_target.target.clone(),
+                kind: NodeKind::PkgConfig,
+                library_type: None,
+                manifest_directory: None,
+                include_directory: None,
+                libraries: pkg_config_target.libraries(),
+            }),
+            DependencySource::FromLibraryFiles(ref target) => Some(Self {
+                name: target.target.clone(),
+                kind: NodeKind::LibraryFiles,
+                library_type: None,
+                manifest_directory: None,
+                include_directory: None,
+                libraries: target.libraries(),
+            }),
+            DependencySource::FromConan(ref conan_target) => Some(Self {
+                name: conan_target.name.clone(),
+                kind: NodeKind::Conan,
+                library_type: None,
+                manifest_directory: None,
+                include_directory: None,
+                libraries: conan_target.link_arguments(),
+            }),
+        }
+    }
+}
+
+impl GraphNode {
+    /// The type of the node, like "static library" or "pkg-config".
+    fn type_label(&self) -> String {
+        match self.library_type {
+            Some(ref library_type) => format!("{} library", library_type),
+            None => self.kind.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for GraphNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}", self.name, self.kind)?;
+        if let Some(ref library_type) = self.library_type {
+            write!(f, ", {}", library_type)?;
+        }
+        write!(f, ")")?;
+        if let Some(ref directory) = self.manifest_directory {
+            write!(f, " {}", directory.display())?;
+        }
+        if let Some(ref directory) = self.include_directory {
+            write!(f, " {}", directory.display())?;
+        }
+        if !self.libraries.is_empty() {
+            write!(f, " [{}]", self.libraries.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The targets of a registry and their dependencies as a directed graph. Edges go from a target
+/// to the nodes it depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(usize, usize)>,
+    /// The targets of the root manifest.
+    pub roots: Vec<usize>,
+    /// The object files of the targets, relative to the output directory.
+    pub objects: Vec<(usize, PathBuf)>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph of `registry`. Libraries built from source are located in
+    /// `output_directory`.
+    pub fn from_registry(
+        registry: &TargetRegistry,
+        root_manifest_directory: &Path,
+        output_directory: &Path,
+    ) -> Self {
+        let mut graph = Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            roots: Vec::new(),
+            objects: Vec::new(),
+        };
+        for (index, target) in registry.registry.iter().enumerate() {
+            let target = target.borrow();
+            graph
+                .nodes
+                .push(GraphNode::from_target(&target, output_directory));
+            if target.manifest.directory == root_manifest_directory {
+                graph.roots.push(index);
+            }
+        }
+
+        for (index, target) in registry.registry.iter().enumerate() {
+            for dependency in &target.borrow().dependencies {
+                let dependency_index = match dependency.source {
+                    DependencySource::FromSource(ref source_data) => {
+                        registry.registry.iter().position(|candidate| {
+                            let candidate = candidate.borrow();
+                            candidate.manifest.directory == source_data.manifest.directory
+                                && candidate.is_library()
+                                && candidate.name() == source_data.library.name
+                        })
+                    }
+                    ref source => GraphNode::from_dependency(source).map(|node| {
+                        graph
+                            .nodes
+                            .iter()
+                            .position(|existing| *existing == node)
+                            .unwrap_or_else(|| {
+                                graph.nodes.push(node);
+                                graph.nodes.len() - 1
+                            })
+                    }),
+                };
+                if let Some(dependency_index) = dependency_index {
+                    if !graph.edges.contains(&(index, dependency_index)) {
+                        graph.edges.push((index, dependency_index));
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Adds the object files the targets of `registry` are built from.
+    pub fn add_object_files(&mut self, registry: &TargetRegistry, output_directory: &Path) {
+        for (index, target) in registry.registry.iter().enumerate() {
+            for object_target in ObjectTarget::create_object_targets(target, output_directory) {
+                let object = object_target
+                    .object
+                    .strip_prefix(output_directory)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(object_target.object);
+                self.objects.push((index, object));
+            }
+        }
+    }
+
+    /// Renders the graph in the DOT language of Graphviz.
+    pub fn render_dot(&self) -> String {
+        let mut output = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            output.push_str(&format!(
+                "    n{} [label=\"{}\\n{}\", shape={}];\n",
+                index,
+                escape_dot(&node.name),
+                node.type_label(),
+                dot_shape(node.kind)
+            ));
+        }
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            output.push_str(&format!(
+                "    o{} [label=\"{}\", shape=note];\n",
+                index,
+                escape_dot(&object.display().to_string())
+            ));
+        }
+        for (from, to) in &self.edges {
+            output.push_str(&format!(
+                "    n{} -> n{} [label=\"{}\"];\n",
+                from, to, self.nodes[*to].kind
+            ));
+        }
+        for (index, (target, _)) in self.objects.iter().enumerate() {
+            output.push_str(&format!(
+                "    n{} -> o{} [label=\"object\", style=dashed];\n",
+                target, index
+            ));
+        }
+        output.push_str("}\n");
+        output
+    }
+
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn render_mermaid(&self) -> String {
+        let mut output = String::from("graph LR\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let label = format!("{}<br/>{}", escape_mermaid(&node.name), node.type_label());
+            let shape = match node.kind {
+                NodeKind::Executable => format!("[\"{}\"]", label),
+                NodeKind::Source => format!("([\"{}\"])", label),
+                _ => format!("{{{{\"{}\"}}}}", label),
+            };
+            output.push_str(&format!("    n{}{}\n", index, shape));
+        }
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            output.push_str(&format!(
+                "    o{}[/\"{}\"/]\n",
+                index,
+                escape_mermaid(&object.display().to_string())
+            ));
+        }
+        for (from, to) in &self.edges {
+            output.push_str(&format!(
+                "    n{} -->|{}| n{}\n",
+                from, self.nodes[*to].kind, to
+            ));
+        }
+        for (index, (target, _)) in self.objects.iter().enumerate() {
+            output.push_str(&format!("    n{} -.->|object| o{}\n", target, index));
+        }
+        output
+    }
+
+    pub fn dependencies(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |(from, _)| *from == node)
+            .map(|(_, to)| *to)
+    }
+
+    pub fn dependents(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |(_, to)| *to == node)
+            .map(|(from, _)| *from)
+    }
+
+    /// The dependency trees of the targets of the root manifest.
+    pub fn tree(&self) -> Vec<DependencyTree> {
+        let mut expanded = HashSet::new();
+        self.roots
+            .iter()
+            .map(|root| self.subtree(*root, Direction::Dependencies, &mut expanded))
+            .collect()
+    }
+
+    /// Trees of everything that depends on the nodes named `name`, directly or indirectly.
+    pub fn inverted_tree(&self, name: &str) -> Vec<DependencyTree> {
+        let mut expanded = HashSet::new();
+        (0..self.nodes.len())
+            .filter(|node| self.nodes[*node].name == name)
+            .map(|node| self.subtree(node, Direction::Dependents, &mut expanded))
+            .collect()
+    }
+
+    fn subtree(
+        &self,
+        node: usize,
+        direction: Direction,
+        expanded: &mut HashSet<usize>,
+    ) -> DependencyTree {
+        let mut tree = DependencyTree {
+            node: self.nodes[node].clone(),
+            dependencies: Vec::new(),
+            dependents: Vec::new(),
+            repeated: false,
+        };
+        if !expanded.insert(node) {
+            tree.repeated = self.neighbours(node, direction).next().is_some();
+            return tree;
+        }
+        let children = self
+            .neighbours(node, direction)
+            .collect::<Vec<usize>>()
+            .into_iter()
+            .map(|child| self.subtree(child, direction, expanded))
+            .collect();
+        match direction {
+            Direction::Dependencies => tree.dependencies = children,
+            Direction::Dependents => tree.dependents = children,
+        }
+        tree
+    }
+
+    fn neighbours(
+        &self,
+        node: usize,
+        direction: Direction,
+    ) -> Box<dyn Iterator<Item = usize> + '_> {
+        match direction {
+            Direction::Dependencies => Box::new(self.dependencies(node)),
+            Direction::Dependents => Box::new(self.dependents(node)),
+        }
+    }
+}
+
+fn dot_shape(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Executable => "box",
+        NodeKind::Source => "ellipse",
+        NodeKind::HeaderOnly | NodeKind::PkgConfig | NodeKind::LibraryFiles | NodeKind::Conan => {
+            "hexagon"
+        }
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Replaces the characters that end a quoted label or delimit an edge label with Mermaid entity
+/// codes. `#` is escaped first, so that it is not taken for the start of an entity code.
+fn escape_mermaid(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('|', "#124;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Dependencies,
+    Dependents,
+}
+
+/// A node of the graph together with its dependencies, or with its dependents for an
+/// inverted tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyTree {
+    #[serde(flatten)]
+    pub node: GraphNode,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<DependencyTree>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependents: Vec<DependencyTree>,
+    /// The children of the node are already shown elsewhere in the tree.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeated: bool,
+}
+
+impl DependencyTree {
+    fn children(&self) -> &[DependencyTree] {
+        if self.dependencies.is_empty() {
+            &self.dependents
+        } else {
+            &self.dependencies
+        }
+    }
+
+    fn render(&self, prefix: &str, output: &mut String) {
+        let children = self.children();
+        for (index, child) in children.iter().enumerate() {
+            let last = index == children.len() - 1;
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            output.push_str(&format!("{}{}{}\n", prefix, branch, child.line()));
+            child.render(&format!("{}{}", prefix, indent), output);
+        }
+    }
+
+    fn line(&self) -> String {
+        if self.repeated {
+            format!("{} (*)", self.node)
+        } else {
+            self.node.to_string()
+        }
+    }
+}
+
+/// Renders the trees like `cargo tree`, marking nodes whose children are shown elsewhere with
+/// `(*)`.
+pub fn render_trees(trees: &[DependencyTree]) -> String {
+    let mut output = String::new();
+    for tree in trees {
+        output.push_str(&tree.line());
+        output.push('\n');
+        tree.render("", &mut output);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::build_target::include_directories::IncludeDirectories;
+    use crate::build_target::pkg_config::{PkgConfigLDFlags, PkgConfigTarget, ProvideMethod};
+    use crate::build_target::LibraryType;
+    use crate::flags::CXXFlags;
+
+    fn library(directory: &str, name: &str) -> BuildTarget {
+        BuildTarget::library_fixture(directory, name, LibraryType::Static)
+    }
+
+    fn zlib() -> DependencySource {
+        DependencySource::FromPkgConfig(PkgConfigTarget {
+            target: "zlib".to_string(),
+            version: Some("1.3".to_string()),
+            include_directories: IncludeDirectories::new(),
+            cxx_flags: CXXFlags::new(&[]),
+            method: ProvideMethod::PkgConfigOutput(PkgConfigLDFlags {
+                link_libs: vec!["-lz".to_string()],
+                link_dirs: Vec::new(),
+            }),
+            input_files: Vec::new(),
+            requirement: None,
+        })
+    }
+
+    /// `app` depends on `a` and `b`, which both depend on `c`. `c` depends on zlib.
+    fn registry() -> TargetRegistry {
+        let c = library("/c", "c").with_dependency(zlib());
+        let mut a = library("/a", "a");
+        a.depend_on(&c);
+        let mut b = library("/b", "b");
+        b.depend_on(&c);
+        let mut app = BuildTarget::executable_fixture("/app", "app");
+        app.depend_on(&a);
+        app.depend_on(&b);
+
+        TargetRegistry::from_targets(&[
+            app.into_node(),
+            a.into_node(),
+            b.into_node(),
+            c.into_node(),
+        ])
+    }
+
+    #[test]
+    fn tree_shows_shared_dependencies_once() {
+        let graph =
+            DependencyGraph::from_registry(&registry(), Path::new("/app"), Path::new("/build"));
+        assert_eq!(
+            render_trees(&graph.tree()),
+            indoc::indoc! {"
+                app (executable) /app
+                ├── a (source, static) /a [/build/deps/a.a.dir/liba.a]
+                │   └── c (source, static) /c [/build/deps/c.c.dir/libc.a]
+                │       └── zlib (pkg-config) [-lz]
+                └── b (source, static) /b [/build/deps/b.b.dir/libb.a]
+                    └── c (source, static) /c [/build/deps/c.c.dir/libc.a] (*)
+            "}
+        );
+    }
+
+    #[test]
+    fn inverted_tree_shows_dependents() {
+        let graph =
+            DependencyGraph::from_registry(&registry(), Path::new("/app"), Path::new("/build"));
+        assert_eq!(
+            render_trees(&graph.inverted_tree("zlib")),
+            indoc::indoc! {"
+                zlib (pkg-config) [-lz]
+                └── c (source, static) /c [/build/deps/c.c.dir/libc.a]
+                    ├── a (source, static) /a [/build/deps/a.a.dir/liba.a]
+                    │   └── app (executable) /app
+                    └── b (source, static) /b [/build/deps/b.b.dir/libb.a]
+                        └── app (executable) /app
+            "}
+        );
+        assert!(graph.inverted_tree("unknown").is_empty());
+    }
+
+    #[test]
+    fn tree_is_serialized_with_nested_dependencies() {
+        let graph =
+            DependencyGraph::from_registry(&registry(), Path::new("/app"), Path::new("/build"));
+        let json = serde_json::to_value(graph.tree()).unwrap();
+        assert_eq!(
+            json[0]["dependencies"][0]["dependencies"][0],
+            serde_json::json!({
+                "name": "c",
+                "kind": "source",
+                "library_type": "static",
+                "manifest_directory": "/c",
+                "libraries": ["/build/deps/c.c.dir/libc.a"],
+                "dependencies": [{
+                    "name": "zlib",
+                    "kind": "pkg-config",
+                    "libraries": ["-lz"]
+                }]
+            })
+        );
+        assert_eq!(
+            json[0]["dependencies"][1]["dependencies"][0]["repeated"],
+            true
+        );
+    }
+
+    #[test]
+    fn graph_is_rendered_as_dot_with_labeled_edges() {
+        let mut graph =
+            DependencyGraph::from_registry(&registry(), Path::new("/app"), Path::new("/build"));
+        graph.objects.push((0, PathBuf::from("main.o")));
+        assert_eq!(
+            graph.render_dot(),
+            indoc::indoc! {r#"
+                digraph dependencies {
+                    rankdir=LR;
+                    n0 [label="app\nexecutable", shape=box];
+                    n1 [label="a\nstatic library", shape=ellipse];
+                    n2 [label="b\nstatic library", shape=ellipse];
+                    n3 [label="c\nstatic library", shape=ellipse];
+                    n4 [label="zlib\npkg-config", shape=hexagon];
+                    o0 [label="main.o", shape=note];
+                    n0 -> n1 [label="source"];
+                    n0 -> n2 [label="source"];
+                    n1 -> n3 [label="source"];
+                    n2 -> n3 [label="source"];
+                    n3 -> n4 [label="pkg-config"];
+                    n0 -> o0 [label="object", style=dashed];
+                }
+            "#}
+        );
+    }
+
+    #[test]
+    fn graph_is_rendered_as_mermaid_flowchart() {
+        let graph =
+            DependencyGraph::from_registry(&registry(), Path::new("/app"), Path::new("/build"));
+        assert_eq!(
+            graph.render_mermaid(),
+            indoc::indoc! {r#"
+                graph LR
+                    n0["app<br/>executable"]
+                    n1(["a<br/>static library"])
+                    n2(["b<br/>static library"])
+                    n3(["c<br/>static library"])
+                    n4{{"zlib<br/>pkg-config"}}
+                    n0 -->|source| n1
+                    n0 -->|source| n2
+                    n1 -->|source| n3
+                    n2 -->|source| n3
+                    n3 -->|pkg-config| n4
+            "#}
+        );
+    }
+
+    #[test]
+    fn mermaid_labels_are_escaped() {
+        assert_eq!(
+            escape_mermaid(r#"lib"a"|<b>#1"#),
+            "lib#quot;a#quot;#124;#lt;b#gt;#35;1"
+        );
+    }
+}
@@ -5,7 +5,13 @@ use std::rc::Rc;
 use crate::cli::configurations::BuildType;
 use crate::errors;
 use crate::flags::CompilerFlags;
+use crate::git::{GitCache, GitError, GitReference};
+use crate::lockfile::{
+    LockedConanDependency, LockedGitDependency, LockedHeaderOnlyDependency,
+    LockedPkgConfigDependency, LockedSourceDependency, Lockfile, LockfileError,
+};
 use crate::manifest;
+use crate::overrides::{OverrideError, Overrides};
 use crate::parser;
 use crate::parser::types;
 use crate::targets;
@@ -13,13 +19,20 @@ use crate::toolchain::NormalizedToolchain;
 use crate::YAMBS_MANIFEST_NAME;
 
 pub mod associated_files;
+pub mod conan;
+pub mod dependency_graph;
 pub mod include_directories;
 pub mod pkg_config;
+pub mod system;
 pub mod target_registry;
+pub mod vcpkg;
 use associated_files::SourceFiles;
+use conan::{ConanError, ConanTarget};
 use include_directories::IncludeDirectory;
 use include_directories::IncludeType;
 use pkg_config::{PkgConfigError, PkgConfigTarget};
+use system::SystemError;
+use vcpkg::{Vcpkg, VcpkgError};
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct DependencySourceData {
@@ -40,6 +53,10 @@ pub enum DependencySource {
     FromSource(DependencySourceData),
     FromHeaderOnly(HeaderOnlyData),
     FromPkgConfig(PkgConfigTarget),
+    /// Libraries found by their files, without a pkg-config file: system dependencies and vcpkg
+    /// packages that don't install one.
+    FromLibraryFiles(PkgConfigTarget),
+    FromConan(ConanTarget),
 }
 
 impl DependencySource {
@@ -49,6 +66,31 @@ impl DependencySource {
             _ => None,
         }
     }
+
+    pub fn from_conan(&self) -> Option<&ConanTarget> {
+        match self {
+            Self::FromConan(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// The files outside of the manifests that the dependency was resolved from, so a change to
+    /// any of them can be detected.
+    pub fn resolved_from(&self) -> Vec<std::path::PathBuf> {
+        match self {
+            Self::FromSource(_) | Self::FromHeaderOnly(_) => Vec::new(),
+            Self::FromPkgConfig(t) | Self::FromLibraryFiles(t) => t.resolved_from(),
+            Self::FromConan(c) => c.resolved_from(),
+        }
+    }
+
+    /// The libraries of a dependency found with pkg-config or by its library files.
+    pub fn pkg_config_target(&self) -> Option<&PkgConfigTarget> {
+        match self {
+            Self::FromPkgConfig(t) | Self::FromLibraryFiles(t) => Some(t),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -64,6 +106,7 @@ impl Dependency {
         registry.get_target_from_predicate(|build_target| {
             let dependency_source_data = self.source.from_source().unwrap();
             build_target.manifest.directory == dependency_source_data.manifest.directory
+                && build_target.name() == dependency_source_data.library.name
                 && build_target.library_type() == Some(dependency_source_data.library.ty.clone())
         })
     }
@@ -77,9 +120,35 @@ pub struct SourceBuildData {
     pub defines: Vec<types::Define>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Project {
+    pub name: Option<String>,
+    pub version: Option<semver::Version>,
+}
+
+impl Project {
+    pub fn from_manifest_data(manifest_data: &manifest::ManifestData) -> Self {
+        match manifest_data.project_config {
+            Some(ref project_config) => Self {
+                name: project_config.name.clone(),
+                version: project_config.version.clone(),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// What dependencies are resolved against: the lockfile they are locked in and recorded to, and
+/// the overrides that replace them. Overrides take precedence over the lockfile.
+pub struct Resolution<'a> {
+    pub lockfile: &'a mut Lockfile,
+    pub overrides: &'a mut Overrides,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct BuildTarget {
     pub state: TargetState,
+    pub project: Project,
     pub target_type: TargetType,
     pub include_directory: IncludeDirectory,
     pub compiler_flags: CompilerFlags,
@@ -87,16 +156,19 @@ pub struct BuildTarget {
     pub dependencies: Vec<Dependency>,
     pub source_files: SourceFiles,
     pub defines: Vec<types::Define>,
+    pub pkg_config_export: Option<types::PkgConfigExportData>,
 }
 
 impl BuildTarget {
     pub fn target_node_from_source(
-        manifest_dir_path: &std::path::Path,
+        manifest: &manifest::ParsedManifest,
         target: &targets::Target,
         registry: &mut target_registry::TargetRegistry,
         toolchain: &Rc<RefCell<NormalizedToolchain>>,
         build_type: &BuildType,
+        resolution: &mut Resolution,
     ) -> Result<TargetNode, TargetError> {
+        let manifest_dir_path = manifest.manifest.directory.as_path();
         let target_type = TargetType::new(target);
 
         if let Some(existing_node) = registry.get_target_from_predicate(|build_target| {
@@ -107,13 +179,12 @@ impl BuildTarget {
         }
 
         let target_node = match target {
-            targets::Target::Executable(executable) => TargetNode::new(
-                BuildTarget::executable_from_source(manifest_dir_path, executable)?,
-            ),
-            targets::Target::Library(library) => TargetNode::new(BuildTarget::library_from_source(
-                manifest_dir_path,
-                library,
-            )?),
+            targets::Target::Executable(executable) => {
+                TargetNode::new(BuildTarget::executable_from_source(manifest, executable)?)
+            }
+            targets::Target::Library(library) => {
+                TargetNode::new(BuildTarget::library_from_source(manifest, library)?)
+            }
         };
 
         log::debug!(
@@ -124,7 +195,7 @@ impl BuildTarget {
         target_node.borrow_mut().state = TargetState::InProcess;
         let target_vec = target_node
             .borrow()
-            .detect_target(registry, target, toolchain, build_type)?;
+            .detect_target(registry, target, toolchain, build_type, resolution)?;
 
         for target in target_vec {
             match target.source {
@@ -143,6 +214,14 @@ impl BuildTarget {
             target_node.borrow_mut().add_target(target);
         }
         target_node.borrow_mut().state = TargetState::Registered;
+        if let Some(cycle) = registry.find_cycle(&target_node) {
+            return Err(TargetError::Circulation(
+                cycle
+                    .iter()
+                    .map(|target| target.borrow().qualified_name())
+                    .collect(),
+            ));
+        }
         Ok(target_node)
     }
 
@@ -178,14 +257,46 @@ impl BuildTarget {
         }
     }
 
+    /// The name of the target together with the directory of its manifest, which identifies
+    /// the target among libraries with the same name.
+    pub fn qualified_name(&self) -> String {
+        format!("{}@{}", self.name(), self.manifest.directory.display())
+    }
+
+    /// The name of the target qualified with the name of its project, like `fmt.fmt`. Projects
+    /// without a name are named after their manifest directory. The identity names the rules,
+    /// directories and variables of the target in build files, so that libraries with the same
+    /// name in different projects don't collide.
+    pub fn identity(&self) -> String {
+        let project = self.project.name.clone().unwrap_or_else(|| {
+            self.manifest
+                .directory
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        format!("{}.{}", project, self.name())
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
     fn executable_from_source(
-        manifest_dir_path: &std::path::Path,
+        manifest: &manifest::ParsedManifest,
         executable: &targets::Executable,
     ) -> Result<Self, TargetError> {
+        let manifest_dir_path = manifest.manifest.directory.as_path();
         let source_files = executable.sources.clone();
 
         Ok(Self {
             state: TargetState::NotInProcess,
+            project: Project::from_manifest_data(&manifest.data),
             target_type: TargetType::Executable(PrintableExecutable(executable.name.to_string())),
             include_directory: include_directories::IncludeDirectory {
                 include_type: include_directories::IncludeType::Include,
@@ -197,17 +308,20 @@ impl BuildTarget {
             source_files: SourceFiles::from_paths(&source_files)
                 .map_err(TargetError::AssociatedFile)?,
             defines: executable.defines.clone(),
+            pkg_config_export: None,
         })
     }
 
     fn library_from_source(
-        manifest_dir_path: &std::path::Path,
+        manifest: &manifest::ParsedManifest,
         library: &targets::Library,
     ) -> Result<Self, TargetError> {
+        let manifest_dir_path = manifest.manifest.directory.as_path();
         let source_files = library.sources.clone();
 
         Ok(Self {
             state: TargetState::NotInProcess,
+            project: Project::from_manifest_data(&manifest.data),
             target_type: TargetType::Library(PrintableLibrary::from(library)),
             include_directory: include_directories::IncludeDirectory {
                 include_type: include_directories::IncludeType::Include,
@@ -219,6 +333,7 @@ impl BuildTarget {
             source_files: SourceFiles::from_paths(&source_files)
                 .map_err(TargetError::AssociatedFile)?,
             defines: library.defines.clone(),
+            pkg_config_export: library.pkg_config.clone(),
         })
     }
 
@@ -228,6 +343,7 @@ impl BuildTarget {
         target: &targets::Target,
         toolchain: &Rc<RefCell<NormalizedToolchain>>,
         build_type: &BuildType,
+        resolution: &mut Resolution,
     ) -> Result<Vec<Dependency>, TargetError> {
         log::debug!(
             "Checking if target \"{}\" has registered dependencies",
@@ -237,83 +353,161 @@ impl BuildTarget {
         for dependency in target.dependencies() {
             match dependency.data {
                 types::DependencyData::Source(ref dependency_source_data) => {
-                    if let Some(registered_dep) =
-                        registry.get_target_from_predicate(|build_target| {
-                            build_target.manifest.directory == dependency_source_data.path
-                                && build_target.name() == dependency.name
-                        })
+                    let overridden_source_data = resolution
+                        .overrides
+                        .replacement_for(&dependency.name, Some(&dependency_source_data.path))
+                        .map_err(|err| TargetError::Override(dependency.name.clone(), err))?
+                        .map(|path| types::SourceData {
+                            path,
+                            ..dependency_source_data.clone()
+                        });
+                    let dependency_source_data = overridden_source_data
+                        .as_ref()
+                        .unwrap_or(dependency_source_data);
+                    target_vec.push(self.source_dependency(
+                        &dependency.name,
+                        dependency_source_data,
+                        registry,
+                        toolchain,
+                        build_type,
+                        resolution,
+                    )?);
+                    resolution.lockfile.record_source(
+                        LockedSourceDependency::from_path(
+                            &dependency.name,
+                            &dependency_source_data.path,
+                        )
+                        .map_err(TargetError::Lockfile)?,
+                    );
+                }
+                types::DependencyData::Git(ref git_data) => {
+                    let dependency_source_data = match resolution
+                        .overrides
+                        .replacement_for(&dependency.name, None)
+                        .map_err(|err| TargetError::Override(dependency.name.clone(), err))?
                     {
-                        log::debug!(
-                            "Found registered dependency. Checking for cyclic dependencies"
-                        );
-                        self.detect_cycle_from_target(&registered_dep)?;
-                        let borrowed_dep = registered_dep.borrow();
-                        let dependency_source =
-                            DependencySource::FromSource(DependencySourceData {
-                                library: PrintableLibrary {
-                                    name: registered_dep.borrow().name(),
-                                    ty: registered_dep.borrow().library_type().ok_or_else(
-                                        || {
-                                            TargetError::DependencyNotALibrary(
-                                                registered_dep.borrow().name(),
-                                            )
-                                        },
-                                    )?,
-                                },
-                                manifest: borrowed_dep.manifest.clone(),
-                                include_directory: registered_dep
-                                    .borrow()
-                                    .include_directory
-                                    .clone(),
-                            });
-                        let dependency = Dependency {
-                            source: dependency_source,
-                        };
-                        target_vec.push(dependency);
-                    } else {
-                        log::debug!(
-                            "No registered dependency found. Creating dependency build target."
-                        );
-                        let manifest_path = dependency_source_data.path.join(YAMBS_MANIFEST_NAME);
-                        let manifest = parser::parse(&manifest_path).map_err(TargetError::Parse)?;
-                        let dep_target = manifest
-                            .data
-                            .targets
-                            .iter()
-                            .find_map(|dep| {
-                                if let Some(lib) = dep.library() {
-                                    if lib.name == dependency.name {
-                                        return Some(dep);
-                                    }
-                                }
-                                None
-                            })
-                            .ok_or_else(|| {
-                                TargetError::NoLibraryWithName(dependency.name.clone())
-                            })?;
-                        let target = BuildTarget::target_node_from_source(
+                        Some(path) => types::SourceData {
+                            path,
+                            origin: git_data.origin.clone(),
+                            version: git_data.version.clone(),
+                        },
+                        None => checkout_git_dependency(
+                            &dependency.name,
+                            git_data,
+                            resolution.lockfile,
+                        )?,
+                    };
+                    target_vec.push(self.source_dependency(
+                        &dependency.name,
+                        &dependency_source_data,
+                        registry,
+                        toolchain,
+                        build_type,
+                        resolution,
+                    )?);
+                    resolution.lockfile.record_source(
+                        LockedSourceDependency::from_git(
+                            &dependency.name,
+                            &git_data.git,
                             &dependency_source_data.path,
-                            dep_target,
-                            registry,
-                            toolchain,
+                        )
+                        .map_err(TargetError::Lockfile)?,
+                    );
+                }
+                types::DependencyData::Vcpkg(ref vcpkg_data) => {
+                    let vcpkg_error = |err| TargetError::Vcpkg(dependency.name.clone(), err);
+                    let vcpkg =
+                        Vcpkg::locate(&self.manifest.directory, vcpkg_data.triplet.as_deref())
+                            .map_err(vcpkg_error)?;
+                    let mut vcpkg_source = vcpkg
+                        .find_target(
+                            &dependency.name,
+                            vcpkg_data,
                             build_type,
-                        )?;
-                        let borrowed_target = target.borrow();
-                        let dependency_source =
-                            DependencySource::FromSource(DependencySourceData {
-                                library: PrintableLibrary {
-                                    name: target.borrow().name(),
-                                    ty: target.borrow().library_type().ok_or_else(|| {
-                                        TargetError::DependencyNotALibrary(target.borrow().name())
-                                    })?,
-                                },
-                                manifest: borrowed_target.manifest.clone(),
-                                include_directory: target.borrow().include_directory.clone(),
-                            });
-                        target_vec.push(Dependency {
-                            source: dependency_source,
+                            &mut toolchain.borrow_mut().pkg_config,
+                        )
+                        .map_err(vcpkg_error)?;
+                    if let DependencySource::FromPkgConfig(ref mut vcpkg_target) = vcpkg_source {
+                        vcpkg_target.requirement = vcpkg_data.version.clone();
+                    }
+                    let vcpkg_target = vcpkg_source
+                        .pkg_config_target()
+                        .expect("vcpkg packages are found with pkg-config or by their libraries");
+                    self.verify_dependency_version(
+                        &dependency.name,
+                        vcpkg_data.version.as_ref(),
+                        vcpkg_target.version.clone(),
+                    )?;
+                    resolution
+                        .lockfile
+                        .record_pkg_config(LockedPkgConfigDependency {
+                            name: dependency.name.clone(),
+                            version: vcpkg_target.version.clone(),
+                            libraries: vcpkg_target.libraries(),
                         });
+                    target_vec.push(Dependency {
+                        source: vcpkg_source,
+                    });
+                }
+                types::DependencyData::Conan(ref conan_data) => {
+                    let mut conan_source = conan::find_dependency(
+                        &dependency.name,
+                        &conan_data.conan,
+                        &mut toolchain.borrow_mut().pkg_config,
+                    )
+                    .map_err(|err| TargetError::Conan(dependency.name.clone(), err))?;
+                    match conan_source {
+                        DependencySource::FromConan(ref conan_target) => {
+                            self.verify_dependency_version(
+                                &dependency.name,
+                                conan_data.version.as_ref(),
+                                conan_target.version.clone(),
+                            )?;
+                            resolution.lockfile.record_conan(LockedConanDependency {
+                                name: dependency.name.clone(),
+                                version: conan_target.version.clone(),
+                                libraries: conan_target.link_arguments(),
+                            });
+                        }
+                        DependencySource::FromPkgConfig(ref mut pkg_config_target) => {
+                            self.verify_dependency_version(
+                                &dependency.name,
+                                conan_data.version.as_ref(),
+                                pkg_config_target.version.clone(),
+                            )?;
+                            pkg_config_target.requirement = conan_data.version.clone();
+                            resolution
+                                .lockfile
+                                .record_pkg_config(LockedPkgConfigDependency {
+                                    name: dependency.name.clone(),
+                                    version: pkg_config_target.version.clone(),
+                                    libraries: pkg_config_target.libraries(),
+                                });
+                        }
+                        _ => {}
                     }
+                    target_vec.push(Dependency {
+                        source: conan_source,
+                    });
+                }
+                types::DependencyData::System(ref system_data) => {
+                    let system_error = |err| TargetError::System(dependency.name.clone(), err);
+                    let toolchain = toolchain.borrow();
+                    let search_directories =
+                        toolchain.search_directories().map_err(system_error)?;
+                    let system_target =
+                        system::find_target(&dependency.name, system_data, search_directories)
+                            .map_err(system_error)?;
+                    resolution
+                        .lockfile
+                        .record_pkg_config(LockedPkgConfigDependency {
+                            name: dependency.name.clone(),
+                            version: None,
+                            libraries: system_target.libraries(),
+                        });
+                    target_vec.push(Dependency {
+                        source: DependencySource::FromLibraryFiles(system_target),
+                    });
                 }
                 types::DependencyData::HeaderOnly(ref header_only_data) => {
                     let header_only = HeaderOnlyData {
@@ -323,6 +517,12 @@ impl BuildTarget {
                             include_type: IncludeType::System,
                         },
                     };
+                    resolution
+                        .lockfile
+                        .record_header_only(LockedHeaderOnlyDependency {
+                            name: dependency.name.clone(),
+                            include_directory: header_only_data.include_directory.clone(),
+                        });
                     let header_only = DependencySource::FromHeaderOnly(header_only);
                     target_vec.push(Dependency {
                         source: header_only,
@@ -330,25 +530,39 @@ impl BuildTarget {
                 }
                 types::DependencyData::PkgConfig(ref pkg_config_data) => {
                     let mut toolchain_lock = toolchain.borrow_mut();
-                    if let Some(ref mut pkg_config) = toolchain_lock.pkg_config {
-                        pkg_config.add_search_path(&pkg_config_data.search_dir);
-                        match pkg_config.find_target(&dependency.name) {
-                            Ok(pkg_config_target) => {
-                                let pkg_config_dep =
-                                    DependencySource::FromPkgConfig(pkg_config_target);
-                                target_vec.push(Dependency {
-                                    source: pkg_config_dep,
+                    let pkg_config = &mut toolchain_lock.pkg_config;
+                    pkg_config.add_search_path(&pkg_config_data.search_dir);
+                    let link = pkg_config_data.link.as_ref().map(LibraryType::from);
+                    match pkg_config.find_target(
+                        &dependency.name,
+                        &pkg_config_data.define_variable,
+                        link.as_ref(),
+                    ) {
+                        Ok(mut pkg_config_target) => {
+                            self.verify_dependency_version(
+                                &dependency.name,
+                                pkg_config_data.version.as_ref(),
+                                pkg_config_target.version.clone(),
+                            )?;
+                            pkg_config_target.requirement = pkg_config_data.version.clone();
+                            resolution
+                                .lockfile
+                                .record_pkg_config(LockedPkgConfigDependency {
+                                    name: dependency.name.clone(),
+                                    version: pkg_config_target.version.clone(),
+                                    libraries: pkg_config_target.libraries(),
                                 });
-                            }
-                            Err(e) => {
-                                return Err(TargetError::CouldNotFindPkgConfigPackage(
-                                    dependency.name.clone(),
-                                    e,
-                                ))
-                            }
+                            let pkg_config_dep = DependencySource::FromPkgConfig(pkg_config_target);
+                            target_vec.push(Dependency {
+                                source: pkg_config_dep,
+                            });
+                        }
+                        Err(e) => {
+                            return Err(TargetError::CouldNotFindPkgConfigPackage(
+                                dependency.name.clone(),
+                                e,
+                            ))
                         }
-                    } else {
-                        return Err(TargetError::NoPkgConfigInstance);
                     }
                 }
             }
@@ -357,17 +571,118 @@ impl BuildTarget {
         Ok(target_vec)
     }
 
-    fn detect_cycle_from_target(&self, target_node: &TargetNode) -> Result<(), TargetError> {
-        if target_node.borrow().state == TargetState::InProcess
-            && target_node.borrow().name() == self.name()
-        {
-            let borrowed_target_node = target_node.borrow();
-            return Err(TargetError::Circulation(
-                borrowed_target_node.manifest.directory.to_path_buf(),
-                self.manifest.directory.to_path_buf(),
-            ));
+    fn source_dependency(
+        &self,
+        dependency_name: &str,
+        dependency_source_data: &types::SourceData,
+        registry: &mut target_registry::TargetRegistry,
+        toolchain: &Rc<RefCell<NormalizedToolchain>>,
+        build_type: &BuildType,
+        resolution: &mut Resolution,
+    ) -> Result<Dependency, TargetError> {
+        if let Some(registered_dep) = registry.get_target_from_predicate(|build_target| {
+            build_target.manifest.directory == dependency_source_data.path
+                && build_target.name() == dependency_name
+        }) {
+            log::debug!("Found registered dependency");
+            let borrowed_dep = registered_dep.borrow();
+            self.verify_dependency_version(
+                dependency_name,
+                dependency_source_data.version.as_ref(),
+                borrowed_dep
+                    .project
+                    .version
+                    .as_ref()
+                    .map(|version| version.to_string()),
+            )?;
+            let dependency_source = DependencySource::FromSource(DependencySourceData {
+                library: PrintableLibrary {
+                    name: registered_dep.borrow().name(),
+                    ty: registered_dep.borrow().library_type().ok_or_else(|| {
+                        TargetError::DependencyNotALibrary(registered_dep.borrow().name())
+                    })?,
+                },
+                manifest: borrowed_dep.manifest.clone(),
+                include_directory: registered_dep.borrow().include_directory.clone(),
+            });
+            let dependency = Dependency {
+                source: dependency_source,
+            };
+            Ok(dependency)
+        } else {
+            log::debug!("No registered dependency found. Creating dependency build target.");
+            let manifest_path = dependency_source_data.path.join(YAMBS_MANIFEST_NAME);
+            let manifest = parser::parse(&manifest_path).map_err(TargetError::Parse)?;
+            let dep_target = manifest
+                .data
+                .targets
+                .iter()
+                .find_map(|dep| {
+                    if let Some(lib) = dep.library() {
+                        if lib.name == dependency_name {
+                            return Some(dep);
+                        }
+                    }
+                    None
+                })
+                .ok_or_else(|| TargetError::NoLibraryWithName(dependency_name.to_string()))?;
+            let target = BuildTarget::target_node_from_source(
+                &manifest, dep_target, registry, toolchain, build_type, resolution,
+            )?;
+            let borrowed_target = target.borrow();
+            self.verify_dependency_version(
+                dependency_name,
+                dependency_source_data.version.as_ref(),
+                borrowed_target
+                    .project
+                    .version
+                    .as_ref()
+                    .map(|version| version.to_string()),
+            )?;
+            let dependency_source = DependencySource::FromSource(DependencySourceData {
+                library: PrintableLibrary {
+                    name: target.borrow().name(),
+                    ty: target.borrow().library_type().ok_or_else(|| {
+                        TargetError::DependencyNotALibrary(target.borrow().name())
+                    })?,
+                },
+                manifest: borrowed_target.manifest.clone(),
+                include_directory: target.borrow().include_directory.clone(),
+            });
+            Ok(Dependency {
+                source: dependency_source,
+            })
+        }
+    }
+
+    fn verify_dependency_version(
+        &self,
+        dependency: &str,
+        requirement: Option<&semver::VersionReq>,
+        version: Option<String>,
+    ) -> Result<(), TargetError> {
+        let requirement = match requirement {
+            Some(requirement) => requirement,
+            None => return Ok(()),
+        };
+        let version = version.ok_or_else(|| TargetError::MissingDependencyVersion {
+            target: self.name(),
+            dependency: dependency.to_string(),
+            requirement: requirement.clone(),
+        })?;
+        let satisfied = pkg_config::parse_version(&version)
+            .map(|parsed_version| requirement.matches(&parsed_version))
+            .unwrap_or(false);
+        if satisfied {
+            Ok(())
+        } else {
+            Err(TargetError::DependencyVersionMismatch {
+                target: self.name(),
+                dependency: dependency.to_string(),
+                requirement: requirement.clone(),
+                version,
+            })
         }
-        Ok(())
     }
 
     fn add_target(&mut self, dependency: Dependency) {
@@ -375,6 +690,76 @@ impl BuildTarget {
     }
 }
 
+/// Targets for tests, registered without sources or dependencies.
+#[cfg(test)]
+impl BuildTarget {
+    pub fn library_fixture(directory: &str, name: &str, ty: LibraryType) -> Self {
+        Self::fixture(
+            directory,
+            TargetType::Library(PrintableLibrary {
+                name: name.to_string(),
+                ty,
+            }),
+        )
+    }
+
+    pub fn executable_fixture(directory: &str, name: &str) -> Self {
+        Self::fixture(
+            directory,
+            TargetType::Executable(PrintableExecutable(name.to_string())),
+        )
+    }
+
+    fn fixture(directory: &str, target_type: TargetType) -> Self {
+        Self {
+            state: TargetState::Registered,
+            project: Project::default(),
+            target_type,
+            include_directory: IncludeDirectory {
+                include_type: IncludeType::Include,
+                path: std::path::PathBuf::from(directory).join("include"),
+            },
+            compiler_flags: CompilerFlags::new(),
+            manifest: manifest::Manifest {
+                directory: std::path::PathBuf::from(directory),
+                modification_time: std::time::SystemTime::UNIX_EPOCH,
+            },
+            dependencies: Vec::new(),
+            source_files: SourceFiles::from_paths(&[]).unwrap(),
+            defines: Vec::new(),
+            pkg_config_export: None,
+        }
+    }
+
+    pub fn with_project(mut self, name: &str) -> Self {
+        self.project.name = Some(name.to_string());
+        self
+    }
+
+    pub fn with_dependency(mut self, source: DependencySource) -> Self {
+        self.add_target(Dependency { source });
+        self
+    }
+
+    /// Adds `library` as a dependency built from source.
+    pub fn depend_on(&mut self, library: &BuildTarget) {
+        self.add_target(Dependency {
+            source: DependencySource::FromSource(DependencySourceData {
+                manifest: library.manifest.clone(),
+                library: PrintableLibrary {
+                    name: library.name(),
+                    ty: library.library_type().expect("Dependency is not a library"),
+                },
+                include_directory: library.include_directory.clone(),
+            }),
+        });
+    }
+
+    pub fn into_node(self) -> TargetNode {
+        TargetNode::new(self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct TargetNode(Rc<RefCell<BuildTarget>>);
 
@@ -500,6 +885,15 @@ pub enum LibraryType {
     Dynamic,
 }
 
+impl fmt::Display for LibraryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Static => write!(f, "static"),
+            Self::Dynamic => write!(f, "shared"),
+        }
+    }
+}
+
 impl LibraryType {
     pub fn from(lib_type: &types::LibraryType) -> Self {
         match lib_type {
@@ -524,6 +918,41 @@ impl TargetState {
     }
 }
 
+/// Checks out the commit locked for the git dependency, or the commit its reference currently
+/// points to, and records the commit in the lockfile.
+fn checkout_git_dependency(
+    dependency_name: &str,
+    git_data: &types::GitData,
+    lockfile: &mut Lockfile,
+) -> Result<types::SourceData, TargetError> {
+    let git_error = |err| TargetError::Git(dependency_name.to_string(), err);
+    let reference = GitReference::from_data(git_data).map_err(git_error)?;
+    let cache = GitCache::from_home().map_err(git_error)?;
+    let commit = match lockfile.locked_git_commit(dependency_name, &git_data.git, &reference) {
+        Some(commit) => {
+            log::debug!("Using commit {} of {} from lockfile", commit, git_data.git);
+            commit.to_string()
+        }
+        None => cache
+            .resolve(&git_data.git, &reference)
+            .map_err(git_error)?,
+    };
+    let checkout = cache.checkout(&git_data.git, &commit).map_err(git_error)?;
+    lockfile.record_git(LockedGitDependency::new(
+        dependency_name,
+        &git_data.git,
+        &reference,
+        &commit,
+    ));
+    Ok(types::SourceData {
+        path: checkout
+            .canonicalize()
+            .map_err(errors::FsError::Canonicalize)?,
+        origin: git_data.origin.clone(),
+        version: git_data.version.clone(),
+    })
+}
+
 #[non_exhaustive]
 #[derive(Debug, thiserror::Error)]
 pub enum TargetError {
@@ -533,8 +962,8 @@ pub enum TargetError {
     Parse(#[source] parser::ParseTomlError),
     #[error("Failed to create cache of dependencies")]
     FailedToCache(#[source] errors::CacheError),
-    #[error("Dependency circulation! {0:?} depends on {1:?}, which depends on itself")]
-    Circulation(std::path::PathBuf, std::path::PathBuf),
+    #[error("Dependency circulation! {}", .0.join(" -> "))]
+    Circulation(Vec<String>),
     #[error("Error occured classifying associated file")]
     AssociatedFile(#[source] associated_files::AssociatedFileError),
     #[error("Could not find any library with name {0}")]
@@ -543,8 +972,247 @@ pub enum TargetError {
     DependencyNotALibrary(String),
     #[error("Error occured when parsing include directories")]
     IncludeDirectories(#[source] include_directories::IncludeDirectoriesError),
-    #[error("Could not find any instance of pkg-config! Unable to find pkg-config dependencies.")]
-    NoPkgConfigInstance,
-    #[error("Could not find any pkg-config package with name {0}")]
+    #[error("Target \"{target}\" requires {dependency} {requirement}, but found version {version} of {dependency}")]
+    DependencyVersionMismatch {
+        target: String,
+        dependency: String,
+        requirement: semver::VersionReq,
+        version: String,
+    },
+    #[error("Target \"{target}\" requires {dependency} {requirement}, but {dependency} does not state any version")]
+    MissingDependencyVersion {
+        target: String,
+        dependency: String,
+        requirement: semver::VersionReq,
+    },
+    #[error("Failed to override dependency {0}")]
+    Override(String, #[source] OverrideError),
+    #[error("Failed to record dependency in lockfile")]
+    Lockfile(#[source] LockfileError),
+    #[error("Failed to fetch git dependency {0}")]
+    Git(String, #[source] GitError),
+    #[error("Failed to resolve conan dependency {0}")]
+    Conan(String, #[source] ConanError),
+    #[error("Failed to resolve vcpkg dependency {0}")]
+    Vcpkg(String, #[source] VcpkgError),
+    #[error("Failed to resolve system dependency {0}")]
+    System(String, #[source] SystemError),
+    #[error("Failed to resolve pkg-config package {0}")]
     CouldNotFindPkgConfigPackage(String, #[source] PkgConfigError),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// A toolchain of a compiler that only answers `--version` and `-dumpmachine`.
+    fn stub_toolchain(directory: &Path) -> Rc<RefCell<NormalizedToolchain>> {
+        let compiler = directory.join("stub-g++");
+        std::fs::write(
+            &compiler,
+            "#!/bin/sh\n\
+             case \"$*\" in\n\
+             *-dumpmachine*) echo x86_64-linux-gnu ;;\n\
+             *) echo 'g++ (GCC) 13.2.0' ;;\n\
+             esac\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&compiler, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let toolchain_file = directory.join("toolchain.toml");
+        std::fs::write(
+            &toolchain_file,
+            format!(
+                "[CXX]\ncompiler = {0:?}\n\n[CC]\ncompiler = {0:?}\n\n[common]\narchiver = {0:?}\n",
+                compiler
+            ),
+        )
+        .unwrap();
+        Rc::new(RefCell::new(
+            NormalizedToolchain::from_file(&toolchain_file).unwrap(),
+        ))
+    }
+
+    fn write_library(directory: &Path, name: &str, dependency: &str) {
+        std::fs::create_dir_all(directory.join(name)).unwrap();
+        std::fs::write(directory.join(name).join(format!("{}.cpp", name)), "").unwrap();
+        std::fs::write(
+            directory.join(name).join(YAMBS_MANIFEST_NAME),
+            format!(
+                "[library.{0}]\nsources = [\"{0}.cpp\"]\n\n[library.{0}.dependencies]\n{1} = {{ path = {2:?} }}\n",
+                name,
+                dependency,
+                directory.join(dependency)
+            ),
+        )
+        .unwrap();
+    }
+
+    /// Resolves the first target of the manifest in `directory`, with its dependencies.
+    fn resolve(directory: &Path) -> (manifest::ParsedManifest, Result<TargetNode, TargetError>) {
+        let toolchain = stub_toolchain(directory);
+        // Manifests are parsed with the directories of the command line.
+        crate::YAMBS_BUILD_DIR_VAR.get_or_init(|| crate::cli::BuildDirectory::from(directory));
+        crate::YAMBS_MANIFEST_DIR.get_or_init(|| directory.to_str().unwrap().parse().unwrap());
+        crate::YAMBS_BUILD_TYPE.get_or_init(|| BuildType::Debug);
+        let manifest = parser::parse(&directory.join(YAMBS_MANIFEST_NAME)).unwrap();
+        let mut lockfile = Lockfile::from_manifest_dir(directory, &BuildType::Debug).unwrap();
+
+        let result = BuildTarget::target_node_from_source(
+            &manifest,
+            &manifest.data.targets[0],
+            &mut target_registry::TargetRegistry::new(),
+            &toolchain,
+            &BuildType::Debug,
+            &mut Resolution {
+                lockfile: &mut lockfile,
+                overrides: &mut Overrides::new(),
+            },
+        );
+        (manifest, result)
+    }
+
+    /// Writes an application in `directory` with a single dependency, given as its TOML table.
+    fn write_application(directory: &Path, dependency: &str) {
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(directory.join("main.cpp"), "").unwrap();
+        std::fs::write(
+            directory.join(YAMBS_MANIFEST_NAME),
+            format!(
+                "[executable.app]\nsources = [\"main.cpp\"]\n\n[executable.app.dependencies]\n{}\n",
+                dependency
+            ),
+        )
+        .unwrap();
+    }
+
+    /// Writes the library `dep` in `directory`, with the project version `version` if any.
+    fn write_versioned_library(directory: &Path, version: Option<&str>) {
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(directory.join("dep.cpp"), "").unwrap();
+        let project_config = version
+            .map(|version| format!("[project_config]\nversion = {:?}\n\n", version))
+            .unwrap_or_default();
+        std::fs::write(
+            directory.join(YAMBS_MANIFEST_NAME),
+            format!("{}[library.dep]\nsources = [\"dep.cpp\"]\n", project_config),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn libraries_depending_on_each_other_are_a_circulation() {
+        let dir = tempdir::TempDir::new("circulation").unwrap();
+        write_library(dir.path(), "a", "b");
+        write_library(dir.path(), "b", "a");
+
+        let (manifest, result) = resolve(&dir.path().join("a"));
+        match result {
+            Err(TargetError::Circulation(cycle)) => assert_eq!(
+                cycle,
+                vec![
+                    format!("a@{}", manifest.manifest.directory.display()),
+                    format!(
+                        "b@{}",
+                        manifest.manifest.directory.with_file_name("b").display()
+                    ),
+                    format!("a@{}", manifest.manifest.directory.display()),
+                ]
+            ),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn source_dependency_must_satisfy_version_requirement() {
+        let dir = tempdir::TempDir::new("source_version").unwrap();
+        let dependency = format!(
+            "dep = {{ path = {:?}, version = \"^2\" }}",
+            dir.path().join("dep")
+        );
+        write_application(&dir.path().join("app"), &dependency);
+
+        write_versioned_library(&dir.path().join("dep"), Some("2.3.0"));
+        assert!(resolve(&dir.path().join("app")).1.is_ok());
+
+        write_versioned_library(&dir.path().join("dep"), Some("1.2.0"));
+        match resolve(&dir.path().join("app")).1 {
+            Err(TargetError::DependencyVersionMismatch {
+                target,
+                dependency,
+                requirement,
+                version,
+            }) => {
+                assert_eq!(target, "app");
+                assert_eq!(dependency, "dep");
+                assert_eq!(requirement.to_string(), "^2");
+                assert_eq!(version, "1.2.0");
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        write_versioned_library(&dir.path().join("dep"), None);
+        match resolve(&dir.path().join("app")).1 {
+            Err(TargetError::MissingDependencyVersion {
+                target, dependency, ..
+            }) => {
+                assert_eq!(target, "app");
+                assert_eq!(dependency, "dep");
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn pkg_config_dependency_must_satisfy_version_requirement() {
+        let dir = tempdir::TempDir::new("pkg_config_version").unwrap();
+        let pkg_config_directory = dir.path().join("pkgconfig");
+        std::fs::create_dir_all(&pkg_config_directory).unwrap();
+        let dependency = format!(
+            "foo = {{ pkg_config_search_dir = {:?}, version = \">=3.1\" }}",
+            pkg_config_directory
+        );
+        write_application(&dir.path().join("app"), &dependency);
+
+        std::fs::write(
+            pkg_config_directory.join("foo.pc"),
+            "Name: foo\nVersion: 3.2",
+        )
+        .unwrap();
+        assert!(resolve(&dir.path().join("app")).1.is_ok());
+
+        std::fs::write(
+            pkg_config_directory.join("foo.pc"),
+            "Name: foo\nVersion: 3.0",
+        )
+        .unwrap();
+        match resolve(&dir.path().join("app")).1 {
+            Err(TargetError::DependencyVersionMismatch {
+                dependency,
+                version,
+                ..
+            }) => {
+                assert_eq!(dependency, "foo");
+                assert_eq!(version, "3.0");
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        std::fs::write(pkg_config_directory.join("foo.pc"), "Name: foo").unwrap();
+        match resolve(&dir.path().join("app")).1 {
+            Err(TargetError::MissingDependencyVersion {
+                dependency,
+                requirement,
+                ..
+            }) => {
+                assert_eq!(dependency, "foo");
+                assert_eq!(requirement.to_string(), ">=3.1");
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
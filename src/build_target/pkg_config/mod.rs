@@ -0,0 +1,1041 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::build_target::include_directories::{IncludeDirectories, IncludeDirectory, IncludeType};
+use crate::build_target::{
+    LibraryType, PrintableLibrary, SHARED_LIBRARY_FILE_EXTENSION, STATIC_LIBRARY_FILE_EXTENSION,
+};
+use crate::flags::CXXFlags;
+use crate::{find_program, FindProgramOptions};
+
+mod pc_file;
+
+use pc_file::{split_flags, PackageRequirement};
+pub use pc_file::{PcFile, PcFileError};
+
+#[derive(Debug, Error)]
+pub enum PkgConfigError {
+    #[error("Failed to run pkg-config")]
+    FailedToRunPkgConfig(#[source] std::io::Error),
+    #[error("pkg-config failed with the following error:\n{0}")]
+    PkgConfigFailedWithError(String),
+    #[error("Could not find {0}.pc in any of the pkg-config search directories: {1:?}")]
+    PackageNotFound(String, Vec<PathBuf>),
+    #[error("Failed to parse pkg-config file")]
+    PcFile(#[source] PcFileError),
+    #[error("Failed to locate library {0}")]
+    CouldNotLocateLibrary(String),
+    #[error("Library {0} is not available as a {1} library in any of {2:?}")]
+    LibraryKindNotAvailable(String, LibraryType, Vec<PathBuf>),
+    #[error("{0} requires {1}, but version {2} was found")]
+    RequiredVersionMismatch(String, String, String),
+}
+
+/// Compiler and linker flags of a package and everything it requires.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PkgConfigFlags {
+    /// The version of the package itself, as given in its `.pc` file.
+    pub version: Option<String>,
+    pub cflags: Vec<String>,
+    pub libs: Vec<String>,
+    /// The `.pc` files the flags were read from. Unknown when queried from `pkg-config`.
+    pub pc_files: Vec<PathBuf>,
+}
+
+/// Looks up pkg-config packages.
+///
+/// `.pc` files are read and resolved by yambs itself, searching `PKG_CONFIG_PATH`, the
+/// directories added with [`PkgConfig::add_search_path`] and finally `PKG_CONFIG_LIBDIR` or the
+/// default system directories. `PKG_CONFIG_SYSROOT_DIR` is honoured. If a package can not be
+/// found and a `pkg-config` executable is available, it is used as a fallback.
+#[derive(PartialEq, Eq, Debug)]
+pub struct PkgConfig {
+    binary: Option<PathBuf>,
+    search_paths: Vec<PathBuf>,
+    library_paths: Vec<PathBuf>,
+    /// Whether `library_paths` were given by `PKG_CONFIG_LIBDIR`, which a sysroot does not
+    /// override.
+    library_paths_from_environment: bool,
+    sysroot: Option<PathBuf>,
+    multiarch: String,
+}
+
+impl PkgConfig {
+    pub fn new() -> Self {
+        let mut search_options = FindProgramOptions::new();
+        search_options.with_path_env();
+        let binary = find_program(Path::new("pkg-config"), search_options);
+        if binary.is_none() {
+            log::debug!("No pkg-config executable found. Only the built-in .pc parser is used.");
+        }
+        Self::with_binary(binary)
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        Self::with_binary(Some(path.to_path_buf()))
+    }
+
+    fn with_binary(binary: Option<PathBuf>) -> Self {
+        let search_paths = std::env::var_os("PKG_CONFIG_PATH")
+            .map(|paths| std::env::split_paths(&paths).collect::<Vec<PathBuf>>())
+            .unwrap_or_default();
+        let library_paths_from_environment = std::env::var_os("PKG_CONFIG_LIBDIR");
+        let library_paths = match library_paths_from_environment {
+            Some(ref paths) => std::env::split_paths(paths).collect::<Vec<PathBuf>>(),
+            None => default_library_paths(&multiarch_triple()),
+        };
+        Self {
+            binary,
+            search_paths,
+            library_paths,
+            library_paths_from_environment: library_paths_from_environment.is_some(),
+            sysroot: std::env::var_os("PKG_CONFIG_SYSROOT_DIR").map(PathBuf::from),
+            multiarch: multiarch_triple(),
+        }
+    }
+
+    /// Looks up packages only in the directories added with [`PkgConfig::add_search_path`],
+    /// regardless of the environment and the packages installed on the system.
+    #[cfg(test)]
+    pub fn isolated() -> Self {
+        Self {
+            binary: None,
+            search_paths: vec![],
+            library_paths: vec![],
+            library_paths_from_environment: false,
+            sysroot: None,
+            multiarch: multiarch_triple(),
+        }
+    }
+
+    /// Looks up packages in `sysroot`, built for the target `target`, when cross compiling.
+    /// Unless `PKG_CONFIG_LIBDIR` is set, only the directories of the sysroot are searched. The
+    /// `pkg-config` executable is run with `PKG_CONFIG_SYSROOT_DIR` and `PKG_CONFIG_LIBDIR` set
+    /// accordingly, while the environment of yambs itself is left as it is.
+    pub fn set_sysroot(&mut self, sysroot: &Path, target: Option<&str>) {
+        if let Some(target) = target {
+            self.multiarch = target.to_string();
+        }
+        if !self.library_paths_from_environment {
+            self.library_paths = default_library_paths(&self.multiarch)
+                .iter()
+                .map(|path| sysroot.join(path.strip_prefix("/").unwrap_or(path)))
+                .collect();
+        }
+        self.sysroot = Some(sysroot.to_path_buf());
+    }
+
+    pub fn add_search_path(&mut self, path: &Path) {
+        if !self.search_paths.iter().any(|p| p == path) {
+            self.search_paths.push(path.to_path_buf());
+        }
+    }
+
+    /// Finds the package `target`. With `link` set, only that kind of library is accepted for the
+    /// libraries of the package, and static linking includes the private dependencies of the
+    /// package, like `pkg-config --static`.
+    pub fn find_target(
+        &self,
+        target: &str,
+        variables: &BTreeMap<String, String>,
+        link: Option<&LibraryType>,
+    ) -> Result<PkgConfigTarget, PkgConfigError> {
+        let static_linking = link == Some(&LibraryType::Static);
+        let flags = match self.resolve(target, variables, static_linking) {
+            Err(PkgConfigError::PackageNotFound(_, _)) if self.binary.is_some() => {
+                log::debug!(
+                    "Did not find {}.pc in the search directories. Falling back to pkg-config.",
+                    target
+                );
+                self.query(target, variables, static_linking)?
+            }
+            result => result?,
+        };
+
+        let mut cxx_flags = Vec::new();
+        let mut include_directories = IncludeDirectories::new();
+        for flag in &flags.cflags {
+            match IncludeDirectory::from_str(flag) {
+                Some(mut include_directory) => {
+                    // Although pkg-config tells what kind of include type is "offered", we
+                    // override it to be System include instead. We don't want warnings that
+                    // exist in third party code to propagate to ours.
+                    include_directory.include_type = IncludeType::System;
+                    include_directories.add(include_directory);
+                }
+                None => cxx_flags.push(flag.as_str()),
+            }
+        }
+
+        Ok(PkgConfigTarget {
+            target: target.to_string(),
+            version: flags.version,
+            include_directories,
+            cxx_flags: CXXFlags::new(&cxx_flags),
+            method: determine_provide_method(
+                target,
+                &flags.libs,
+                link,
+                &self.system_library_paths(),
+            )?,
+            input_files: flags.pc_files,
+            requirement: None,
+        })
+    }
+
+    /// Resolves the flags of a package from its `.pc` file and the files of the packages it
+    /// requires.
+    ///
+    /// `Cflags` are collected from both `Requires` and `Requires.private`. `Cflags.private`,
+    /// `Libs.private` and the libraries of `Requires.private` are only included when linking
+    /// statically. Packages are
+    /// ordered so that every package comes before the packages it requires, which is the order
+    /// the linker needs.
+    pub fn resolve(
+        &self,
+        target: &str,
+        variables: &BTreeMap<String, String>,
+        static_linking: bool,
+    ) -> Result<PkgConfigFlags, PkgConfigError> {
+        let mut pc_files = BTreeMap::new();
+
+        let mut cflags = Vec::new();
+        for package in self.sort_packages(target, variables, true, &mut pc_files)? {
+            let pc_file = &pc_files[&package];
+            cflags.extend_from_slice(&pc_file.cflags);
+            if static_linking {
+                cflags.extend_from_slice(&pc_file.cflags_private);
+            }
+        }
+
+        let mut libs = Vec::new();
+        for package in self.sort_packages(target, variables, static_linking, &mut pc_files)? {
+            let pc_file = &pc_files[&package];
+            libs.extend_from_slice(&pc_file.libs);
+            if static_linking {
+                libs.extend_from_slice(&pc_file.libs_private);
+            }
+        }
+
+        // Like pkg-config, library directories go ahead of the libraries and system directories
+        // are left to the compiler. Passing them explicitly changes the search order of the
+        // compiler, which breaks headers such as the C++ standard library's `#include_next`.
+        let (link_dirs, libs): (Vec<String>, Vec<String>) =
+            libs.into_iter().partition(|flag| flag.starts_with("-L"));
+        let mut cflags = deduplicate_keep_first(cflags);
+        let mut link_dirs = deduplicate_keep_first(link_dirs);
+        if std::env::var_os("PKG_CONFIG_ALLOW_SYSTEM_CFLAGS").is_none() {
+            cflags.retain(|flag| !self.is_system_directory_flag(flag, "-I", SYSTEM_INCLUDE_PATHS));
+        }
+        if std::env::var_os("PKG_CONFIG_ALLOW_SYSTEM_LIBS").is_none() {
+            link_dirs
+                .retain(|flag| !self.is_system_directory_flag(flag, "-L", SYSTEM_LIBRARY_PATHS));
+        }
+        link_dirs.extend(deduplicate_keep_last(libs));
+
+        Ok(PkgConfigFlags {
+            version: pc_files[target].version.clone(),
+            cflags,
+            libs: link_dirs,
+            pc_files: pc_files
+                .values()
+                .map(|pc_file| pc_file.path.clone())
+                .collect(),
+        })
+    }
+
+    // Returns `target` and the packages it requires, each package ahead of its requirements.
+    fn sort_packages(
+        &self,
+        target: &str,
+        variables: &BTreeMap<String, String>,
+        include_private: bool,
+        pc_files: &mut BTreeMap<String, PcFile>,
+    ) -> Result<Vec<String>, PkgConfigError> {
+        let mut visited = Vec::new();
+        let mut sorted = Vec::new();
+        self.visit(
+            target,
+            variables,
+            include_private,
+            pc_files,
+            &mut visited,
+            &mut sorted,
+        )?;
+        sorted.reverse();
+        Ok(sorted)
+    }
+
+    fn visit(
+        &self,
+        package: &str,
+        variables: &BTreeMap<String, String>,
+        include_private: bool,
+        pc_files: &mut BTreeMap<String, PcFile>,
+        visited: &mut Vec<String>,
+        sorted: &mut Vec<String>,
+    ) -> Result<(), PkgConfigError> {
+        if visited.iter().any(|p| p == package) {
+            return Ok(());
+        }
+        visited.push(package.to_string());
+
+        if !pc_files.contains_key(package) {
+            let pc_file = self.load(package, variables)?;
+            pc_files.insert(package.to_string(), pc_file);
+        }
+        let pc_file = &pc_files[package];
+        let mut required_packages = pc_file.requires.clone();
+        if include_private {
+            required_packages.extend_from_slice(&pc_file.requires_private);
+        }
+        // Requirements are visited in reverse, so that they keep their order once the sorted
+        // packages are reversed.
+        for required_package in required_packages.iter().rev() {
+            self.visit(
+                &required_package.name,
+                variables,
+                include_private,
+                pc_files,
+                visited,
+                sorted,
+            )?;
+            verify_required_version(package, required_package, &pc_files[&required_package.name])?;
+        }
+        sorted.push(package.to_string());
+        Ok(())
+    }
+
+    fn system_library_paths(&self) -> Vec<PathBuf> {
+        SYSTEM_LIBRARY_PATHS
+            .iter()
+            .map(|system_path| {
+                let system_path = system_path.replace("<multiarch>", &self.multiarch);
+                match self.sysroot {
+                    Some(ref sysroot) => sysroot.join(system_path.trim_start_matches('/')),
+                    None => PathBuf::from(system_path),
+                }
+            })
+            .collect()
+    }
+
+    fn is_system_directory_flag(&self, flag: &str, prefix: &str, system_paths: &[&str]) -> bool {
+        let path = match flag.strip_prefix(prefix) {
+            Some(path) => Path::new(path),
+            None => return false,
+        };
+        let mut multiarch_paths = system_paths
+            .iter()
+            .map(|system_path| system_path.replace("<multiarch>", &self.multiarch));
+        multiarch_paths.any(|system_path| {
+            let sysroot_path = self
+                .sysroot
+                .as_ref()
+                .map(|sysroot| sysroot.join(system_path.trim_start_matches('/')));
+            path == Path::new(&system_path) || Some(path.to_path_buf()) == sysroot_path
+        })
+    }
+
+    fn load(
+        &self,
+        package: &str,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<PcFile, PkgConfigError> {
+        let search_paths = self
+            .search_paths
+            .iter()
+            .chain(self.library_paths.iter())
+            .cloned()
+            .collect::<Vec<PathBuf>>();
+        let path = search_paths
+            .iter()
+            .map(|search_path| search_path.join(format!("{}.pc", package)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| PkgConfigError::PackageNotFound(package.to_string(), search_paths))?;
+        log::debug!("Reading pkg-config file {}", path.display());
+        PcFile::from_file(&path, variables, self.sysroot.as_deref()).map_err(PkgConfigError::PcFile)
+    }
+
+    fn query(
+        &self,
+        target: &str,
+        variables: &BTreeMap<String, String>,
+        static_linking: bool,
+    ) -> Result<PkgConfigFlags, PkgConfigError> {
+        let mut args = variables
+            .iter()
+            .map(|(variable, value)| format!("--define-variable={}={}", variable, value))
+            .collect::<Vec<String>>();
+        if static_linking {
+            args.push("--static".to_string());
+        }
+        args.push(target.to_string());
+
+        let split = |output: String| {
+            split_flags(Path::new(target), &output).map_err(PkgConfigError::PcFile)
+        };
+        let version = self.run(&args, "--modversion")?.trim().to_string();
+        let cflags = split(self.run(&args, "--cflags")?)?;
+        let libs = split(self.run(&args, "--libs")?)?;
+        Ok(PkgConfigFlags {
+            version: Some(version).filter(|version| !version.is_empty()),
+            cflags,
+            libs,
+            pc_files: Vec::new(),
+        })
+    }
+
+    fn run(&self, args: &[String], query: &str) -> Result<String, PkgConfigError> {
+        let binary = self
+            .binary
+            .as_ref()
+            .expect("pkg-config is only run when an executable was found");
+        let mut command = Command::new(binary);
+        if let Ok(search_paths) = std::env::join_paths(&self.search_paths) {
+            command.env("PKG_CONFIG_PATH", search_paths);
+        }
+        if let Some(ref sysroot) = self.sysroot {
+            command.env("PKG_CONFIG_SYSROOT_DIR", sysroot);
+            if let Ok(library_paths) = std::env::join_paths(&self.library_paths) {
+                command.env("PKG_CONFIG_LIBDIR", library_paths);
+            }
+        }
+        let output = command
+            .arg(query)
+            .args(args)
+            .output()
+            .map_err(PkgConfigError::FailedToRunPkgConfig)?;
+        let exit_status = output.status;
+        if exit_status.success() {
+            let stdout = output.stdout;
+            let stdout = String::from_utf8(stdout).unwrap();
+            log::debug!(
+                "Output from 'pkg-config {} {}': {}",
+                query,
+                args.join(" "),
+                stdout
+            );
+            Ok(stdout)
+        } else {
+            let stderr = output.stderr;
+            let stderr = String::from_utf8(stderr).unwrap();
+            Err(PkgConfigError::PkgConfigFailedWithError(stderr))
+        }
+    }
+}
+
+impl Default for PkgConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const SYSTEM_INCLUDE_PATHS: &[&str] = &["/usr/include"];
+const SYSTEM_LIBRARY_PATHS: &[&str] = &[
+    "/lib",
+    "/lib64",
+    "/lib/<multiarch>",
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/lib/<multiarch>",
+];
+
+fn multiarch_triple() -> String {
+    format!("{}-linux-gnu", std::env::consts::ARCH)
+}
+
+// Checks the version of a required package against the constraint of the requiring `.pc` file,
+// like `Requires: zlib >= 1.2`.
+fn verify_required_version(
+    package: &str,
+    requirement: &PackageRequirement,
+    required_pc_file: &PcFile,
+) -> Result<(), PkgConfigError> {
+    let (operator, required_version) = match requirement.constraint {
+        Some((ref operator, ref required_version)) => (operator, required_version),
+        None => return Ok(()),
+    };
+    let version = required_pc_file.version.as_deref();
+    let satisfied = match (
+        version.and_then(parse_version),
+        parse_version(required_version),
+    ) {
+        (Some(version), Some(required_version)) => match operator.as_str() {
+            "=" => version == required_version,
+            "!=" => version != required_version,
+            "<" => version < required_version,
+            "<=" => version <= required_version,
+            ">" => version > required_version,
+            ">=" => version >= required_version,
+            _ => false,
+        },
+        _ => false,
+    };
+    if satisfied {
+        Ok(())
+    } else {
+        Err(PkgConfigError::RequiredVersionMismatch(
+            package.to_string(),
+            format!("{} {} {}", requirement.name, operator, required_version),
+            version.unwrap_or("unknown").to_string(),
+        ))
+    }
+}
+
+/// Parses the version of a pkg-config package as a semantic version.
+///
+/// Versions in `.pc` files do not have to follow semantic versioning. Versions like `1.2` or
+/// `1.1.1w` are read leniently as `1.2.0` and `1.1.1`.
+pub fn parse_version(version: &str) -> Option<semver::Version> {
+    if let Ok(version) = semver::Version::parse(version) {
+        return Some(version);
+    }
+    let mut numbers = Vec::new();
+    for component in version.split('.').take(3) {
+        let digits = component
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>();
+        match digits.parse::<u64>() {
+            Ok(number) => numbers.push(number),
+            Err(_) => break,
+        }
+        if digits.len() != component.len() {
+            break;
+        }
+    }
+    match numbers[..] {
+        [] => None,
+        [major] => Some(semver::Version::new(major, 0, 0)),
+        [major, minor] => Some(semver::Version::new(major, minor, 0)),
+        [major, minor, patch, ..] => Some(semver::Version::new(major, minor, patch)),
+    }
+}
+
+fn default_library_paths(multiarch: &str) -> Vec<PathBuf> {
+    let multiarch = format!("/usr/lib/{}/pkgconfig", multiarch);
+    [
+        "/usr/local/lib/pkgconfig",
+        "/usr/local/share/pkgconfig",
+        multiarch.as_str(),
+        "/usr/lib/pkgconfig",
+        "/usr/share/pkgconfig",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect()
+}
+
+fn deduplicate_keep_first(flags: Vec<String>) -> Vec<String> {
+    let mut deduplicated = Vec::with_capacity(flags.len());
+    for flag in flags {
+        if !deduplicated.contains(&flag) {
+            deduplicated.push(flag);
+        }
+    }
+    deduplicated
+}
+
+// Libraries are linked in order, so a library needed by several packages has to stay after the
+// last of them.
+fn deduplicate_keep_last(flags: Vec<String>) -> Vec<String> {
+    let mut deduplicated = deduplicate_keep_first(flags.into_iter().rev().collect());
+    deduplicated.reverse();
+    deduplicated
+}
+
+fn determine_provide_method(
+    target: &str,
+    libs: &[String],
+    link: Option<&LibraryType>,
+    system_library_paths: &[PathBuf],
+) -> Result<ProvideMethod, PkgConfigError> {
+    let link_libs = libs
+        .iter()
+        .filter(|flag| flag.starts_with("-l"))
+        .cloned()
+        .collect::<Vec<String>>();
+    let link_dirs = libs
+        .iter()
+        .filter(|flag| flag.starts_with("-L"))
+        .cloned()
+        .collect::<Vec<String>>();
+    let mut search_paths = link_dirs
+        .iter()
+        .map(|s| PathBuf::from(s.trim_start_matches("-L")))
+        .collect::<Vec<PathBuf>>();
+    // The linker would fall back to the system directories, so a specific kind of library has to
+    // be looked for there as well.
+    if link.is_some() {
+        search_paths.extend_from_slice(system_library_paths);
+    }
+
+    let mut library_paths = vec![];
+    for link_lib in &link_libs {
+        let lib_name = link_lib.trim_start_matches("-l");
+        let alternative_lib_name = &format!("{}d", lib_name);
+        let lib = search_paths.iter().find_map(|search_path| {
+            PkgConfigLibrary::find(lib_name, Some(alternative_lib_name), search_path, link)
+        });
+        if let Some(lib) = lib {
+            log::info!("Found library {} with pkg-config", lib.path().display());
+            library_paths.push(lib);
+        } else if let Some(kind) = link {
+            return Err(PkgConfigError::LibraryKindNotAvailable(
+                lib_name.to_string(),
+                kind.clone(),
+                search_paths,
+            ));
+        } else {
+            log::error!("Failed to find library {} in {:?}", lib_name, search_paths);
+            log::info!(
+                "Library {} will be registered through the values of pkg-config flags",
+                target
+            );
+            return Ok(ProvideMethod::PkgConfigOutput(PkgConfigLDFlags {
+                link_libs,
+                link_dirs,
+            }));
+        }
+    }
+    log::info!(
+        "Library {} is registered through concrete paths found using pkg-config.",
+        target
+    );
+    Ok(ProvideMethod::Finegrained(library_paths))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PkgConfigTarget {
+    pub target: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    pub include_directories: IncludeDirectories,
+    pub cxx_flags: CXXFlags,
+    pub method: ProvideMethod,
+    /// Files read while resolving the package, other than its libraries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub input_files: Vec<PathBuf>,
+    /// Requirement of the manifest on the version of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirement: Option<semver::VersionReq>,
+}
+
+impl PkgConfigTarget {
+    /// The libraries the package is linked with, either as paths or as linker flags.
+    pub fn libraries(&self) -> Vec<String> {
+        match self.method {
+            ProvideMethod::Finegrained(ref libraries) => libraries
+                .iter()
+                .map(|library| library.path().display().to_string())
+                .collect(),
+            ProvideMethod::PkgConfigOutput(ref ld_flags) => ld_flags
+                .link_dirs
+                .iter()
+                .chain(ld_flags.link_libs.iter())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The files the package was resolved from, including the libraries that were found.
+    pub fn resolved_from(&self) -> Vec<PathBuf> {
+        let mut files = self.input_files.clone();
+        if let ProvideMethod::Finegrained(ref libraries) = self.method {
+            files.extend(libraries.iter().map(PkgConfigLibrary::path));
+        }
+        files
+    }
+
+    /// The libraries of the package as `-L` and `-l` flags, for consumers that look them up
+    /// themselves.
+    pub fn link_arguments(&self) -> Vec<String> {
+        match self.method {
+            ProvideMethod::Finegrained(ref libraries) => {
+                let mut link_dirs = Vec::new();
+                let mut link_libs = Vec::new();
+                for library in libraries {
+                    let link_dir = format!("-L{}", library.dir.display());
+                    if !link_dirs.contains(&link_dir) {
+                        link_dirs.push(link_dir);
+                    }
+                    link_libs.push(format!("-l{}", library.link_name()));
+                }
+                link_dirs.into_iter().chain(link_libs).collect()
+            }
+            ProvideMethod::PkgConfigOutput(_) => self.libraries(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ProvideMethod {
+    Finegrained(Vec<PkgConfigLibrary>),
+    PkgConfigOutput(PkgConfigLDFlags),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PkgConfigLDFlags {
+    pub link_libs: Vec<String>,
+    pub link_dirs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PkgConfigLibrary {
+    printable: PrintableLibrary,
+    dir: PathBuf,
+}
+
+impl PkgConfigLibrary {
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(self.printable.name.clone())
+    }
+
+    /// The name the library is linked with `-l`, its file name without `lib` and extension.
+    pub fn link_name(&self) -> &str {
+        let name = self.printable.name.as_str();
+        let name = name.strip_prefix("lib").unwrap_or(name);
+        name.strip_suffix(&format!(".{}", STATIC_LIBRARY_FILE_EXTENSION))
+            .or_else(|| name.strip_suffix(&format!(".{}", SHARED_LIBRARY_FILE_EXTENSION)))
+            .unwrap_or(name)
+    }
+
+    /// Finds `library`, or `alternative_library` if `library` does not exist, in `dir` or one of
+    /// its subdirectories. Static libraries are preferred, unless `link` asks for a specific kind
+    /// of library, in which case only that kind is accepted.
+    pub fn find(
+        library: &str,
+        alternative_library: Option<&str>,
+        dir: &Path,
+        link: Option<&LibraryType>,
+    ) -> Option<Self> {
+        let mut search_options = FindProgramOptions::new();
+        search_options.search_directory(dir);
+        search_options.look_in_subdirectories(true);
+
+        let libraries = std::iter::once(library).chain(alternative_library);
+        for library in libraries {
+            let [static_lib_name, shared_lib_name] = PrintableLibrary::possible_lib_names(library);
+            let lib_names = match link {
+                Some(LibraryType::Static) => vec![static_lib_name],
+                Some(LibraryType::Dynamic) => vec![shared_lib_name],
+                None => vec![static_lib_name, shared_lib_name],
+            };
+            for lib_name in lib_names {
+                if let Some(found_lib) = find_program(Path::new(&lib_name), search_options.clone())
+                {
+                    let ty = match found_lib.extension().and_then(|e| e.to_str()) {
+                        Some(STATIC_LIBRARY_FILE_EXTENSION) => LibraryType::Static,
+                        Some(SHARED_LIBRARY_FILE_EXTENSION) => LibraryType::Dynamic,
+                        // We just assume that the library is static if there is no clear
+                        // indication on what the extension is, for now.
+                        _ => LibraryType::Static,
+                    };
+                    return Some(Self {
+                        printable: PrintableLibrary { name: lib_name, ty },
+                        dir: found_lib.parent().unwrap().to_path_buf(),
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn pkg_config_in(directory: &Path) -> PkgConfig {
+        PkgConfig {
+            search_paths: vec![directory.to_path_buf()],
+            ..PkgConfig::isolated()
+        }
+    }
+
+    fn create_pc_files(directory: &Path) {
+        std::fs::write(
+            directory.join("top.pc"),
+            "Name: top\nRequires: middle\nRequires.private: private\nLibs: -ltop\nLibs.private: -lm\nCflags: -I/top\nCflags.private: -DTOP_STATIC",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("middle.pc"),
+            "Name: middle\nRequires: bottom\nLibs: -lmiddle\nCflags: -I/middle",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("private.pc"),
+            "Name: private\nRequires: bottom\nLibs: -lprivate\nCflags: -I/private",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("bottom.pc"),
+            "Name: bottom\nRequires: top\nLibs: -lbottom\nCflags: -I/bottom",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn resolve_follows_requires_for_shared_linking() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        create_pc_files(tempdir.path());
+
+        let flags = pkg_config_in(tempdir.path())
+            .resolve("top", &BTreeMap::new(), false)
+            .unwrap();
+        assert_eq!(
+            flags.cflags,
+            vec!["-I/top", "-I/middle", "-I/private", "-I/bottom"]
+        );
+        assert_eq!(flags.libs, vec!["-ltop", "-lmiddle", "-lbottom"]);
+    }
+
+    #[test]
+    fn resolve_includes_private_libraries_for_static_linking() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        create_pc_files(tempdir.path());
+
+        let flags = pkg_config_in(tempdir.path())
+            .resolve("top", &BTreeMap::new(), true)
+            .unwrap();
+        assert_eq!(
+            flags.libs,
+            vec!["-ltop", "-lm", "-lmiddle", "-lprivate", "-lbottom"]
+        );
+        assert!(flags.cflags.contains(&"-DTOP_STATIC".to_string()));
+    }
+
+    #[test]
+    fn resolve_records_every_pc_file_read() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        create_pc_files(tempdir.path());
+
+        let target = pkg_config_in(tempdir.path())
+            .find_target("top", &BTreeMap::new(), None)
+            .unwrap();
+        assert_eq!(
+            target.resolved_from(),
+            ["bottom", "middle", "private", "top"]
+                .iter()
+                .map(|package| tempdir.path().join(format!("{}.pc", package)))
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[test]
+    fn resolve_fails_for_missing_package() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+
+        let result = pkg_config_in(tempdir.path()).resolve("missing", &BTreeMap::new(), false);
+        assert!(matches!(
+            result,
+            Err(PkgConfigError::PackageNotFound(ref package, _)) if package == "missing"
+        ));
+    }
+
+    #[test]
+    fn find_target_separates_include_directories_from_other_flags() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        std::fs::write(
+            tempdir.path().join("foo.pc"),
+            "prefix=/opt/foo\nName: foo\nLibs: -L${prefix}/lib -lfoo\nCflags: -I${prefix}/include -DFOO",
+        )
+        .unwrap();
+
+        let target = pkg_config_in(tempdir.path())
+            .find_target("foo", &BTreeMap::new(), None)
+            .unwrap();
+        assert_eq!(target.cxx_flags, CXXFlags::new(&["-DFOO"]));
+        assert_eq!(
+            target.method,
+            ProvideMethod::PkgConfigOutput(PkgConfigLDFlags {
+                link_libs: vec!["-lfoo".to_string()],
+                link_dirs: vec!["-L/opt/foo/lib".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn sysroot_replaces_library_paths_and_prefixes_flags() {
+        let sysroot = tempdir::TempDir::new("sysroot").unwrap();
+        let pc_directory = sysroot.path().join("usr/lib/aarch64-linux-gnu/pkgconfig");
+        std::fs::create_dir_all(&pc_directory).unwrap();
+        std::fs::write(
+            pc_directory.join("foo.pc"),
+            "prefix=/usr\nName: foo\nLibs: -lfoo\nCflags: -I${prefix}/include/foo",
+        )
+        .unwrap();
+
+        let mut pkg_config = PkgConfig {
+            search_paths: vec![],
+            ..pkg_config_in(sysroot.path())
+        };
+        pkg_config.set_sysroot(sysroot.path(), Some("aarch64-linux-gnu"));
+        assert!(pkg_config.library_paths.contains(&pc_directory));
+        assert!(pkg_config
+            .library_paths
+            .iter()
+            .all(|path| path.starts_with(sysroot.path())));
+        let flags = pkg_config.resolve("foo", &BTreeMap::new(), false).unwrap();
+        assert_eq!(
+            flags.cflags,
+            vec![format!(
+                "-I{}",
+                sysroot.path().join("usr/include/foo").display()
+            )]
+        );
+        assert_ne!(
+            std::env::var_os("PKG_CONFIG_SYSROOT_DIR"),
+            Some(sysroot.path().as_os_str().to_owned())
+        );
+
+        let mut pkg_config = PkgConfig {
+            library_paths: vec![PathBuf::from("/opt/target/pkgconfig")],
+            library_paths_from_environment: true,
+            ..pkg_config_in(sysroot.path())
+        };
+        pkg_config.set_sysroot(sysroot.path(), None);
+        assert_eq!(
+            pkg_config.library_paths,
+            vec![PathBuf::from("/opt/target/pkgconfig")]
+        );
+    }
+
+    #[test]
+    fn find_library_respects_requested_kind() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        std::fs::write(tempdir.path().join("libfoo.a"), "").unwrap();
+        std::fs::write(tempdir.path().join("libfoo.so"), "").unwrap();
+        std::fs::write(tempdir.path().join("libbar.so"), "").unwrap();
+
+        let static_foo =
+            PkgConfigLibrary::find("foo", None, tempdir.path(), Some(&LibraryType::Static))
+                .unwrap();
+        assert_eq!(static_foo.path(), tempdir.path().join("libfoo.a"));
+        let shared_foo =
+            PkgConfigLibrary::find("foo", None, tempdir.path(), Some(&LibraryType::Dynamic))
+                .unwrap();
+        assert_eq!(shared_foo.path(), tempdir.path().join("libfoo.so"));
+        assert!(
+            PkgConfigLibrary::find("bar", None, tempdir.path(), Some(&LibraryType::Static))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn find_target_links_private_libraries_statically() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        let lib_dir = tempdir.path().join("lib");
+        std::fs::create_dir(&lib_dir).unwrap();
+        std::fs::write(lib_dir.join("libfoo.a"), "").unwrap();
+        std::fs::write(lib_dir.join("libfoo.so"), "").unwrap();
+        std::fs::write(lib_dir.join("libbar.a"), "").unwrap();
+        std::fs::write(
+            tempdir.path().join("foo.pc"),
+            format!(
+                "Name: foo\nLibs: -L{} -lfoo\nLibs.private: -lbar",
+                lib_dir.display()
+            ),
+        )
+        .unwrap();
+
+        let target = pkg_config_in(tempdir.path())
+            .find_target("foo", &BTreeMap::new(), Some(&LibraryType::Static))
+            .unwrap();
+        let library_paths = match target.method {
+            ProvideMethod::Finegrained(ref libraries) => libraries
+                .iter()
+                .map(|library| library.path())
+                .collect::<Vec<PathBuf>>(),
+            ProvideMethod::PkgConfigOutput(_) => panic!("Expected concrete library paths"),
+        };
+        assert_eq!(
+            library_paths,
+            vec![lib_dir.join("libfoo.a"), lib_dir.join("libbar.a")]
+        );
+    }
+
+    #[test]
+    fn find_target_fails_when_requested_kind_is_unavailable() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        std::fs::write(tempdir.path().join("libfoo.so"), "").unwrap();
+        std::fs::write(
+            tempdir.path().join("foo.pc"),
+            format!("Name: foo\nLibs: -L{} -lfoo", tempdir.path().display()),
+        )
+        .unwrap();
+
+        let result = pkg_config_in(tempdir.path()).find_target(
+            "foo",
+            &BTreeMap::new(),
+            Some(&LibraryType::Static),
+        );
+        assert!(matches!(
+            result,
+            Err(PkgConfigError::LibraryKindNotAvailable(ref library, LibraryType::Static, _))
+                if library == "foo"
+        ));
+    }
+
+    #[test]
+    fn parse_version_reads_versions_leniently() {
+        assert_eq!(
+            parse_version("1.2.13"),
+            Some(semver::Version::new(1, 2, 13))
+        );
+        assert_eq!(parse_version("6.4"), Some(semver::Version::new(6, 4, 0)));
+        assert_eq!(parse_version("1.1.1w"), Some(semver::Version::new(1, 1, 1)));
+        assert_eq!(
+            parse_version("2.5.0.1"),
+            Some(semver::Version::new(2, 5, 0))
+        );
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn resolve_checks_version_constraints_of_requirements() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        std::fs::write(
+            tempdir.path().join("app.pc"),
+            "Name: app\nRequires: zlib >= 1.2.11\nLibs: -lapp",
+        )
+        .unwrap();
+        std::fs::write(
+            tempdir.path().join("zlib.pc"),
+            "Name: zlib\nVersion: 1.2.13\nLibs: -lz",
+        )
+        .unwrap();
+        let pkg_config = pkg_config_in(tempdir.path());
+        assert!(pkg_config.resolve("app", &BTreeMap::new(), false).is_ok());
+
+        std::fs::write(
+            tempdir.path().join("zlib.pc"),
+            "Name: zlib\nVersion: 1.2.8\nLibs: -lz",
+        )
+        .unwrap();
+        let result = pkg_config.resolve("app", &BTreeMap::new(), false);
+        assert!(matches!(
+            result,
+            Err(PkgConfigError::RequiredVersionMismatch(ref package, ref requirement, ref version))
+                if package == "app" && requirement == "zlib >= 1.2.11" && version == "1.2.8"
+        ));
+    }
+
+    #[test]
+    fn find_target_reads_version_of_package() {
+        let tempdir = tempdir::TempDir::new("pkg_config").unwrap();
+        std::fs::write(tempdir.path().join("foo.pc"), "Name: foo\nVersion: 3.1").unwrap();
+
+        let target = pkg_config_in(tempdir.path())
+            .find_target("foo", &BTreeMap::new(), None)
+            .unwrap();
+        assert_eq!(target.version, Some("3.1".to_string()));
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PcFileError {
+    #[error("Failed to read pkg-config file {0:?}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("{0:?}, line {1}: Expected a variable definition or a field")]
+    InvalidLine(PathBuf, usize),
+    #[error("{0:?}: Variable \"{1}\" is not defined")]
+    UndefinedVariable(PathBuf, String),
+    #[error("{0:?}: Unterminated quote in \"{1}\"")]
+    UnterminatedQuote(PathBuf, String),
+}
+
+/// A package that a `.pc` file requires, with an optional version constraint such as `>= 1.2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageRequirement {
+    pub name: String,
+    pub constraint: Option<(String, String)>,
+}
+
+/// The contents of a pkg-config `.pc` file, with all variables expanded.
+///
+/// Besides the variables defined in the file, `pcfiledir` and `pc_sysrootdir` are predefined.
+/// Variables given as overrides take precedence over definitions in the file, like
+/// `pkg-config --define-variable`. When a sysroot is given, absolute `-I` and `-L` paths are
+/// prefixed with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub requires: Vec<PackageRequirement>,
+    pub requires_private: Vec<PackageRequirement>,
+    pub cflags: Vec<String>,
+    pub cflags_private: Vec<String>,
+    pub libs: Vec<String>,
+    pub libs_private: Vec<String>,
+    pub variables: BTreeMap<String, String>,
+}
+
+impl PcFile {
+    pub fn from_file(
+        path: &Path,
+        overrides: &BTreeMap<String, String>,
+        sysroot: Option<&Path>,
+    ) -> Result<Self, PcFileError> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| PcFileError::Read(path.to_path_buf(), e))?;
+        Self::parse(path, &content, overrides, sysroot)
+    }
+
+    pub fn parse(
+        path: &Path,
+        content: &str,
+        overrides: &BTreeMap<String, String>,
+        sysroot: Option<&Path>,
+    ) -> Result<Self, PcFileError> {
+        let mut variables = BTreeMap::new();
+        variables.insert(
+            "pcfiledir".to_string(),
+            path.parent()
+                .map(|parent| parent.display().to_string())
+                .unwrap_or_default(),
+        );
+        variables.insert(
+            "pc_sysrootdir".to_string(),
+            sysroot
+                .map(|sysroot| sysroot.display().to_string())
+                .unwrap_or_else(|| "/".to_string()),
+        );
+        variables.extend(overrides.clone());
+
+        let mut pc_file = Self {
+            path: path.to_path_buf(),
+            name: String::new(),
+            description: None,
+            version: None,
+            requires: Vec::new(),
+            requires_private: Vec::new(),
+            cflags: Vec::new(),
+            cflags_private: Vec::new(),
+            libs: Vec::new(),
+            libs_private: Vec::new(),
+            variables: BTreeMap::new(),
+        };
+
+        for (line_number, line) in logical_lines(content) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let separator = line
+                .find([':', '='])
+                .ok_or_else(|| PcFileError::InvalidLine(path.to_path_buf(), line_number))?;
+            let key = line[..separator].trim();
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+            {
+                return Err(PcFileError::InvalidLine(path.to_path_buf(), line_number));
+            }
+            let value = expand(path, line[separator + 1..].trim(), &variables)?;
+
+            if line.as_bytes()[separator] == b'=' {
+                if !overrides.contains_key(key) {
+                    variables.insert(key.to_string(), value);
+                }
+                continue;
+            }
+            match key {
+                "Name" => pc_file.name = value,
+                "Description" => pc_file.description = Some(value),
+                "Version" => pc_file.version = Some(value),
+                "Requires" => pc_file.requires = parse_requirements(&value),
+                "Requires.private" => pc_file.requires_private = parse_requirements(&value),
+                "Cflags" | "CFlags" => {
+                    pc_file.cflags = apply_sysroot(split_flags(path, &value)?, sysroot)
+                }
+                "Cflags.private" | "CFlags.private" => {
+                    pc_file.cflags_private = apply_sysroot(split_flags(path, &value)?, sysroot)
+                }
+                "Libs" => pc_file.libs = apply_sysroot(split_flags(path, &value)?, sysroot),
+                "Libs.private" => {
+                    pc_file.libs_private = apply_sysroot(split_flags(path, &value)?, sysroot)
+                }
+                _ => log::debug!("{}: Ignoring field {}", path.display(), key),
+            }
+        }
+        pc_file.variables = variables;
+        Ok(pc_file)
+    }
+}
+
+// Joins lines ending with a backslash and strips comments, keeping the number of the first line
+// of each logical line for error messages.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_line_number = 1;
+
+    for (index, line) in content.lines().enumerate() {
+        if current.is_empty() {
+            current_line_number = index + 1;
+        }
+        let line = strip_comment(line);
+        match line.strip_suffix('\\') {
+            Some(continued) => current.push_str(continued),
+            None => {
+                current.push_str(&line);
+                lines.push((current_line_number, std::mem::take(&mut current)));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push((current_line_number, current));
+    }
+    lines
+}
+
+fn strip_comment(line: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'#') => {
+                stripped.push('#');
+                chars.next();
+            }
+            '#' => break,
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+fn expand(
+    path: &Path,
+    value: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, PcFileError> {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(position) = rest.find('$') {
+        expanded.push_str(&rest[..position]);
+        rest = &rest[position..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = match after.find('}') {
+                Some(end) => end,
+                None => {
+                    expanded.push_str(rest);
+                    return Ok(expanded);
+                }
+            };
+            let name = &after[..end];
+            let variable = variables.get(name).ok_or_else(|| {
+                PcFileError::UndefinedVariable(path.to_path_buf(), name.to_string())
+            })?;
+            expanded.push_str(variable);
+            rest = &after[end + 1..];
+        } else {
+            expanded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Splits a string of flags the way a shell would, honouring quotes and backslash escapes.
+pub fn split_flags(path: &Path, value: &str) -> Result<Vec<String>, PcFileError> {
+    let mut flags = Vec::new();
+    let mut current = String::new();
+    let mut in_flag = false;
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_flag {
+                    flags.push(std::mem::take(&mut current));
+                    in_flag = false;
+                }
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                in_flag = true;
+            }
+            '\'' | '"' => {
+                let quote = c;
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some('\\') if quote == '"' => {
+                            if let Some(escaped) = chars.next() {
+                                current.push(escaped);
+                            }
+                        }
+                        Some(c) => current.push(c),
+                        None => {
+                            return Err(PcFileError::UnterminatedQuote(
+                                path.to_path_buf(),
+                                value.to_string(),
+                            ))
+                        }
+                    }
+                }
+                in_flag = true;
+            }
+            _ => {
+                current.push(c);
+                in_flag = true;
+            }
+        }
+    }
+    if in_flag {
+        flags.push(current);
+    }
+    Ok(flags)
+}
+
+fn parse_requirements(value: &str) -> Vec<PackageRequirement> {
+    fn is_operator(c: char) -> bool {
+        matches!(c, '<' | '>' | '=' | '!')
+    }
+    fn is_separator(c: char) -> bool {
+        c.is_whitespace() || c == ','
+    }
+
+    let mut requirements = Vec::new();
+    let mut rest = value.trim_start_matches(is_separator);
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c| is_separator(c) || is_operator(c))
+            .unwrap_or(rest.len());
+        let name = rest[..end].to_string();
+        rest = rest[end..].trim_start_matches(char::is_whitespace);
+
+        let mut constraint = None;
+        if rest.starts_with(is_operator) {
+            let operator_end = rest.find(|c| !is_operator(c)).unwrap_or(rest.len());
+            let operator = rest[..operator_end].to_string();
+            rest = rest[operator_end..].trim_start_matches(char::is_whitespace);
+            let version_end = rest.find(is_separator).unwrap_or(rest.len());
+            constraint = Some((operator, rest[..version_end].to_string()));
+            rest = &rest[version_end..];
+        }
+        requirements.push(PackageRequirement { name, constraint });
+        rest = rest.trim_start_matches(is_separator);
+    }
+    requirements
+}
+
+fn apply_sysroot(flags: Vec<String>, sysroot: Option<&Path>) -> Vec<String> {
+    let sysroot = match sysroot {
+        Some(sysroot) => sysroot,
+        None => return flags,
+    };
+
+    let mut prefixed_flags = Vec::with_capacity(flags.len());
+    let mut flags = flags.into_iter();
+    while let Some(flag) = flags.next() {
+        // Flags given as two arguments, like "-I /usr/include", are joined so that the path
+        // can be prefixed.
+        let flag = if flag == "-I" || flag == "-L" || flag == "-isystem" {
+            match flags.next() {
+                Some(argument) => format!("{}{}", flag, argument),
+                None => flag,
+            }
+        } else {
+            flag
+        };
+        let prefix = ["-isystem", "-I", "-L"]
+            .iter()
+            .find(|prefix| flag.starts_with(**prefix));
+        match prefix {
+            Some(prefix) => {
+                let path = &flag[prefix.len()..];
+                if path.starts_with('/') && !Path::new(path).starts_with(sysroot) {
+                    prefixed_flags.push(format!("{}{}{}", prefix, sysroot.display(), path));
+                } else {
+                    prefixed_flags.push(flag);
+                }
+            }
+            None => prefixed_flags.push(flag),
+        }
+    }
+    prefixed_flags
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const ZLIB_PC: &str = indoc::indoc!(
+        r#"
+        prefix=/usr
+        exec_prefix=${prefix}
+        libdir=${exec_prefix}/lib # the library directory
+        includedir=${prefix}/include
+
+        Name: zlib
+        Description: zlib compression library
+        Version: 1.2.13
+        Requires: foo >= 1.0, bar
+        Requires.private: baz
+        Libs: -L${libdir} \
+              -lz
+        Libs.private: -lm
+        Cflags: -I${includedir} -DZ_PREFIX="zlib \#1"
+        "#
+    );
+
+    fn parse(content: &str) -> PcFile {
+        PcFile::parse(
+            Path::new("/usr/lib/pkgconfig/zlib.pc"),
+            content,
+            &BTreeMap::new(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_expands_variables_in_fields() {
+        let pc_file = parse(ZLIB_PC);
+        assert_eq!(pc_file.name, "zlib");
+        assert_eq!(pc_file.version, Some("1.2.13".to_string()));
+        assert_eq!(pc_file.libs, vec!["-L/usr/lib", "-lz"]);
+        assert_eq!(pc_file.libs_private, vec!["-lm"]);
+        assert_eq!(pc_file.cflags, vec!["-I/usr/include", "-DZ_PREFIX=zlib #1"]);
+        assert_eq!(pc_file.variables["libdir"], "/usr/lib");
+    }
+
+    #[test]
+    fn parse_reads_requirements_with_version_constraints() {
+        let pc_file = parse(ZLIB_PC);
+        assert_eq!(
+            pc_file.requires,
+            vec![
+                PackageRequirement {
+                    name: "foo".to_string(),
+                    constraint: Some((">=".to_string(), "1.0".to_string())),
+                },
+                PackageRequirement {
+                    name: "bar".to_string(),
+                    constraint: None,
+                },
+            ]
+        );
+        assert_eq!(
+            pc_file.requires_private,
+            vec![PackageRequirement {
+                name: "baz".to_string(),
+                constraint: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_requirements_without_whitespace_around_operator() {
+        assert_eq!(
+            parse_requirements("foo>=1.0 bar"),
+            vec![
+                PackageRequirement {
+                    name: "foo".to_string(),
+                    constraint: Some((">=".to_string(), "1.0".to_string())),
+                },
+                PackageRequirement {
+                    name: "bar".to_string(),
+                    constraint: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lets_overrides_take_precedence_over_file_variables() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("prefix".to_string(), "/opt/zlib".to_string());
+        let pc_file = PcFile::parse(
+            Path::new("/usr/lib/pkgconfig/zlib.pc"),
+            ZLIB_PC,
+            &overrides,
+            None,
+        )
+        .unwrap();
+        assert_eq!(pc_file.libs, vec!["-L/opt/zlib/lib", "-lz"]);
+    }
+
+    #[test]
+    fn parse_defines_pcfiledir() {
+        let pc_file = parse(indoc::indoc!(
+            "
+            prefix=${pcfiledir}/../..
+            Name: relocatable
+            Libs: -L${prefix}/lib -lrelocatable
+            "
+        ));
+        assert_eq!(
+            pc_file.libs,
+            vec!["-L/usr/lib/pkgconfig/../../lib", "-lrelocatable"]
+        );
+    }
+
+    #[test]
+    fn parse_prefixes_absolute_paths_with_sysroot() {
+        let pc_file = PcFile::parse(
+            Path::new("/sysroot/usr/lib/pkgconfig/zlib.pc"),
+            "Name: zlib\nLibs: -L /usr/lib -lz\nCflags: -I/usr/include -Irelative",
+            &BTreeMap::new(),
+            Some(Path::new("/sysroot")),
+        )
+        .unwrap();
+        assert_eq!(pc_file.libs, vec!["-L/sysroot/usr/lib", "-lz"]);
+        assert_eq!(pc_file.cflags, vec!["-I/sysroot/usr/include", "-Irelative"]);
+    }
+
+    #[test]
+    fn parse_prefixes_paths_that_only_share_a_name_with_sysroot() {
+        let pc_file = PcFile::parse(
+            Path::new("/sysroot/usr/lib/pkgconfig/zlib.pc"),
+            "Name: zlib\nCflags: -I/sysroot/usr/include -I/sysroot2/usr/include",
+            &BTreeMap::new(),
+            Some(Path::new("/sysroot")),
+        )
+        .unwrap();
+        assert_eq!(
+            pc_file.cflags,
+            vec!["-I/sysroot/usr/include", "-I/sysroot/sysroot2/usr/include"]
+        );
+    }
+
+    #[test]
+    fn parse_fails_on_undefined_variable() {
+        let result = PcFile::parse(
+            Path::new("broken.pc"),
+            "Name: broken\nLibs: -L${libdir}",
+            &BTreeMap::new(),
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(PcFileError::UndefinedVariable(_, ref variable)) if variable == "libdir"
+        ));
+    }
+
+    #[test]
+    fn split_flags_honours_quotes_and_escapes() {
+        assert_eq!(
+            split_flags(Path::new("x.pc"), r#"-DA="a b" '-DB=c d' -DC=e\ f  -lg"#).unwrap(),
+            vec!["-DA=a b", "-DB=c d", "-DC=e f", "-lg"]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use thiserror::Error;
+
+use crate::build_target::include_directories::IncludeDirectories;
+use crate::build_target::pkg_config::{PkgConfigLibrary, PkgConfigTarget, ProvideMethod};
+use crate::build_target::LibraryType;
+use crate::flags::CXXFlags;
+use crate::parser::types::SystemData;
+
+#[derive(Debug, Error)]
+pub enum SystemError {
+    #[error("Failed to run {0:?} to query its search directories")]
+    FailedToRunCompiler(PathBuf, #[source] std::io::Error),
+    #[error("{0:?} failed to report its search directories:\n{1}")]
+    CompilerFailed(PathBuf, String),
+    #[error("Could not find library {0} in the library search directories of the toolchain:\n{1}")]
+    LibraryNotFound(String, String),
+    #[error("Could not find header {0} in the include search directories of the toolchain:\n{1}")]
+    HeaderNotFound(String, String),
+}
+
+/// The directories the compiler searches for libraries and system headers by default.
+///
+/// The directories are reported by the compiler itself, so they include the directories of the
+/// sysroot the compiler is configured with, or the one given in `target_flags`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchDirectories {
+    pub library_directories: Vec<PathBuf>,
+    pub include_directories: Vec<PathBuf>,
+}
+
+impl SearchDirectories {
+    pub fn from_compiler(compiler: &Path, target_flags: &[String]) -> Result<Self, SystemError> {
+        log::debug!(
+            "Querying search directories with '{} -print-search-dirs'",
+            compiler.display()
+        );
+        let search_dirs_output =
+            run_compiler(compiler, target_flags, &["-print-search-dirs"])?.stdout;
+        log::debug!(
+            "Querying include directories with '{} -E -x c -v /dev/null'",
+            compiler.display()
+        );
+        let include_output = run_compiler(
+            compiler,
+            target_flags,
+            &["-E", "-x", "c", "-v", "/dev/null"],
+        )?
+        .stderr;
+
+        Ok(Self {
+            library_directories: existing_directories(parse_library_directories(
+                &search_dirs_output,
+            )),
+            include_directories: existing_directories(parse_include_directories(&include_output)),
+        })
+    }
+
+    fn find_library(&self, library: &str, link: Option<&LibraryType>) -> Option<PkgConfigLibrary> {
+        self.library_directories
+            .iter()
+            .find_map(|directory| PkgConfigLibrary::find(library, None, directory, link))
+    }
+
+    fn find_header(&self, header: &str) -> Option<PathBuf> {
+        self.include_directories
+            .iter()
+            .map(|directory| directory.join(header))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Looks up the libraries and headers of a system dependency in the search directories of the
+/// toolchain. The headers are only checked for existence, since the compiler finds them without
+/// any include flags.
+pub fn find_target(
+    name: &str,
+    data: &SystemData,
+    search_directories: &SearchDirectories,
+) -> Result<PkgConfigTarget, SystemError> {
+    let link = data.link.as_ref().map(LibraryType::from);
+    let mut libraries = Vec::new();
+    let mut headers = Vec::new();
+    for library in &data.system.libs {
+        let found_library = search_directories
+            .find_library(library, link.as_ref())
+            .ok_or_else(|| {
+                SystemError::LibraryNotFound(
+                    library.clone(),
+                    format_directories(&search_directories.library_directories),
+                )
+            })?;
+        log::debug!(
+            "Found library {} of {} as {}",
+            library,
+            name,
+            found_library.path().display()
+        );
+        libraries.push(found_library);
+    }
+    for header in &data.system.headers {
+        let found_header = search_directories.find_header(header).ok_or_else(|| {
+            SystemError::HeaderNotFound(
+                header.clone(),
+                format_directories(&search_directories.include_directories),
+            )
+        })?;
+        log::debug!(
+            "Found header {} of {} as {}",
+            header,
+            name,
+            found_header.display()
+        );
+        headers.push(found_header);
+    }
+
+    Ok(PkgConfigTarget {
+        target: name.to_string(),
+        version: None,
+        include_directories: IncludeDirectories::new(),
+        cxx_flags: CXXFlags::new(&[]),
+        method: ProvideMethod::Finegrained(libraries),
+        input_files: headers,
+        requirement: None,
+    })
+}
+
+struct CompilerOutput {
+    stdout: String,
+    stderr: String,
+}
+
+fn run_compiler(
+    compiler: &Path,
+    target_flags: &[String],
+    args: &[&str],
+) -> Result<CompilerOutput, SystemError> {
+    let output = Command::new(compiler)
+        .args(target_flags)
+        .args(args)
+        .output()
+        .map_err(|err| SystemError::FailedToRunCompiler(compiler.to_path_buf(), err))?;
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        return Err(SystemError::CompilerFailed(compiler.to_path_buf(), stderr));
+    }
+    Ok(CompilerOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr,
+    })
+}
+
+/// Parses the `libraries: =dir1:dir2` line of `-print-search-dirs`.
+fn parse_library_directories(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("libraries:"))
+        .map(|directories| {
+            let directories = directories.trim_start().trim_start_matches('=');
+            std::env::split_paths(directories)
+                .filter(|directory| !directory.as_os_str().is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses the `#include <...>` search list printed by the preprocessor with `-v`.
+fn parse_include_directories(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("#include <...> search starts here:"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("End of search list."))
+        // Clang marks framework directories on macOS.
+        .map(|line| line.trim().trim_end_matches(" (framework directory)"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Normalizes the directories and drops the ones that don't exist, keeping the search order.
+fn existing_directories(directories: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut existing = Vec::new();
+    for directory in directories {
+        if let Ok(directory) = directory.canonicalize() {
+            if directory.is_dir() && !existing.contains(&directory) {
+                existing.push(directory);
+            }
+        }
+    }
+    existing
+}
+
+fn format_directories(directories: &[PathBuf]) -> String {
+    directories
+        .iter()
+        .map(|directory| format!("    {}", directory.display()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::parser::types::{LibraryType as ManifestLibraryType, SystemLookup};
+
+    fn create_file(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    fn system_data(libs: &[&str], headers: &[&str]) -> SystemData {
+        SystemData {
+            system: SystemLookup {
+                libs: libs.iter().map(|lib| lib.to_string()).collect(),
+                headers: headers.iter().map(|header| header.to_string()).collect(),
+            },
+            link: None,
+        }
+    }
+
+    #[test]
+    fn library_directories_are_parsed_from_print_search_dirs() {
+        let output = "install: /usr/lib/gcc/x86_64-linux-gnu/12/\n\
+                      programs: =/usr/lib/gcc/x86_64-linux-gnu/12/\n\
+                      libraries: =/usr/lib/gcc/x86_64-linux-gnu/12/:/lib/x86_64-linux-gnu/:/usr/lib/\n";
+        assert_eq!(
+            parse_library_directories(output),
+            vec![
+                PathBuf::from("/usr/lib/gcc/x86_64-linux-gnu/12/"),
+                PathBuf::from("/lib/x86_64-linux-gnu/"),
+                PathBuf::from("/usr/lib/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn include_directories_are_parsed_from_verbose_preprocessor_output() {
+        let output = "ignoring nonexistent directory \"/usr/local/include/x86_64-linux-gnu\"\n\
+                      #include \"...\" search starts here:\n\
+                      #include <...> search starts here:\n \
+                      /usr/lib/gcc/x86_64-linux-gnu/12/include\n \
+                      /usr/include\n\
+                      End of search list.\n";
+        assert_eq!(
+            parse_include_directories(output),
+            vec![
+                PathBuf::from("/usr/lib/gcc/x86_64-linux-gnu/12/include"),
+                PathBuf::from("/usr/include"),
+            ]
+        );
+    }
+
+    #[test]
+    fn libraries_are_found_in_search_directories_in_order() {
+        let dir = tempdir::TempDir::new("system").unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        create_file(&first.join("libz.so"));
+        create_file(&second.join("libz.a"));
+        create_file(&second.join("libm.a"));
+        create_file(&dir.path().join("include").join("zlib.h"));
+        let search_directories = SearchDirectories {
+            library_directories: vec![first.clone(), second.clone()],
+            include_directories: vec![dir.path().join("include")],
+        };
+
+        let target = find_target(
+            "zlib",
+            &system_data(&["z", "m"], &["zlib.h"]),
+            &search_directories,
+        )
+        .unwrap();
+        assert_eq!(
+            target.libraries(),
+            vec![
+                first.join("libz.so").display().to_string(),
+                second.join("libm.a").display().to_string(),
+            ]
+        );
+        assert_eq!(
+            target.resolved_from(),
+            vec![
+                dir.path().join("include").join("zlib.h"),
+                first.join("libz.so"),
+                second.join("libm.a"),
+            ]
+        );
+
+        let mut data = system_data(&["z"], &[]);
+        data.link = Some(ManifestLibraryType::Static);
+        let target = find_target("zlib", &data, &search_directories).unwrap();
+        assert_eq!(
+            target.libraries(),
+            vec![second.join("libz.a").display().to_string()]
+        );
+    }
+
+    #[test]
+    fn found_libraries_are_exported_as_link_flags() {
+        let dir = tempdir::TempDir::new("system").unwrap();
+        create_file(&dir.path().join("libz.so"));
+        create_file(&dir.path().join("libm.a"));
+        let search_directories = SearchDirectories {
+            library_directories: vec![dir.path().to_path_buf()],
+            include_directories: vec![],
+        };
+
+        let target =
+            find_target("zlib", &system_data(&["z", "m"], &[]), &search_directories).unwrap();
+        assert_eq!(
+            target.link_arguments(),
+            vec![
+                format!("-L{}", dir.path().display()),
+                "-lz".to_string(),
+                "-lm".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_library_or_header_is_an_error() {
+        let dir = tempdir::TempDir::new("system").unwrap();
+        let search_directories = SearchDirectories {
+            library_directories: vec![dir.path().to_path_buf()],
+            include_directories: vec![dir.path().to_path_buf()],
+        };
+
+        let error =
+            find_target("zlib", &system_data(&["z"], &[]), &search_directories).unwrap_err();
+        assert!(matches!(error, SystemError::LibraryNotFound(ref library, _) if library == "z"));
+        assert!(error
+            .to_string()
+            .contains(&dir.path().display().to_string()));
+
+        let error =
+            find_target("zlib", &system_data(&[], &["zlib.h"]), &search_directories).unwrap_err();
+        assert!(matches!(error, SystemError::HeaderNotFound(ref header, _) if header == "zlib.h"));
+    }
+}
@@ -1,5 +1,11 @@
-use crate::build_target::{BuildTarget, TargetNode};
-// LEGG TIL TESTER
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::build_target::{BuildTarget, DependencySource, TargetNode};
+
+/// Identifies a target by the allocation it is shared through.
+type TargetKey = *const RefCell<BuildTarget>;
 
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
@@ -36,4 +42,146 @@ impl TargetRegistry {
         }
         None
     }
+    /// Searches the targets reachable from `target` for a dependency cycle. The cycle is
+    /// returned as the targets on it, with the first target repeated at the end.
+    pub fn find_cycle(&self, target: &TargetNode) -> Option<Vec<TargetNode>> {
+        self.find_cycle_from(
+            target,
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut HashSet::new(),
+        )
+    }
+
+    fn find_cycle_from(
+        &self,
+        target: &TargetNode,
+        path: &mut Vec<TargetNode>,
+        on_path: &mut HashSet<TargetKey>,
+        finished: &mut HashSet<TargetKey>,
+    ) -> Option<Vec<TargetNode>> {
+        let key = Rc::as_ptr(target);
+        if on_path.contains(&key) {
+            let position = path.iter().position(|node| Rc::ptr_eq(node, target))?;
+            let mut cycle = path[position..].to_vec();
+            cycle.push(target.clone());
+            return Some(cycle);
+        }
+        if finished.contains(&key) {
+            return None;
+        }
+
+        path.push(target.clone());
+        on_path.insert(key);
+        for dependency in self.source_dependencies(target) {
+            if let Some(cycle) = self.find_cycle_from(&dependency, path, on_path, finished) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        on_path.remove(&key);
+        finished.insert(key);
+        None
+    }
+
+    /// The registered targets `target` depends on.
+    fn source_dependencies(&self, target: &TargetNode) -> Vec<TargetNode> {
+        target
+            .borrow()
+            .dependencies
+            .iter()
+            .filter_map(|dependency| match dependency.source {
+                DependencySource::FromSource(ref source_data) => {
+                    self.get_target_from_predicate(|candidate| {
+                        candidate.manifest.directory == source_data.manifest.directory
+                            && candidate.is_library()
+                            && candidate.name() == source_data.library.name
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl TargetRegistry {
+    pub fn from_targets(targets: &[TargetNode]) -> Self {
+        Self {
+            registry: targets.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::build_target::LibraryType;
+
+    fn library(directory: &str, name: &str) -> TargetNode {
+        BuildTarget::library_fixture(directory, name, LibraryType::Static).into_node()
+    }
+
+    fn depends_on(target: &TargetNode, library: &TargetNode) {
+        target.borrow_mut().depend_on(&library.borrow());
+    }
+
+    fn directories(cycle: &[TargetNode]) -> Vec<PathBuf> {
+        cycle
+            .iter()
+            .map(|target| target.borrow().manifest.directory.clone())
+            .collect()
+    }
+
+    #[test]
+    fn finds_complete_cycle_through_several_targets() {
+        let a = library("/a", "a");
+        let b = library("/b", "b");
+        let c = library("/c", "c");
+        depends_on(&a, &b);
+        depends_on(&b, &c);
+        depends_on(&c, &a);
+        let registry = TargetRegistry::from_targets(&[a.clone(), b.clone(), c]);
+
+        let cycle = registry.find_cycle(&a).unwrap();
+        assert_eq!(
+            directories(&cycle),
+            vec![
+                PathBuf::from("/a"),
+                PathBuf::from("/b"),
+                PathBuf::from("/c"),
+                PathBuf::from("/a")
+            ]
+        );
+    }
+
+    #[test]
+    fn libraries_with_same_name_in_different_manifests_are_not_a_cycle() {
+        let app_util = library("/app", "util");
+        let vendored_util = library("/vendor/util", "util");
+        let shared = library("/shared", "shared");
+        depends_on(&app_util, &vendored_util);
+        depends_on(&app_util, &shared);
+        depends_on(&vendored_util, &shared);
+        let registry =
+            TargetRegistry::from_targets(&[app_util.clone(), vendored_util, shared.clone()]);
+
+        assert!(registry.find_cycle(&app_util).is_none());
+
+        depends_on(&shared, &app_util);
+        let cycle = registry.find_cycle(&app_util).unwrap();
+        assert_eq!(
+            directories(&cycle),
+            vec![
+                PathBuf::from("/app"),
+                PathBuf::from("/vendor/util"),
+                PathBuf::from("/shared"),
+                PathBuf::from("/app")
+            ]
+        );
+    }
 }
@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::build_target::include_directories::{IncludeDirectories, IncludeDirectory, IncludeType};
+use crate::build_target::pkg_config::{
+    PkgConfig, PkgConfigError, PkgConfigLibrary, PkgConfigTarget, ProvideMethod,
+};
+use crate::build_target::{DependencySource, LibraryType};
+use crate::cli::configurations::BuildType;
+use crate::flags::CXXFlags;
+use crate::parser::types::VcpkgData;
+
+const VCPKG_MANIFEST_NAME: &str = "vcpkg.json";
+const VCPKG_MANIFEST_INSTALL_DIRECTORY: &str = "vcpkg_installed";
+
+#[derive(Debug, Error)]
+pub enum VcpkgError {
+    #[error("Could not find a vcpkg installation. Set VCPKG_ROOT or VCPKG_INSTALLED_DIR, or use vcpkg in manifest mode.")]
+    VcpkgNotFound,
+    #[error("Triplet {0} is not installed in {1:?}")]
+    TripletNotInstalled(String, PathBuf),
+    #[error("Could not find library {0} in {1:?}")]
+    LibraryNotFound(String, PathBuf),
+    #[error(transparent)]
+    PkgConfig(#[from] PkgConfigError),
+}
+
+/// The packages of a vcpkg installation for one triplet.
+///
+/// The installation is `VCPKG_INSTALLED_DIR` if it is set, `vcpkg_installed` next to the
+/// manifest if the project uses vcpkg in manifest mode, and `$VCPKG_ROOT/installed` otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vcpkg {
+    installed_directory: PathBuf,
+    triplet: String,
+}
+
+impl Vcpkg {
+    pub fn locate(manifest_dir: &Path, triplet: Option<&str>) -> Result<Self, VcpkgError> {
+        let installed_directory =
+            if let Some(installed_dir) = std::env::var_os("VCPKG_INSTALLED_DIR") {
+                PathBuf::from(installed_dir)
+            } else if manifest_dir.join(VCPKG_MANIFEST_NAME).is_file() {
+                manifest_dir.join(VCPKG_MANIFEST_INSTALL_DIRECTORY)
+            } else if let Some(vcpkg_root) = std::env::var_os("VCPKG_ROOT") {
+                PathBuf::from(vcpkg_root).join("installed")
+            } else {
+                return Err(VcpkgError::VcpkgNotFound);
+            };
+        let triplet = triplet
+            .map(str::to_string)
+            .or_else(|| std::env::var("VCPKG_DEFAULT_TRIPLET").ok())
+            .unwrap_or_else(host_triplet);
+        Self::new(&installed_directory, &triplet)
+    }
+
+    pub fn new(installed_directory: &Path, triplet: &str) -> Result<Self, VcpkgError> {
+        let vcpkg = Self {
+            installed_directory: installed_directory.to_path_buf(),
+            triplet: triplet.to_string(),
+        };
+        if !vcpkg.triplet_directory().is_dir() {
+            return Err(VcpkgError::TripletNotInstalled(
+                vcpkg.triplet,
+                vcpkg.installed_directory,
+            ));
+        }
+        log::debug!(
+            "Using vcpkg packages in {}",
+            vcpkg.triplet_directory().display()
+        );
+        Ok(vcpkg)
+    }
+
+    pub fn triplet_directory(&self) -> PathBuf {
+        self.installed_directory.join(&self.triplet)
+    }
+
+    /// vcpkg installs debug builds of the libraries to the `debug` directory of the triplet.
+    pub fn library_directory(&self, build_type: &BuildType) -> PathBuf {
+        match build_type {
+            BuildType::Debug => self.triplet_directory().join("debug").join("lib"),
+            BuildType::Release => self.triplet_directory().join("lib"),
+        }
+    }
+
+    pub fn pkg_config_directory(&self, build_type: &BuildType) -> PathBuf {
+        self.library_directory(build_type).join("pkgconfig")
+    }
+
+    /// The status database of the installation, which lists the installed ports.
+    pub fn status_file(&self) -> PathBuf {
+        self.installed_directory.join("vcpkg").join("status")
+    }
+
+    /// The version of `port`, as recorded in the status database of the installation.
+    pub fn port_version(&self, port: &str) -> Option<String> {
+        let status = std::fs::read_to_string(self.status_file()).ok()?;
+        status.split("\n\n").find_map(|paragraph| {
+            let fields = paragraph
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .collect::<BTreeMap<&str, &str>>();
+            let is_port = fields.get("Package") == Some(&port)
+                && fields.get("Architecture") == Some(&self.triplet.as_str())
+                && !fields.contains_key("Feature")
+                && fields
+                    .get("Status")
+                    .is_some_and(|status| status.ends_with(" installed"));
+            if is_port {
+                fields.get("Version").map(|version| version.to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Resolves the package `name` for `build_type`.
+    ///
+    /// If the package has a `.pc` file, it is resolved like any other pkg-config package.
+    /// Otherwise the `include` directory of the triplet is used, together with the libraries of
+    /// the dependency found in the library directory of the triplet.
+    pub fn find_target(
+        &self,
+        name: &str,
+        data: &VcpkgData,
+        build_type: &BuildType,
+        pkg_config: &mut PkgConfig,
+    ) -> Result<DependencySource, VcpkgError> {
+        let link = data.link.as_ref().map(LibraryType::from);
+        let pkg_config_directory = self.pkg_config_directory(build_type);
+        let with_port_version = |mut target: PkgConfigTarget| {
+            if target.version.is_none() {
+                target.version = self.port_version(&data.vcpkg);
+                target.input_files.push(self.status_file());
+            }
+            target
+        };
+        if pkg_config_directory.join(format!("{}.pc", name)).is_file() {
+            pkg_config.add_search_path(&pkg_config_directory);
+            let target = pkg_config.find_target(name, &BTreeMap::new(), link.as_ref())?;
+            Ok(DependencySource::FromPkgConfig(with_port_version(target)))
+        } else {
+            log::debug!(
+                "No pkg-config file found for {} in {}. Using include and lib directories of triplet {}",
+                name,
+                pkg_config_directory.display(),
+                self.triplet
+            );
+            let target = self.find_libraries(name, data, build_type, link.as_ref())?;
+            Ok(DependencySource::FromLibraryFiles(with_port_version(
+                target,
+            )))
+        }
+    }
+
+    fn find_libraries(
+        &self,
+        name: &str,
+        data: &VcpkgData,
+        build_type: &BuildType,
+        link: Option<&LibraryType>,
+    ) -> Result<PkgConfigTarget, VcpkgError> {
+        let library_directory = self.library_directory(build_type);
+        let mut libraries = Vec::new();
+        match data.libraries {
+            Some(ref library_names) => {
+                for library_name in library_names {
+                    let library = self
+                        .find_library(library_name, &library_directory, build_type, link)
+                        .ok_or_else(|| {
+                            VcpkgError::LibraryNotFound(
+                                library_name.clone(),
+                                library_directory.clone(),
+                            )
+                        })?;
+                    libraries.push(library);
+                }
+            }
+            // Without explicit libraries, a package without a library of its own name is
+            // assumed to be header only.
+            None => libraries.extend(self.find_library(name, &library_directory, build_type, link)),
+        }
+
+        let mut include_directories = IncludeDirectories::new();
+        include_directories.add(IncludeDirectory {
+            include_type: IncludeType::System,
+            path: self.triplet_directory().join("include"),
+        });
+        Ok(PkgConfigTarget {
+            target: name.to_string(),
+            version: None,
+            include_directories,
+            cxx_flags: CXXFlags::new(&[]),
+            method: ProvideMethod::Finegrained(libraries),
+            input_files: Vec::new(),
+            requirement: None,
+        })
+    }
+
+    /// Debug builds of libraries are commonly suffixed with `d` by vcpkg ports.
+    fn find_library(
+        &self,
+        library: &str,
+        library_directory: &Path,
+        build_type: &BuildType,
+        link: Option<&LibraryType>,
+    ) -> Option<PkgConfigLibrary> {
+        let debug_library = format!("{}d", library);
+        let alternative_library = match build_type {
+            BuildType::Debug => Some(debug_library.as_str()),
+            BuildType::Release => None,
+        };
+        PkgConfigLibrary::find(library, alternative_library, library_directory, link)
+    }
+}
+
+fn host_triplet() -> String {
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "x86",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        arch => arch,
+    };
+    let os = match std::env::consts::OS {
+        "macos" => "osx",
+        os => os,
+    };
+    format!("{}-{}", arch, os)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn create_file(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// A vcpkg installation with zlib, which has `.pc` files, and fmt, which has none.
+    fn fake_installation(installed: &Path) {
+        let triplet = installed.join("x64-linux");
+        create_file(&triplet.join("include").join("zlib.h"), "");
+        create_file(&triplet.join("include").join("fmt").join("core.h"), "");
+        create_file(&triplet.join("lib").join("libz.a"), "");
+        create_file(&triplet.join("lib").join("libfmt.a"), "");
+        create_file(&triplet.join("debug").join("lib").join("libz.a"), "");
+        create_file(&triplet.join("debug").join("lib").join("libfmtd.a"), "");
+        for (pc_directory, prefix, libdir) in [
+            (triplet.join("lib").join("pkgconfig"), "../..", "lib"),
+            (
+                triplet.join("debug").join("lib").join("pkgconfig"),
+                "../../..",
+                "debug/lib",
+            ),
+        ] {
+            create_file(
+                &pc_directory.join("zlib.pc"),
+                &format!(
+                    "prefix=${{pcfiledir}}/{}\nlibdir=${{prefix}}/{}\nincludedir=${{prefix}}/include\nName: zlib\nDescription: zlib\nVersion: 1.3.1\nLibs: -L${{libdir}} -lz\nCflags: -I${{includedir}}\n",
+                    prefix, libdir
+                ),
+            );
+        }
+        create_file(
+            &installed.join("vcpkg").join("status"),
+            indoc::indoc!(
+                "
+                Package: fmt
+                Version: 10.2.1
+                Port-Version: 1
+                Architecture: x64-linux
+                Status: install ok installed
+
+                Package: fmt
+                Version: 9.1.0
+                Architecture: arm64-linux
+                Status: install ok installed
+                "
+            ),
+        );
+    }
+
+    fn vcpkg_data(port: &str) -> VcpkgData {
+        VcpkgData {
+            vcpkg: port.to_string(),
+            triplet: None,
+            libraries: None,
+            link: None,
+            version: None,
+        }
+    }
+
+    fn library_paths(target: &PkgConfigTarget) -> Vec<PathBuf> {
+        match target.method {
+            ProvideMethod::Finegrained(ref libraries) => libraries
+                .iter()
+                .map(|library| library.path().canonicalize().unwrap())
+                .collect(),
+            ProvideMethod::PkgConfigOutput(_) => panic!("Expected libraries to be found"),
+        }
+    }
+
+    #[test]
+    fn pkg_config_directory_follows_build_type() {
+        let dir = tempdir::TempDir::new("vcpkg").unwrap();
+        fake_installation(dir.path());
+        let vcpkg = Vcpkg::new(dir.path(), "x64-linux").unwrap();
+        assert_eq!(
+            vcpkg.pkg_config_directory(&BuildType::Debug),
+            dir.path().join("x64-linux/debug/lib/pkgconfig")
+        );
+        assert_eq!(
+            vcpkg.pkg_config_directory(&BuildType::Release),
+            dir.path().join("x64-linux/lib/pkgconfig")
+        );
+    }
+
+    #[test]
+    fn missing_triplet_is_an_error() {
+        let dir = tempdir::TempDir::new("vcpkg").unwrap();
+        fake_installation(dir.path());
+        assert!(matches!(
+            Vcpkg::new(dir.path(), "x64-windows"),
+            Err(VcpkgError::TripletNotInstalled(..))
+        ));
+    }
+
+    #[test]
+    fn package_with_pc_file_is_resolved_through_pkg_config() {
+        let dir = tempdir::TempDir::new("vcpkg").unwrap();
+        fake_installation(dir.path());
+        let vcpkg = Vcpkg::new(dir.path(), "x64-linux").unwrap();
+        let mut pkg_config = PkgConfig::isolated();
+
+        let target = vcpkg
+            .find_target(
+                "zlib",
+                &vcpkg_data("zlib"),
+                &BuildType::Debug,
+                &mut pkg_config,
+            )
+            .unwrap();
+        let target = match target {
+            DependencySource::FromPkgConfig(target) => target,
+            source => panic!("Unexpected dependency source {:?}", source),
+        };
+        assert_eq!(target.version, Some("1.3.1".to_string()));
+        assert_eq!(
+            library_paths(&target),
+            vec![dir.path().join("x64-linux/debug/lib/libz.a")]
+        );
+    }
+
+    #[test]
+    fn package_without_pc_file_falls_back_to_triplet_directories() {
+        let dir = tempdir::TempDir::new("vcpkg").unwrap();
+        fake_installation(dir.path());
+        let vcpkg = Vcpkg::new(dir.path(), "x64-linux").unwrap();
+        let mut pkg_config = PkgConfig::isolated();
+
+        let debug = vcpkg
+            .find_target(
+                "fmt",
+                &vcpkg_data("fmt"),
+                &BuildType::Debug,
+                &mut pkg_config,
+            )
+            .unwrap();
+        let debug = match debug {
+            DependencySource::FromLibraryFiles(target) => target,
+            source => panic!("Unexpected dependency source {:?}", source),
+        };
+        assert_eq!(debug.version, Some("10.2.1".to_string()));
+        assert_eq!(
+            debug.include_directories.iter().collect::<Vec<_>>(),
+            vec![&IncludeDirectory {
+                include_type: IncludeType::System,
+                path: dir.path().join("x64-linux/include"),
+            }]
+        );
+        assert_eq!(
+            library_paths(&debug),
+            vec![dir.path().join("x64-linux/debug/lib/libfmtd.a")]
+        );
+
+        let release = vcpkg
+            .find_target(
+                "fmt",
+                &vcpkg_data("fmt"),
+                &BuildType::Release,
+                &mut pkg_config,
+            )
+            .unwrap();
+        assert_eq!(
+            library_paths(release.pkg_config_target().unwrap()),
+            vec![dir.path().join("x64-linux/lib/libfmt.a")]
+        );
+    }
+
+    #[test]
+    fn explicit_libraries_must_exist() {
+        let dir = tempdir::TempDir::new("vcpkg").unwrap();
+        fake_installation(dir.path());
+        let vcpkg = Vcpkg::new(dir.path(), "x64-linux").unwrap();
+        let mut pkg_config = PkgConfig::isolated();
+        let mut data = vcpkg_data("fmt");
+        data.libraries = Some(vec!["fmt-extra".to_string()]);
+
+        assert!(matches!(
+            vcpkg.find_target("fmt", &data, &BuildType::Release, &mut pkg_config),
+            Err(VcpkgError::LibraryNotFound(..))
+        ));
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::build_target::target_registry::TargetRegistry;
+use crate::cli::command_line::ConfigurationOpts;
+use crate::cli::configurations::BuildType;
+use crate::errors::CacheError;
+use crate::lockfile::LOCKFILE_NAME;
+use crate::overrides::OVERRIDES_FILE_NAME;
+use crate::parser::preprocessor;
+use crate::toolchain::{NamedToolchain, TOOLCHAIN_FILE_NAME};
+use crate::YAMBS_MANIFEST_NAME;
+
+pub const CACHE_DIRECTORY: &str = "cache";
+const CONFIGURATION_FILE_NAME: &str = "configuration.json";
+
+/// Environment variables that affect how dependencies and the toolchain are resolved.
+const ENVIRONMENT_VARIABLES: &[&str] = &[
+    "CXX",
+    "CC",
+    "AR",
+    "PATH",
+    "HOME",
+    "PKG_CONFIG_PATH",
+    "PKG_CONFIG_LIBDIR",
+    "PKG_CONFIG_SYSROOT_DIR",
+    "PKG_CONFIG_ALLOW_SYSTEM_CFLAGS",
+    "PKG_CONFIG_ALLOW_SYSTEM_LIBS",
+    "VCPKG_INSTALLED_DIR",
+    "VCPKG_ROOT",
+    "VCPKG_DEFAULT_TRIPLET",
+];
+
+/// The state of a file the configuration was made from. A missing file has no hash.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FileFingerprint {
+    pub path: PathBuf,
+    pub modification_time: Option<std::time::SystemTime>,
+    pub hash: Option<String>,
+}
+
+impl FileFingerprint {
+    pub fn new(path: &Path) -> Self {
+        let modification_time = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let hash = std::fs::read(path)
+            .ok()
+            .map(|content| crate::utility::sha256_hex(&content));
+        Self {
+            path: path.to_path_buf(),
+            modification_time,
+            hash,
+        }
+    }
+
+    /// A file is unchanged if its modification time is the same, or if it was only touched and
+    /// its content is the same.
+    fn is_unchanged(&self) -> bool {
+        let modification_time = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modification_time.is_some() && modification_time == self.modification_time {
+            return true;
+        }
+        self.hash == Self::new(&self.path).hash
+    }
+}
+
+/// Everything the resolved targets and the generated build files depend on: the version of yambs,
+/// the configuration given on the command line, the manifests, lockfile, toolchain and overrides
+/// files, the files dependencies were resolved from, the compiler executables, and the
+/// environment variables that are read while resolving.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Fingerprint {
+    pub version: String,
+    pub configuration: ConfigurationOpts,
+    pub locked: bool,
+    pub files: Vec<FileFingerprint>,
+    pub environment: BTreeMap<String, Option<String>>,
+}
+
+impl Fingerprint {
+    /// Takes the fingerprint of the configuration of the manifest in `manifest_dir`, which
+    /// resolved to the targets of `registry` with the compilers in `compilers`.
+    pub fn new(
+        configuration: &ConfigurationOpts,
+        locked: bool,
+        manifest_dir: &Path,
+        registry: &TargetRegistry,
+        compilers: &[PathBuf],
+    ) -> Self {
+        let mut files = input_files(configuration, manifest_dir, registry)
+            .iter()
+            .map(|path| FileFingerprint::new(path))
+            .collect::<Vec<FileFingerprint>>();
+        let mut variables = ENVIRONMENT_VARIABLES
+            .iter()
+            .map(|variable| variable.to_string())
+            .collect::<Vec<String>>();
+        for file in &files {
+            if let Ok(content) = std::fs::read_to_string(&file.path) {
+                variables.extend(preprocessor::environment_variables(&content));
+            }
+        }
+        files.extend(
+            resolved_files(registry)
+                .iter()
+                .map(|path| FileFingerprint::new(path)),
+        );
+        // A compiler that is upgraded in place changes the identity of the toolchain.
+        files.extend(
+            compilers
+                .iter()
+                .map(|compiler| FileFingerprint::new(compiler)),
+        );
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            configuration: configuration.clone(),
+            locked,
+            files,
+            environment: variables
+                .into_iter()
+                .map(|variable| {
+                    let value = std::env::var_os(&variable)
+                        .map(|value| value.to_string_lossy().to_string());
+                    (variable, value)
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether a configuration with `configuration` would give the same result as the one the
+    /// fingerprint was taken of.
+    pub fn is_up_to_date(&self, configuration: &ConfigurationOpts, locked: bool) -> bool {
+        self.version == env!("CARGO_PKG_VERSION")
+            && self.configuration == *configuration
+            && self.locked == locked
+            && self.environment.iter().all(|(variable, value)| {
+                std::env::var_os(variable).map(|value| value.to_string_lossy().to_string())
+                    == *value
+            })
+            && self.files.iter().all(FileFingerprint::is_unchanged)
+    }
+}
+
+/// The files a configuration is made from. Files that don't exist are included as well, since
+/// creating them changes the configuration.
+fn input_files(
+    configuration: &ConfigurationOpts,
+    manifest_dir: &Path,
+    registry: &TargetRegistry,
+) -> Vec<PathBuf> {
+    let mut files = vec![manifest_dir.join(YAMBS_MANIFEST_NAME)];
+    for target in &registry.registry {
+        let manifest = target.borrow().manifest.directory.join(YAMBS_MANIFEST_NAME);
+        if !files.contains(&manifest) {
+            files.push(manifest);
+        }
+    }
+    files.push(manifest_dir.join(LOCKFILE_NAME));
+    if let Some(named_toolchain) = configuration
+        .toolchain
+        .as_ref()
+        .and_then(|toolchain| NamedToolchain::locate(toolchain, manifest_dir).ok())
+    {
+        files.push(named_toolchain.path);
+    }
+    files.push(manifest_dir.join(".yambs").join(TOOLCHAIN_FILE_NAME));
+    if let Some(home_dir) = home::home_dir() {
+        files.push(home_dir.join(".yambs").join(TOOLCHAIN_FILE_NAME));
+        files.push(home_dir.join(".yambs").join(OVERRIDES_FILE_NAME));
+    }
+    files
+}
+
+/// The files outside of the project that dependencies were resolved from, like `.pc` files, the
+/// files generated by Conan and the libraries that were found.
+fn resolved_files(registry: &TargetRegistry) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for target in &registry.registry {
+        for dependency in &target.borrow().dependencies {
+            for file in dependency.source.resolved_from() {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// A configured project, as stored in the cache.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct CachedConfiguration {
+    pub fingerprint: Fingerprint,
+    pub registry: TargetRegistry,
+    pub buildfile_directory: PathBuf,
+}
+
+/// Cache of the last configuration of a build type, so that builds where nothing that affects
+/// the configuration has changed can skip resolving the dependencies and generating build files.
+pub struct ConfigureCache {
+    directory: PathBuf,
+}
+
+impl ConfigureCache {
+    pub fn new(build_directory: &Path, build_type: &BuildType) -> Self {
+        Self {
+            directory: build_directory
+                .join(CACHE_DIRECTORY)
+                .join(build_type.to_string()),
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.directory.join(CONFIGURATION_FILE_NAME)
+    }
+
+    /// The cached configuration, if it is still up to date and its build files exist.
+    pub fn load(
+        &self,
+        configuration: &ConfigurationOpts,
+        locked: bool,
+    ) -> Option<CachedConfiguration> {
+        let content = std::fs::read_to_string(self.path()).ok()?;
+        let cached: CachedConfiguration = match serde_json::from_str(&content) {
+            Ok(cached) => cached,
+            Err(err) => {
+                log::debug!("Ignoring unreadable configure cache: {}", err);
+                return None;
+            }
+        };
+        if !cached.fingerprint.is_up_to_date(configuration, locked) {
+            log::debug!("Configure cache is out of date");
+            return None;
+        }
+        if !cached.buildfile_directory.is_dir() {
+            log::debug!("Build files of configure cache are missing");
+            return None;
+        }
+        Some(cached)
+    }
+
+    pub fn store(&self, cached: &CachedConfiguration) -> Result<(), CacheError> {
+        std::fs::create_dir_all(&self.directory).map_err(CacheError::FailedToCache)?;
+        let content = serde_json::to_string(cached).map_err(CacheError::FailedToWrite)?;
+        std::fs::write(self.path(), content).map_err(CacheError::FailedToCache)
+    }
+
+    /// Removes the cached configuration, so that the next build configures the project again.
+    pub fn invalidate(&self) -> Result<(), CacheError> {
+        match std::fs::remove_file(self.path()) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(CacheError::FailedToCache(err))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::command_line::ConfigurationOpts;
+    use crate::generator::GeneratorType;
+
+    fn configuration() -> ConfigurationOpts {
+        ConfigurationOpts {
+            build_type: BuildType::Debug,
+            standard: None,
+            generator_type: GeneratorType::GNUMakefiles,
+            defines: Vec::new(),
+            no_launcher: false,
+            object_cache: false,
+            toolchain: None,
+        }
+    }
+
+    #[test]
+    fn touched_file_with_same_content_is_unchanged() {
+        let dir = tempdir::TempDir::new("cache").unwrap();
+        let path = dir.path().join(YAMBS_MANIFEST_NAME);
+        std::fs::write(&path, "[executable.x]\n").unwrap();
+        let mut fingerprint = FileFingerprint::new(&path);
+        assert!(fingerprint.is_unchanged());
+
+        fingerprint.modification_time = Some(std::time::SystemTime::UNIX_EPOCH);
+        assert!(fingerprint.is_unchanged());
+
+        std::fs::write(&path, "[executable.y]\n").unwrap();
+        fingerprint.modification_time = Some(std::time::SystemTime::UNIX_EPOCH);
+        assert!(!fingerprint.is_unchanged());
+
+        let missing = FileFingerprint::new(&dir.path().join("missing.toml"));
+        assert!(missing.is_unchanged());
+        std::fs::write(&missing.path, "").unwrap();
+        assert!(!missing.is_unchanged());
+    }
+
+    #[test]
+    fn configuration_is_cached_until_inputs_change() {
+        let dir = tempdir::TempDir::new("cache").unwrap();
+        std::fs::write(dir.path().join(YAMBS_MANIFEST_NAME), "[executable.x]\n").unwrap();
+        let buildfile_directory = dir.path().join("build").join("debug");
+        std::fs::create_dir_all(&buildfile_directory).unwrap();
+        let cache = ConfigureCache::new(&dir.path().join("build"), &BuildType::Debug);
+        let registry = TargetRegistry::new();
+        let compiler = dir.path().join("g++");
+        std::fs::write(&compiler, "gcc 12").unwrap();
+
+        cache
+            .store(&CachedConfiguration {
+                fingerprint: Fingerprint::new(
+                    &configuration(),
+                    false,
+                    dir.path(),
+                    &registry,
+                    std::slice::from_ref(&compiler),
+                ),
+                registry,
+                buildfile_directory,
+            })
+            .unwrap();
+        assert!(cache.load(&configuration(), false).is_some());
+        assert!(cache.load(&configuration(), true).is_none());
+        let mut release = configuration();
+        release.build_type = BuildType::Release;
+        assert!(cache.load(&release, false).is_none());
+
+        std::fs::write(&compiler, "gcc 13").unwrap();
+        assert!(cache.load(&configuration(), false).is_none());
+        std::fs::write(&compiler, "gcc 12").unwrap();
+        assert!(cache.load(&configuration(), false).is_some());
+
+        std::fs::write(dir.path().join(LOCKFILE_NAME), "").unwrap();
+        assert!(cache.load(&configuration(), false).is_none());
+
+        cache.invalidate().unwrap();
+        assert!(!cache.path().exists());
+        cache.invalidate().unwrap();
+    }
+}
@@ -61,6 +61,17 @@ pub enum Subcommand {
     Build(BuildOpts),
     /// Print previous invocation line used and exit.
     Remake(RemakeOpts),
+    /// Build project and package its output into a relocatable tarball.
+    Package(PackageOpts),
+    /// Resolve the dependencies of the project and print them as a tree.
+    Tree(TreeOpts),
+    /// Resolve the dependencies of the project and export the target graph for visualization.
+    Graph(GraphOpts),
+    /// Compile an object file through the object cache. Used by the generated build files.
+    #[command(hide = true)]
+    CacheCompile(CacheCompileOpts),
+    /// Inspect and generate toolchain files.
+    Toolchain(ToolchainOpts),
 }
 
 #[derive(clap::Args, Debug)]
@@ -87,6 +98,9 @@ pub struct BuildOpts {
     /// Specific target to build
     #[arg(long)]
     pub target: Option<String>,
+    /// Fail if the resolved dependencies differ from those recorded in yambs.lock
+    #[arg(long)]
+    pub locked: bool,
     #[arg(hide = true)]
     pub make_args: Vec<String>,
 }
@@ -105,6 +119,134 @@ pub struct ConfigurationOpts {
     /// Macro definitions to be passed to the compiler upon build
     #[arg(short = 'D', value_parser = Define::from_cli)]
     pub defines: Vec<Define>,
+    /// Do not run compile commands through a compiler cache, like ccache or sccache
+    #[arg(long = "no-launcher")]
+    pub no_launcher: bool,
+    /// Restore object files from the object cache in ~/.yambs/objects, and store compiled ones there
+    #[arg(long = "object-cache")]
+    pub object_cache: bool,
+    /// Toolchain to build with, by name from .yambs/toolchains or by path. The build files are
+    /// put in a subdirectory of the build directory named after the toolchain.
+    #[arg(long)]
+    pub toolchain: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PackageOpts {
+    #[command(flatten)]
+    pub build_opts: BuildOpts,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
+pub enum TreeFormat {
+    Text,
+    Json,
+}
+
+/// Options of the commands that resolve the dependencies of the project without building it.
+#[derive(clap::Args, Debug)]
+pub struct ResolveOpts {
+    /// Input manifest file for YAMBS. By default, Yambs searches for yambs.toml manifest in current directory.
+    #[arg(default_value_t, hide_default_value(true), long = "manifest-directory")]
+    pub manifest_dir: ManifestDirectory,
+    /// Build configuration to resolve the dependencies for
+    #[arg(default_value_t, long = "build-type")]
+    pub build_type: configurations::BuildType,
+    /// Build directory the libraries are built in. Defaults to current working directory.
+    #[arg(
+        long,
+        short = 'b',
+        default_value_t,
+        hide_default_value(true),
+        value_parser
+    )]
+    pub build_directory: cli::BuildDirectory,
+    /// Toolchain to resolve the dependencies with, by name from .yambs/toolchains or by path
+    #[arg(long)]
+    pub toolchain: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TreeOpts {
+    #[command(flatten)]
+    pub resolve: ResolveOpts,
+    /// Show the targets that depend on the given target or dependency instead
+    #[arg(long)]
+    pub invert: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = TreeFormat::Text)]
+    pub format: TreeFormat,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct GraphOpts {
+    #[command(flatten)]
+    pub resolve: ResolveOpts,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+    pub format: GraphFormat,
+    /// Include the object files of the targets in the graph
+    #[arg(long)]
+    pub objects: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CacheCompileOpts {
+    /// Launcher the compiler is run through on a cache miss
+    #[arg(long)]
+    pub launcher: Option<std::path::PathBuf>,
+    /// The compile command, starting with the compiler
+    #[arg(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ToolchainOpts {
+    #[command(subcommand)]
+    pub command: ToolchainCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ToolchainCommand {
+    /// Detect the compilers and tools in PATH and write a toolchain file for them.
+    Detect(DetectOpts),
+    /// Print the identity of the toolchain a project is built with.
+    Show(ShowOpts),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DetectOpts {
+    /// Project directory to write .yambs/toolchain.toml in. Defaults to current working directory.
+    #[arg(default_value_t, hide_default_value(true), long = "manifest-directory")]
+    pub manifest_dir: ManifestDirectory,
+    /// List all detected compilers and tools instead of writing a toolchain file
+    #[arg(long)]
+    pub list: bool,
+    /// Write the toolchain file here instead of .yambs/toolchain.toml of the project
+    #[arg(long, short = 'o')]
+    pub output: Option<std::path::PathBuf>,
+    /// Overwrite an existing toolchain file
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ShowOpts {
+    /// Input manifest file for YAMBS. By default, Yambs searches for yambs.toml manifest in current directory.
+    #[arg(default_value_t, hide_default_value(true), long = "manifest-directory")]
+    pub manifest_dir: ManifestDirectory,
+    /// Toolchain to show, by name from .yambs/toolchains or by path
+    #[arg(long)]
+    pub toolchain: Option<String>,
+    /// Build directory to compare the toolchain with the one it was configured with
+    #[arg(long, short = 'b', value_parser)]
+    pub build_directory: Option<cli::BuildDirectory>,
 }
 
 #[derive(clap::Args, Debug)]
@@ -39,6 +39,12 @@ pub enum CompilerError {
     FailedToGetVersion(std::path::PathBuf, #[source] errors::FsError),
     #[error("Failed to find version pattern")]
     FailedToFindVersionPattern,
+    #[error(
+        "Failed to retrieve target machine from\n\
+        \n\
+        \t{0} -dumpmachine"
+    )]
+    FailedToGetMachine(std::path::PathBuf),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
@@ -47,6 +53,7 @@ pub enum Linker {
     Ld,
     Gold,
     LLD,
+    Mold,
     Inferred,
 }
 
@@ -102,6 +109,11 @@ pub struct CCCompiler {
     pub compiler_info: CompilerInfo,
     #[serde(default)]
     pub stdlib: StdLibCC,
+    /// Target triple to build for, when cross compiling.
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub sysroot: Option<std::path::PathBuf>,
 }
 
 impl CCCompiler {
@@ -117,6 +129,8 @@ impl CCCompiler {
             compiler_exe,
             compiler_info,
             stdlib,
+            target: None,
+            sysroot: None,
         })
     }
 
@@ -129,17 +143,34 @@ impl CCCompiler {
             compiler_exe,
             compiler_info,
             stdlib,
+            target: None,
+            sysroot: None,
         })
     }
 
+    /// Flags that make the compiler build for the target of the toolchain.
+    pub fn target_flags(&self) -> Vec<String> {
+        target_flags(
+            &self.compiler_info.compiler_type,
+            self.target.as_deref(),
+            self.sysroot.as_deref(),
+        )
+    }
+
     fn create_sample_compile_args(&self, destination_dir: &std::path::Path) -> Vec<String> {
+        let mut args = self.target_flags();
+        if is_cross_compiling(&self.compiler_exe, &args) {
+            log::debug!("Cross compiling. The sample is only compiled, not linked.");
+            args.push("-c".to_string());
+        }
         match self.compiler_info.compiler_type {
-            Type::Gcc | Type::Clang => vec![
+            Type::Gcc | Type::Clang => args.extend([
                 format!("-I{}", destination_dir.display()),
                 "-o".to_string(),
                 destination_dir.join("a.out").display().to_string(),
-            ],
+            ]),
         }
+        args
     }
 
     fn sample_compile(
@@ -186,6 +217,11 @@ pub struct CXXCompiler {
     pub compiler_info: CompilerInfo,
     #[serde(default)]
     pub stdlib: StdLibCXX,
+    /// Target triple to build for, when cross compiling.
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub sysroot: Option<std::path::PathBuf>,
 }
 
 impl CXXCompiler {
@@ -201,6 +237,8 @@ impl CXXCompiler {
             compiler_exe,
             compiler_info,
             stdlib,
+            target: None,
+            sysroot: None,
         })
     }
 
@@ -213,6 +251,8 @@ impl CXXCompiler {
             compiler_exe,
             compiler_info,
             stdlib,
+            target: None,
+            sysroot: None,
         })
     }
 
@@ -225,14 +265,29 @@ impl CXXCompiler {
         Ok(())
     }
 
+    /// Flags that make the compiler build for the target of the toolchain.
+    pub fn target_flags(&self) -> Vec<String> {
+        target_flags(
+            &self.compiler_info.compiler_type,
+            self.target.as_deref(),
+            self.sysroot.as_deref(),
+        )
+    }
+
     fn create_sample_compile_args(&self, destination_dir: &std::path::Path) -> Vec<String> {
+        let mut args = self.target_flags();
+        if is_cross_compiling(&self.compiler_exe, &args) {
+            log::debug!("Cross compiling. The sample is only compiled, not linked.");
+            args.push("-c".to_string());
+        }
         match self.compiler_info.compiler_type {
-            Type::Gcc | Type::Clang => vec![
+            Type::Gcc | Type::Clang => args.extend([
                 format!("-I{}", destination_dir.display()),
                 "-o".to_string(),
                 destination_dir.join("a.out").display().to_string(),
-            ],
+            ]),
         }
+        args
     }
 
     fn sample_compile(
@@ -273,6 +328,102 @@ impl Compiler for CXXCompiler {
     }
 }
 
+/// Flags that make a compiler build for `target` against the headers and libraries in `sysroot`.
+/// A gcc compiler only builds for the target it was configured for, so `--target` is only given
+/// to clang.
+pub fn target_flags(
+    compiler_type: &Type,
+    target: Option<&str>,
+    sysroot: Option<&Path>,
+) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(target) = target {
+        match compiler_type {
+            Type::Clang => flags.push(format!("--target={}", target)),
+            Type::Gcc => log::debug!(
+                "gcc builds for the target it was configured for. Not passing --target={}",
+                target
+            ),
+        }
+    }
+    if let Some(sysroot) = sysroot {
+        flags.push(format!("--sysroot={}", sysroot.display()));
+    }
+    flags
+}
+
+/// Whether the compiler builds for another architecture than the host, in which case its
+/// binaries can not be run on the host.
+fn is_cross_compiling(compiler_exe: &Path, target_flags: &[String]) -> bool {
+    match machine(compiler_exe, target_flags) {
+        Ok(machine) => !is_host_machine(&machine),
+        Err(_) => false,
+    }
+}
+
+/// Whether binaries for the target triple `machine` run on the host, meaning that it has the
+/// architecture and operating system of the host. Vendors and ABIs are ignored, so that
+/// `x86_64-linux-gnu` and `x86_64-pc-linux-gnu` are the same.
+fn is_host_machine(machine: &str) -> bool {
+    let (arch, os) = match triple_arch_and_os(machine) {
+        Some(arch_and_os) => arch_and_os,
+        None => return true,
+    };
+    arch == normalize_arch(std::env::consts::ARCH) && os == std::env::consts::OS
+}
+
+/// The architecture and operating system of a target triple, named like in
+/// `std::env::consts`.
+fn triple_arch_and_os(machine: &str) -> Option<(&str, &'static str)> {
+    let mut components = machine.split('-');
+    let arch = normalize_arch(components.next()?);
+    let os = components.find_map(|component| {
+        if component.starts_with("linux") {
+            Some("linux")
+        } else if component.starts_with("darwin") || component.starts_with("macos") {
+            Some("macos")
+        } else if component.starts_with("mingw")
+            || component.starts_with("windows")
+            || component.starts_with("cygwin")
+        {
+            Some("windows")
+        } else if component.starts_with("freebsd") {
+            Some("freebsd")
+        } else if component == "none" || component == "elf" || component == "eabi" {
+            Some("none")
+        } else {
+            None
+        }
+    })?;
+    Some((arch, os))
+}
+
+fn normalize_arch(arch: &str) -> &str {
+    match arch {
+        "x86_64" | "amd64" => "x86_64",
+        "i386" | "i486" | "i586" | "i686" | "x86" => "x86",
+        "aarch64" | "arm64" => "aarch64",
+        "riscv64" | "riscv64gc" => "riscv64",
+        "powerpc64le" | "ppc64le" => "powerpc64le",
+        arch if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+        arch => arch,
+    }
+}
+
+/// The target triple the compiler builds for with `target_flags`, as given by `-dumpmachine`.
+pub fn machine(compiler_exe: &Path, target_flags: &[String]) -> Result<String, CompilerError> {
+    let args = target_flags
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once("-dumpmachine"));
+    utility::shell::execute_get_stdout(compiler_exe, args)
+        .map(|machine| machine.trim().to_string())
+        .map_err(|e| {
+            log::debug!("{} -dumpmachine failed: {}", compiler_exe.display(), e);
+            CompilerError::FailedToGetMachine(compiler_exe.to_path_buf())
+        })
+}
+
 fn try_get_version(compiler_exe: &std::path::Path) -> Result<semver::Version, CompilerError> {
     let version_regex = Regex::new(r"[0-9]+\.[0-9]+\.[0-9]+").unwrap();
 
@@ -293,7 +444,8 @@ fn parse_version(compiler_exe: &std::path::Path) -> Result<String, CompilerError
     try_get_version(compiler_exe).map(|version| version.to_string())
 }
 
-fn compiler_version_raw(compiler_exe: &std::path::Path) -> Result<String, CompilerError> {
+/// The full output of `--version` of the compiler.
+pub fn compiler_version_raw(compiler_exe: &std::path::Path) -> Result<String, CompilerError> {
     log::debug!(
         "Fetching compiler version with '{} --version'",
         compiler_exe.display()
@@ -339,3 +491,58 @@ impl std::string::ToString for CXXCompiler {
         self.compiler_exe.display().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_flags_pass_target_to_clang_only() {
+        let sysroot = Path::new("/opt/sysroot");
+        assert_eq!(
+            target_flags(&Type::Clang, Some("aarch64-linux-gnu"), Some(sysroot)),
+            vec!["--target=aarch64-linux-gnu", "--sysroot=/opt/sysroot"]
+        );
+        assert_eq!(
+            target_flags(&Type::Gcc, Some("aarch64-linux-gnu"), Some(sysroot)),
+            vec!["--sysroot=/opt/sysroot"]
+        );
+        assert!(target_flags(&Type::Gcc, None, None).is_empty());
+    }
+
+    #[test]
+    fn target_triples_are_compared_by_architecture_and_operating_system() {
+        assert_eq!(
+            triple_arch_and_os("x86_64-linux-gnu"),
+            Some(("x86_64", "linux"))
+        );
+        assert_eq!(
+            triple_arch_and_os("x86_64-pc-linux-gnu"),
+            Some(("x86_64", "linux"))
+        );
+        assert_eq!(
+            triple_arch_and_os("x86_64-w64-mingw32"),
+            Some(("x86_64", "windows"))
+        );
+        assert_eq!(triple_arch_and_os("i686-linux-gnu"), Some(("x86", "linux")));
+        assert_eq!(
+            triple_arch_and_os("arm64-apple-darwin23.1.0"),
+            Some(("aarch64", "macos"))
+        );
+        assert_eq!(
+            triple_arch_and_os("armv7a-none-eabihf"),
+            Some(("arm", "none"))
+        );
+
+        let host = format!(
+            "{}-unknown-{}-gnu",
+            std::env::consts::ARCH,
+            std::env::consts::OS
+        );
+        assert!(is_host_machine(&host));
+        assert!(
+            !is_host_machine(&format!("{}-w64-mingw32", std::env::consts::ARCH)) || cfg!(windows)
+        );
+        assert!(!is_host_machine("riscv64-linux-gnu") || cfg!(target_arch = "riscv64"));
+    }
+}
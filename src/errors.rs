@@ -53,6 +53,12 @@ pub enum FsError {
     CreateFile(std::path::PathBuf, #[source] std::io::Error),
     #[error("Error occured reading from file {0:?}")]
     ReadFromFile(std::path::PathBuf, #[source] std::io::Error),
+    #[error("Error occured copying {0:?} to {1:?}")]
+    CopyFile(
+        std::path::PathBuf,
+        std::path::PathBuf,
+        #[source] std::io::Error,
+    ),
     #[error("The path {0:?} does not exist")]
     FileDoesNotExist(std::path::PathBuf),
     #[error("Failed to canonicalize path")]
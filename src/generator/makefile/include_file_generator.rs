@@ -123,6 +123,7 @@ impl<'generator> IncludeFileGenerator<'generator> {
             Linker::Gold => "CXX_LDFLAGS += -fuse-ld=gold".to_string(),
             Linker::Ld => "CXX_LDFLAGS += -fuse-ld=ld".to_string(),
             Linker::LLD => "CXX_LDFLAGS += -fuse-ld=lld".to_string(),
+            Linker::Mold => "CXX_LDFLAGS += -fuse-ld=mold".to_string(),
             _ => "CXX_LDFLAGS += ".to_string(),
         };
 
@@ -133,6 +134,7 @@ impl<'generator> IncludeFileGenerator<'generator> {
             Linker::Gold => "CC_LDFLAGS += -fuse-ld=gold".to_string(),
             Linker::Ld => "CC_LDFLAGS += -fuse-ld=ld".to_string(),
             Linker::LLD => "CC_LDFLAGS += -fuse-ld=lld".to_string(),
+            Linker::Mold => "CC_LDFLAGS += -fuse-ld=mold".to_string(),
             _ => "CC_LDFLAGS += ".to_string(),
         };
 
@@ -273,11 +275,19 @@ impl<'generator> IncludeFileGenerator<'generator> {
         # Select stdlibc++ implementation based on toolchain file.
         # Will be empty if not specified.
         CXXFLAGS += {stdlib}
+
+        # Target and sysroot to build for when cross compiling.
+        # Will be empty if not specified.
+        CXXFLAGS += {cxx_target_flags}
+        CFLAGS += {cc_target_flags}
+        CC_LDFLAGS += {cc_target_flags}
         \n\
         ",
             compiler_conditional_flags = self.generate_toolchain_defines(),
             linker_selection = self.generate_linker_selection(),
             stdlib = self.select_cxx_stdlib_impl(),
+            cxx_target_flags = self.toolchain.cxx.compiler.target_flags().join(" "),
+            cc_target_flags = self.toolchain.cc.compiler.target_flags().join(" "),
         );
         self.file
             .as_ref()
@@ -297,14 +307,22 @@ impl<'generator> IncludeFileGenerator<'generator> {
         CC := {}
         CXX := {}
         AR := {}
+        RANLIB := {}
         ",
             cc.display(),
             cxx.display(),
             archiver_path.display(),
+            display_optional(&self.toolchain.ranlib),
         )
     }
 }
 
+fn display_optional(path: &Option<std::path::PathBuf>) -> String {
+    path.as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
 impl<'generator> UtilityGenerator<'generator> for IncludeFileGenerator<'generator> {
     fn generate_build_files(&'generator mut self) -> Result<(), GeneratorError> {
         self.generate_warnings_mk()?;
@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::Write;
 use std::rc::Rc;
 
@@ -14,7 +15,7 @@ use crate::build_target::{
     include_directories::{IncludeDirectory, IncludeType},
     pkg_config::ProvideMethod,
     target_registry::TargetRegistry,
-    Dependency, DependencySource, LibraryType, TargetNode, TargetState, TargetType,
+    DependencySource, LibraryType, TargetNode, TargetState, TargetType,
 };
 use crate::cli::configurations;
 use crate::cli::BuildDirectory;
@@ -27,6 +28,7 @@ use crate::generator::{
 use crate::parser::types;
 use crate::parser::types::Language;
 use crate::progress;
+use crate::toolchain::identity::ToolchainIdentity;
 use crate::toolchain::NormalizedToolchain;
 use crate::utility;
 use crate::ProjectConfig;
@@ -34,6 +36,10 @@ use crate::ProjectConfig;
 use include_file_generator::IncludeFileGenerator;
 pub use make::Make;
 
+/// File with the signature of the compile command of a target, located in the object directory
+/// of the target.
+pub const COMMAND_SIGNATURE_FILE_NAME: &str = "command.sig";
+
 struct ExecutableTargetFactory;
 
 impl ExecutableTargetFactory {
@@ -41,8 +47,10 @@ impl ExecutableTargetFactory {
         target: &TargetNode,
         output_directory: &std::path::Path,
         language: &types::Language,
+        registry: &TargetRegistry,
     ) -> String {
         let target_name = target.borrow().name();
+        let target_name_capitalized = variable_prefix(&target.borrow().identity());
 
         match language {
             types::Language::CXX => {
@@ -51,8 +59,8 @@ impl ExecutableTargetFactory {
                         {prerequisites}\n\
                         \t$(strip $(CXX) $(CXXFLAGS) $(CPPFLAGS) $({target_name_capitalized}_CXXFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CXX_LDFLAGS) {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)",
                         target_name = target_name,
-                        target_name_capitalized = target_name.to_uppercase(),
-                        prerequisites = generate_prerequisites(target, output_directory),
+                        target_name_capitalized = target_name_capitalized,
+                        prerequisites = generate_prerequisites(target, output_directory, registry),
                         dependencies = generate_search_directories(target),
                 )
             }
@@ -62,8 +70,8 @@ impl ExecutableTargetFactory {
                         {prerequisites}\n\
                         \t$(strip $(CC) $(CPPFLAGS) $({target_name_capitalized}_CFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CC_LDFLAGS) {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)",
                         target_name = target_name,
-                        target_name_capitalized = target_name.to_uppercase(),
-                        prerequisites = generate_prerequisites(target, output_directory),
+                        target_name_capitalized = target_name_capitalized,
+                        prerequisites = generate_prerequisites(target, output_directory, registry),
                         dependencies = generate_search_directories(target),
                 )
             }
@@ -78,17 +86,21 @@ impl LibraryTargetFactory {
         target: &TargetNode,
         output_directory: &std::path::Path,
         language: &types::Language,
+        registry: &TargetRegistry,
     ) -> String {
         let mut formatted_string = String::new();
-        let library_name = library_name_from_target_type(&target.borrow().target_type);
+        let library_name = generator::artifact(&target.borrow()).display().to_string();
+        let soname = library_name_from_target_type(&target.borrow().target_type);
+        let target_name_capitalized = variable_prefix(&target.borrow().identity());
         let target_rule = match target.borrow().library_type().unwrap() {
             LibraryType::Static => format!(
                 "\
                 {target_name} : \\\n\
                     {prerequisites}\n\
-                    \t$(strip $(AR) $(ARFLAGS) $@ $?)\n\n",
+                    \t$(strip $(AR) $(ARFLAGS) $@ $?)\n\
+                    \t$(if $(RANLIB),$(RANLIB) $@)\n\n",
                 target_name = library_name,
-                prerequisites = generate_prerequisites(target, output_directory)
+                prerequisites = generate_prerequisites(target, output_directory, registry)
             ),
             LibraryType::Dynamic => match language {
                 types::Language::CXX => {
@@ -96,10 +108,11 @@ impl LibraryTargetFactory {
                             "\
                             {target_name} : \\\n\
                                 {prerequisites}\n\
-                                \t$(strip $(CXX) $(CXXFLAGS) $(CPPFLAGS) $({target_name_capitalized}_CXXFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CXX_LDFLAGS) -rdynamic -shared {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)\n\n",
+                                \t$(strip $(CXX) $(CXXFLAGS) $(CPPFLAGS) $({target_name_capitalized}_CXXFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CXX_LDFLAGS) -rdynamic -shared -Wl,-soname,{soname} {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)\n\n",
                                 target_name = library_name,
-                                target_name_capitalized = target.borrow().name().to_uppercase(),
-                                prerequisites = generate_prerequisites(target, output_directory),
+                                soname = soname,
+                                target_name_capitalized = target_name_capitalized,
+                                prerequisites = generate_prerequisites(target, output_directory, registry),
                                 dependencies = generate_search_directories(target),
                         )
                 }
@@ -108,10 +121,11 @@ impl LibraryTargetFactory {
                             "\
                             {target_name} : \\\n\
                                 {prerequisites}\n\
-                                \t$(strip $(CC) $(CPPFLAGS) $({target_name_capitalized}_CFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CC_LDFLAGS) -rdynamic -shared {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)\n\n",
+                                \t$(strip $(CC) $(CPPFLAGS) $({target_name_capitalized}_CFLAGS) $({target_name_capitalized}_CPPFLAGS) $(WARNINGS) $(CC_LDFLAGS) -rdynamic -shared -Wl,-soname,{soname} {dependencies} $^ $({target_name_capitalized}_LDFLAGS) -o $@)\n\n",
                                 target_name = library_name,
-                                target_name_capitalized = target.borrow().name().to_uppercase(),
-                                prerequisites = generate_prerequisites(target, output_directory),
+                                soname = soname,
+                                target_name_capitalized = target_name_capitalized,
+                                prerequisites = generate_prerequisites(target, output_directory, registry),
                                 dependencies = generate_search_directories(target),
                         )
                 }
@@ -123,10 +137,19 @@ impl LibraryTargetFactory {
             "# Convenience rule for \"{target_name}\"
             {target_name}: {library_name}\n
             ",
-            target_name = target.borrow().name(),
+            target_name = target.borrow().identity(),
             library_name = library_name
         );
         formatted_string.push_str(&convenience_rule);
+        if has_alias(&target.borrow(), registry) {
+            formatted_string.push_str(&indoc::formatdoc!(
+                "# Alias of \"{identity}\"
+                {target_name}: {identity}\n
+                ",
+                target_name = target.borrow().name(),
+                identity = target.borrow().identity()
+            ));
+        }
         formatted_string
     }
 }
@@ -138,13 +161,60 @@ impl TargetRuleFactory {
         target: &TargetNode,
         output_dir: &std::path::Path,
         language: &types::Language,
+        registry: &TargetRegistry,
     ) -> String {
         if target.borrow().is_executable() {
-            ExecutableTargetFactory::create_rule(target, output_dir, language)
+            ExecutableTargetFactory::create_rule(target, output_dir, language, registry)
         } else {
-            LibraryTargetFactory::create_rule(target, output_dir, language)
+            LibraryTargetFactory::create_rule(target, output_dir, language, registry)
+        }
+    }
+}
+
+/// The prefix of the make variables of a target, like `FMT_FMT` for the identity `fmt.fmt`.
+fn variable_prefix(identity: &str) -> String {
+    identity.to_uppercase().replace(['.', '-'], "_")
+}
+
+/// Verifies that no two targets of `registry` share a variable prefix, like the identities `a-b.c`
+/// and `a.b-c`, since their flags would silently override each other.
+fn verify_unique_variable_prefixes(registry: &TargetRegistry) -> Result<(), GeneratorError> {
+    let mut targets_by_prefix = HashMap::<String, String>::new();
+    for target in &registry.registry {
+        let target = target.borrow();
+        let prefix = variable_prefix(&target.identity());
+        if let Some(other) = targets_by_prefix.get(&prefix) {
+            return Err(GeneratorError::VariableCollision(
+                other.clone(),
+                target.qualified_name(),
+                prefix,
+            ));
         }
+        targets_by_prefix.insert(prefix, target.qualified_name());
     }
+    Ok(())
+}
+
+/// The rule that builds a target. Executables are built by a rule named after the executable
+/// itself, and libraries by a convenience rule named after their identity.
+fn rule_name(target: &build_target::BuildTarget) -> String {
+    if target.is_executable() {
+        target.name()
+    } else {
+        target.identity()
+    }
+}
+
+/// Whether a library can be built by its plain name, which requires that no other target in
+/// `registry` has the same name.
+fn has_alias(target: &build_target::BuildTarget, registry: &TargetRegistry) -> bool {
+    target.is_library()
+        && registry
+            .registry
+            .iter()
+            .filter(|other| other.borrow().name() == target.name())
+            .count()
+            == 1
 }
 
 fn library_name_from_target_type(target_type: &TargetType) -> String {
@@ -154,7 +224,11 @@ fn library_name_from_target_type(target_type: &TargetType) -> String {
     }
 }
 
-fn generate_prerequisites(target: &TargetNode, output_directory: &std::path::Path) -> String {
+fn generate_prerequisites(
+    target: &TargetNode,
+    output_directory: &std::path::Path,
+    registry: &TargetRegistry,
+) -> String {
     let mut formatted_string = String::new();
     let borrowed_target = target.borrow();
     let sources = borrowed_target
@@ -165,15 +239,8 @@ fn generate_prerequisites(target: &TargetNode, output_directory: &std::path::Pat
     let dependency_root_path = &borrowed_target.manifest.directory;
 
     for (i, source) in sources.iter().enumerate() {
-        let source_file = source.file();
-        let source_dir = source_file
-            .parent()
-            .and_then(|p| p.strip_prefix(dependency_root_path).ok())
-            .unwrap();
-        let object = output_directory
-            .join(source_dir)
-            .join(source_file.file_name().unwrap())
-            .with_extension("o");
+        let object =
+            ObjectTarget::object_path(&source.file(), dependency_root_path, output_directory);
 
         formatted_string.push_str(&format!("   {}", object.display()));
         if i != (sources.len() - 1) {
@@ -182,11 +249,16 @@ fn generate_prerequisites(target: &TargetNode, output_directory: &std::path::Pat
     }
     for dependency in &borrowed_target.dependencies {
         match dependency.source {
-            build_target::DependencySource::FromSource(ref s) => {
+            build_target::DependencySource::FromSource(_) => {
+                let dependency_target = dependency.to_build_target(registry).unwrap();
                 formatted_string.push_str("\\\n");
-                formatted_string.push_str(&format!("   {}", s.library));
+                formatted_string.push_str(&format!(
+                    "   {}",
+                    generator::artifact(&dependency_target.borrow()).display()
+                ));
             }
-            build_target::DependencySource::FromPkgConfig(ref pkg) => {
+            build_target::DependencySource::FromPkgConfig(ref pkg)
+            | build_target::DependencySource::FromLibraryFiles(ref pkg) => {
                 match pkg.method {
                     ProvideMethod::Finegrained(ref libs) => {
                         formatted_string.push_str("\\\n");
@@ -200,6 +272,12 @@ fn generate_prerequisites(target: &TargetNode, output_directory: &std::path::Pat
                     _ => {}
                 };
             }
+            build_target::DependencySource::FromConan(ref conan) => {
+                for library in &conan.libraries {
+                    formatted_string.push_str("\\\n");
+                    formatted_string.push_str(&format!("   {}", library.display()));
+                }
+            }
             _ => {}
         }
     }
@@ -251,7 +329,11 @@ fn generate_include_directories(
     formatted_string.trim_end().to_string()
 }
 
-fn generate_object_target(object_target: &ObjectTarget, language: &types::Language) -> String {
+fn generate_object_target(
+    object_target: &ObjectTarget,
+    command_signature: Option<&std::path::Path>,
+    language: &types::Language,
+) -> String {
     let mut formatted_string = String::new();
     formatted_string.push_str(&format!(
         "# Build rule for {}\n",
@@ -261,22 +343,26 @@ fn generate_object_target(object_target: &ObjectTarget, language: &types::Langua
     formatted_string.push_str(": \\\n");
     formatted_string.push('\t');
     formatted_string.push_str(&object_target.source.display().to_string());
+    if let Some(command_signature) = command_signature {
+        formatted_string.push_str(" \\\n\t");
+        formatted_string.push_str(&command_signature.display().to_string());
+    }
     formatted_string.push('\n');
     match language {
         types::Language::CXX => {
             formatted_string.push_str(&format!(
-                "\t$(strip $(CXX) $(CXXFLAGS) $(CPPFLAGS) $({target}_CXXFLAGS) $({target}_CPPFLAGS) \
+                "\t$(strip $(LAUNCHER) $(CXX) $(CXXFLAGS) $(CPPFLAGS) $({target}_CXXFLAGS) $({target}_CPPFLAGS) \
                  $(WARNINGS) {dependencies} $< -c -o $@)\n\n",
                 dependencies = generate_include_directories(&object_target.include_directories),
-                target = object_target.target.to_uppercase(),
+                target = variable_prefix(&object_target.target),
             ));
         }
         types::Language::C => {
             formatted_string.push_str(&format!(
-                "\t$(strip $(CC) $(CFLAGS) $(CPPFLAGS) $({target}_CFLAGS) $({target}_CPPFLAGS) \
+                "\t$(strip $(LAUNCHER) $(CC) $(CFLAGS) $(CPPFLAGS) $({target}_CFLAGS) $({target}_CPPFLAGS) \
                  $(WARNINGS) {dependencies} $< -c -o $@)\n\n",
                 dependencies = generate_include_directories(&object_target.include_directories),
-                target = object_target.target.to_uppercase(),
+                target = variable_prefix(&object_target.target),
             ));
         }
     }
@@ -289,6 +375,10 @@ pub struct MakefileGenerator {
     pub build_directory: BuildDirectory,
     pub output_directory: std::path::PathBuf,
     pub progress_document: ProgressDocument,
+    /// Digest of the make include files and the toolchain, which are part of every compile command.
+    include_files_digest: String,
+    /// The command signature file of every target, by identity.
+    command_signatures: HashMap<String, std::path::PathBuf>,
 }
 
 impl MakefileGenerator {
@@ -304,12 +394,15 @@ impl MakefileGenerator {
             output_directory: build_directory.as_path().to_path_buf(),
             progress_document: ProgressDocument::new(),
             toolchain,
+            include_files_digest: String::new(),
+            command_signatures: HashMap::new(),
         })
     }
 
     fn generate_all_target_for_progress_document(&mut self, object_targets: &[ObjectTarget]) {
         let mut target_all = ProgressTrackingTarget {
             target: "all".to_string(),
+            name: String::new(),
             object_files: Vec::new(),
             dependencies: Vec::new(),
         };
@@ -330,11 +423,11 @@ impl MakefileGenerator {
     ) -> Result<(), GeneratorError> {
         self.generate_header(&mut writers.makefile_writer, &registry.registry)?;
 
-        self.push_and_create_directory(std::path::Path::new("deps"))?;
+        self.push_and_create_directory(std::path::Path::new(generator::TARGETS_DIRECTORY))?;
         for target in &registry.registry {
             if target.borrow().state != TargetState::BuildFileMade {
                 let borrowed_target = target.borrow();
-                let dep_dir = format!("{}.dir", &borrowed_target.name());
+                let dep_dir = format!("{}.dir", &borrowed_target.identity());
                 self.push_and_create_directory(std::path::Path::new(&dep_dir))?;
                 log::debug!(
                     "Generating makefiles for target {:?} (manifest path: {})",
@@ -342,7 +435,7 @@ impl MakefileGenerator {
                     borrowed_target.manifest.directory.display()
                 );
 
-                self.generate_rule_declaration_for_target(writers, target);
+                self.generate_rule_declaration_for_target(writers, target, registry)?;
                 // Quick hack to allow each dependency / target to be placed in their own
                 // folder, without it being a subfolder of a separate target.
                 // FIXME: Need to figure out if there is a better way to solve this. It is
@@ -357,7 +450,7 @@ impl MakefileGenerator {
                 self.push_and_create_directory(std::path::Path::new(&dep_dir))?;
 
                 let progress_tracking_target =
-                    ProgressTrackingTarget::from_target(target, &self.output_directory);
+                    ProgressTrackingTarget::from_target(target, &self.output_directory, registry);
                 self.progress_document
                     .add_progress_tracking_target(progress_tracking_target);
                 ObjectTarget::create_object_targets(target, &self.output_directory)
@@ -398,9 +491,10 @@ impl MakefileGenerator {
                             s.manifest.directory.display(),
                             target_name,
                             build_target.manifest.directory.display());
-                        let dep_dir = format!("{}.dir", &s.library.name);
+                        let dependency_target = dependency.to_build_target(registry).unwrap();
+                        let dep_dir = format!("{}.dir", dependency_target.borrow().identity());
                         self.push_and_create_directory(std::path::Path::new(&dep_dir))?;
-                        self.generate_rule_for_dependency(writers, dependency, registry);
+                        self.generate_rule_for_dependency(writers, &dependency_target, registry)?;
                         self.output_directory.pop();
                     }
                     _ => {}
@@ -413,22 +507,27 @@ impl MakefileGenerator {
     fn generate_rule_for_dependency(
         &mut self,
         writers: &mut Writers,
-        dependency: &Dependency,
+        dependency_target: &TargetNode,
         registry: &TargetRegistry,
-    ) {
-        let dependency_target = dependency.to_build_target(registry).unwrap();
+    ) -> Result<(), GeneratorError> {
         if dependency_target.borrow().state != TargetState::BuildFileMade {
+            self.generate_phony(&mut writers.makefile_writer, dependency_target, registry);
+            let flags_start = writers.makefile_writer.data.len();
             self.generate_compiler_flags_for_target(
-                &dependency_target,
+                dependency_target,
                 &mut writers.makefile_writer,
             );
+            let flags = writers.makefile_writer.data[flags_start..].to_string();
+            self.write_command_signature(dependency_target, &flags)?;
+            self.generate_linker_flags_for_target(dependency_target, &mut writers.makefile_writer);
             writers.makefile_writer.data.push('\n');
             let rule = LibraryTargetFactory::create_rule(
-                &dependency_target,
+                dependency_target,
                 &self.output_directory,
                 &self.project_config.language,
+                registry,
             );
-            ObjectTarget::create_object_targets(&dependency_target, &self.output_directory)
+            ObjectTarget::create_object_targets(dependency_target, &self.output_directory)
                 .iter()
                 .for_each(|object_target| {
                     if !writers
@@ -443,12 +542,69 @@ impl MakefileGenerator {
                     }
                 });
             writers.makefile_writer.data.push_str(&rule);
-            let progress_tracking_target =
-                ProgressTrackingTarget::from_target(&dependency_target, &self.output_directory);
+            let progress_tracking_target = ProgressTrackingTarget::from_target(
+                dependency_target,
+                &self.output_directory,
+                registry,
+            );
             self.progress_document
                 .add_progress_tracking_target(progress_tracking_target);
             dependency_target.borrow_mut().state = TargetState::BuildFileMade;
         }
+        Ok(())
+    }
+
+    /// Writes the signature of the compile command of `target` to its object directory. The
+    /// objects of the target depend on the signature file, so they are rebuilt when the command
+    /// changes. The file is only written when the signature changes, so that unchanged targets
+    /// are not rebuilt.
+    fn write_command_signature(
+        &mut self,
+        target: &TargetNode,
+        flags: &str,
+    ) -> Result<(), GeneratorError> {
+        let include_directories =
+            ObjectTarget::create_object_targets(target, &self.output_directory)
+                .first()
+                .map(|object_target| {
+                    generate_include_directories(&object_target.include_directories)
+                })
+                .unwrap_or_default();
+        let command = format!(
+            "{}\n{:?}\n{}\n{}",
+            self.include_files_digest, self.project_config.language, include_directories, flags
+        );
+        let path = self.output_directory.join(COMMAND_SIGNATURE_FILE_NAME);
+        if write_signature(&path, &command)? {
+            log::debug!(
+                "Compile command of target \"{}\" changed",
+                target.borrow().identity()
+            );
+        }
+        self.command_signatures
+            .insert(target.borrow().identity(), path);
+        Ok(())
+    }
+
+    /// Digest of the compile variables set by the make include files of the build configuration,
+    /// since they are part of every compile command, and of the identity of the toolchain the
+    /// build directory is configured with, so that changing the toolchain recompiles every object.
+    fn digest_include_files(&self) -> Result<String, GeneratorError> {
+        let include_directory = self.output_directory.join("make_include");
+        let mut content = ToolchainIdentity::load(self.build_directory.as_path())
+            .map(|identity| identity.fingerprint)
+            .unwrap_or_default();
+        for file in [
+            "warnings.mk",
+            "defines.mk",
+            "default_make.mk",
+            self.build_configurations_file(),
+        ] {
+            content.push_str(&compile_variable_assignments(&utility::read_file(
+                &include_directory.join(file),
+            )?));
+        }
+        Ok(utility::sha256_hex(content.as_bytes()))
     }
 
     fn build_configurations_file(&self) -> &str {
@@ -483,7 +639,7 @@ impl MakefileGenerator {
             let mut targets_as_string = String::new();
             for target in targets {
                 targets_as_string.push_str("\\\n");
-                targets_as_string.push_str(&format!("   {}", target.borrow().name()))
+                targets_as_string.push_str(&format!("   {}", rule_name(&target.borrow())))
             }
             targets_as_string
         };
@@ -496,13 +652,20 @@ impl MakefileGenerator {
         writer.data.push_str(&text);
     }
 
-    fn generate_phony(&self, writer: &mut Writer, target: &TargetNode) {
+    fn generate_phony(&self, writer: &mut Writer, target: &TargetNode, registry: &TargetRegistry) {
+        let borrowed_target = target.borrow();
+        let mut phonies = rule_name(&borrowed_target);
+        if has_alias(&borrowed_target, registry) {
+            phonies.push(' ');
+            phonies.push_str(&borrowed_target.name());
+        }
         let data = indoc::formatdoc!(
             "\n
             # Phony for target \"{target_name}\"
-            .PHONY: {target_name}\n
+            .PHONY: {phonies}\n
         ",
-            target_name = target.borrow().name()
+            target_name = borrowed_target.identity(),
+            phonies = phonies
         );
         writer.data.push_str(&data);
     }
@@ -532,10 +695,52 @@ impl MakefileGenerator {
         );
 
         writer.data.push_str(&data);
+        self.generate_launcher(writer)?;
         self.generate_default_all_target(writer, targets);
         Ok(())
     }
 
+    /// Sets the compiler cache that object files are compiled through. The defaults for ccache
+    /// make paths relative to the manifest directory, so that the cache hits across checkouts.
+    /// With the object cache, object files are compiled through `yambs cache-compile`, which
+    /// runs the launcher on a cache miss. The launcher is not part of the command signatures,
+    /// since it does not change the objects.
+    fn generate_launcher(&self, writer: &mut Writer) -> Result<(), GeneratorError> {
+        let toolchain = self.toolchain.borrow();
+        let mut launcher = String::new();
+        if self.project_config.object_cache {
+            let yambs = std::env::current_exe().map_err(GeneratorError::CurrentExecutable)?;
+            launcher.push_str(&format!("{} cache-compile", yambs.display()));
+            if let Some(ref toolchain_launcher) = toolchain.launcher {
+                launcher.push_str(&format!(
+                    " --launcher {}",
+                    toolchain_launcher.path.display()
+                ));
+            }
+            launcher.push_str(" --");
+        } else if let Some(ref toolchain_launcher) = toolchain.launcher {
+            launcher.push_str(&toolchain_launcher.path.display().to_string());
+        }
+        writer.data.push_str("\n# ----- COMPILER LAUNCHER -----\n");
+        writer
+            .data
+            .push_str(format!("LAUNCHER := {}", launcher).trim_end());
+        writer.data.push('\n');
+        if let Some(ref toolchain_launcher) = toolchain.launcher {
+            if toolchain_launcher.is_ccache() {
+                if let Some(manifest_dir) = crate::YAMBS_MANIFEST_DIR.get() {
+                    writer.data.push_str(&format!(
+                        "export CCACHE_BASEDIR ?= {}\n",
+                        manifest_dir.as_path().display()
+                    ));
+                }
+                writer.data.push_str("export CCACHE_NOHASHDIR ?= true\n");
+            }
+        }
+        writer.data.push('\n');
+        Ok(())
+    }
+
     fn generate_include_files(&self) -> Result<(), GeneratorError> {
         let include_output_directory = self.output_directory.join("make_include");
         let toolchain = self.toolchain.borrow();
@@ -563,6 +768,9 @@ impl MakefileGenerator {
                 .data
                 .push_str(&generate_object_target(
                     object_target,
+                    self.command_signatures
+                        .get(&object_target.target)
+                        .map(|path| path.as_path()),
                     &self.project_config.language,
                 ))
         }
@@ -591,18 +799,28 @@ impl MakefileGenerator {
         }
     }
 
-    fn generate_rule_declaration_for_target(&self, writers: &mut Writers, target: &TargetNode) {
-        self.generate_phony(&mut writers.makefile_writer, target);
+    fn generate_rule_declaration_for_target(
+        &mut self,
+        writers: &mut Writers,
+        target: &TargetNode,
+        registry: &TargetRegistry,
+    ) -> Result<(), GeneratorError> {
+        self.generate_phony(&mut writers.makefile_writer, target, registry);
+        let flags_start = writers.makefile_writer.data.len();
         self.generate_compiler_flags_for_target(target, &mut writers.makefile_writer);
+        let flags = writers.makefile_writer.data[flags_start..].to_string();
+        self.write_command_signature(target, &flags)?;
+        self.generate_linker_flags_for_target(target, &mut writers.makefile_writer);
         let target_rule_declaration = TargetRuleFactory::create_rule(
             target,
             &self.output_directory,
             &self.project_config.language,
+            registry,
         );
         writers.makefile_writer.data.push('\n');
         writers.makefile_writer.data.push_str(&format!(
             "# Rule for target \"{}\"\n",
-            target.borrow().name()
+            target.borrow().identity()
         ));
         writers
             .makefile_writer
@@ -610,6 +828,7 @@ impl MakefileGenerator {
             .push_str(&target_rule_declaration);
         writers.makefile_writer.data.push('\n');
         writers.makefile_writer.data.push('\n');
+        Ok(())
     }
 
     fn generate_compiler_flags_for_target(
@@ -618,8 +837,8 @@ impl MakefileGenerator {
         makefile_writer: &mut Writer,
     ) {
         let borrowed_target = target.borrow();
-        let target_name = borrowed_target.name();
-        let target_name_capitalized = target_name.to_uppercase();
+        let target_name = borrowed_target.identity();
+        let target_name_capitalized = variable_prefix(&target_name);
         let cxx_flags = &borrowed_target.compiler_flags.cxx_flags;
         let c_flags = &borrowed_target.compiler_flags.c_flags;
 
@@ -650,6 +869,22 @@ impl MakefileGenerator {
             }
         }
 
+        for conan_target in borrowed_target
+            .dependencies
+            .iter()
+            .filter_map(|dependency| dependency.source.from_conan())
+        {
+            let flags = match self.project_config.language {
+                Language::CXX => &conan_target.cxx_flags,
+                Language::C => &conan_target.c_flags,
+            };
+            for flag in flags {
+                makefile_writer.data.push(' ');
+                makefile_writer.data.push_str(flag);
+                makefile_writer.data.push(' ');
+            }
+        }
+
         for include_dir in &borrowed_target.compiler_flags.include_directories {
             let include_dir = IncludeDirectory {
                 path: include_dir.to_path_buf(),
@@ -697,8 +932,26 @@ impl MakefileGenerator {
 
         makefile_writer.data.push_str(&defines);
 
+        for conan_target in borrowed_target
+            .dependencies
+            .iter()
+            .filter_map(|dependency| dependency.source.from_conan())
+        {
+            for define in &conan_target.defines {
+                makefile_writer.data.push_str(&format!(" -D{}", define));
+            }
+        }
+
         makefile_writer.data.push('\n');
         makefile_writer.data.push('\n');
+    }
+
+    /// Link flags are written separately from the compile flags, since they are not part of the
+    /// command signature of the objects.
+    fn generate_linker_flags_for_target(&self, target: &TargetNode, makefile_writer: &mut Writer) {
+        let borrowed_target = target.borrow();
+        let target_name = borrowed_target.identity();
+        let target_name_capitalized = variable_prefix(&target_name);
         makefile_writer.data.push_str(&indoc::formatdoc!(
             "# LDFLAGS for target \"{target_name}\"
                 {target_name_capitalized}_LDFLAGS +="
@@ -707,7 +960,8 @@ impl MakefileGenerator {
         let deps = &borrowed_target.dependencies;
         for dep in deps {
             match dep.source {
-                DependencySource::FromPkgConfig(ref pkg_config_target) => {
+                DependencySource::FromPkgConfig(ref pkg_config_target)
+                | DependencySource::FromLibraryFiles(ref pkg_config_target) => {
                     match pkg_config_target.method {
                         ProvideMethod::PkgConfigOutput(ref ld_flags) => {
                             let search_flags = ld_flags.link_dirs.join(" ");
@@ -721,6 +975,14 @@ impl MakefileGenerator {
                         _ => {}
                     }
                 }
+                DependencySource::FromConan(ref conan_target) => {
+                    makefile_writer.data.push_str(
+                        &conan_target
+                            .link_flags_for(borrowed_target.library_type().as_ref())
+                            .join(" "),
+                    );
+                    makefile_writer.data.push(' ');
+                }
                 _ => {}
             }
         }
@@ -729,12 +991,63 @@ impl MakefileGenerator {
     }
 }
 
+/// Make variables that are part of compile commands. Other variables, like the archiver or the
+/// linker flags, don't change the objects.
+const COMPILE_VARIABLES: [&str; 5] = ["CC", "CXX", "CFLAGS", "CXXFLAGS", "CPPFLAGS"];
+
+/// The assignments to `COMPILE_VARIABLES` in the make file `content`, without comments and with
+/// continuation lines joined.
+fn compile_variable_assignments(content: &str) -> String {
+    let mut assignments = String::new();
+    let mut statement = String::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        if let Some(continued) = line.trim_end().strip_suffix('\\') {
+            statement.push_str(continued);
+            statement.push(' ');
+            continue;
+        }
+        statement.push_str(line);
+        let variable = statement
+            .trim_start()
+            .split(|c: char| c.is_whitespace() || matches!(c, ':' | '+' | '?' | '='))
+            .next()
+            .unwrap_or_default();
+        if statement.contains('=') && COMPILE_VARIABLES.contains(&variable) {
+            assignments.push_str(
+                &statement
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+            );
+            assignments.push('\n');
+        }
+        statement.clear();
+    }
+    assignments
+}
+
+/// Writes the digest of `command` to `path`, unless the file already holds it, so that the
+/// modification time of the file only changes with the command. Returns whether it was written.
+fn write_signature(path: &std::path::Path, command: &str) -> Result<bool, GeneratorError> {
+    let signature = utility::sha256_hex(command.as_bytes());
+    if std::fs::read_to_string(path).ok().as_deref() == Some(signature.as_str()) {
+        return Ok(false);
+    }
+    std::fs::write(path, &signature).map_err(FsError::WriteToFile)?;
+    Ok(true)
+}
+
 impl Generator for MakefileGenerator {
     fn generate(
         &mut self,
         registry: &TargetRegistry,
     ) -> Result<std::path::PathBuf, GeneratorError> {
+        generator::verify_unique_targets(registry)?;
+        verify_unique_variable_prefixes(registry)?;
+        generator::verify_unique_objects(registry, generator::target_directory)?;
         self.generate_include_files()?;
+        self.include_files_digest = self.digest_include_files()?;
         self.push_and_create_directory(&std::path::PathBuf::from(
             &self.project_config.build_type.to_string(),
         ))?;
@@ -797,3 +1110,102 @@ impl Writer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_depends_on_command_signature() {
+        let object_target = ObjectTarget {
+            target: "app.app".to_string(),
+            object: std::path::PathBuf::from("deps/app.app.dir/main.o"),
+            source: std::path::PathBuf::from("/app/main.cpp"),
+            include_directories: include_directories::IncludeDirectories::new(),
+        };
+        let signature = std::path::PathBuf::from("deps/app.app.dir/command.sig");
+
+        let rule = generate_object_target(
+            &object_target,
+            Some(signature.as_path()),
+            &types::Language::CXX,
+        );
+        assert!(rule.starts_with(
+            "# Build rule for deps/app.app.dir/main.o\n\
+             deps/app.app.dir/main.o: \\\n\
+             \t/app/main.cpp \\\n\
+             \tdeps/app.app.dir/command.sig\n"
+        ));
+        assert!(rule.contains("$(strip $(LAUNCHER) $(CXX) "));
+        assert!(rule.contains("$(APP_APP_CXXFLAGS)"));
+        assert!(rule.contains(" $< -c -o $@"));
+
+        let rule = generate_object_target(&object_target, None, &types::Language::CXX);
+        assert!(rule.contains("deps/app.app.dir/main.o: \\\n\t/app/main.cpp\n"));
+    }
+
+    #[test]
+    fn only_compile_variables_are_part_of_the_command() {
+        let defines = |ar: &str, cxx: &str| {
+            compile_variable_assignments(&format!(
+                "# Toolchain definitions\n\
+                 CC := gcc\n\
+                 CXX := {}\n\
+                 AR := {}\n\
+                 CXX_LDFLAGS += -fuse-ld=mold\n\
+                 CXXFLAGS += -pthread\\\n\
+                 \x20           -fPIC # Position independent code.\n\
+                 ARFLAGS = rs\n",
+                cxx, ar
+            ))
+        };
+        assert_eq!(
+            defines("/usr/bin/ar", "g++"),
+            "CC := gcc\nCXX := g++\nCXXFLAGS += -pthread -fPIC\n"
+        );
+        assert_eq!(
+            defines("/usr/bin/ar", "g++"),
+            defines("/usr/bin/llvm-ar", "g++")
+        );
+        assert_ne!(
+            defines("/usr/bin/ar", "g++"),
+            defines("/usr/bin/ar", "clang++")
+        );
+    }
+
+    #[test]
+    fn signature_is_only_rewritten_when_the_command_changes() {
+        let dir = tempdir::TempDir::new("signature").unwrap();
+        let path = dir.path().join(COMMAND_SIGNATURE_FILE_NAME);
+        let command = "CXXFLAGS += -O0\n-Iinclude\n-Wall";
+        assert!(write_signature(&path, command).unwrap());
+        let signature = std::fs::read_to_string(&path).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        assert!(!write_signature(&path, command).unwrap());
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().modified().unwrap(),
+            modified
+        );
+
+        assert!(write_signature(&path, "CXXFLAGS += -O2\n-Iinclude\n-Wall").unwrap());
+        assert_ne!(std::fs::read_to_string(&path).unwrap(), signature);
+    }
+
+    #[test]
+    fn targets_with_same_variable_prefix_are_rejected() {
+        let library = |directory: &str, project: &str| {
+            build_target::BuildTarget::library_fixture(directory, "utils", LibraryType::Static)
+                .with_project(project)
+                .into_node()
+        };
+        let registry =
+            TargetRegistry::from_targets(&[library("/one", "a-b"), library("/two", "a.b")]);
+        assert!(generator::verify_unique_targets(&registry).is_ok());
+        assert!(matches!(
+            verify_unique_variable_prefixes(&registry),
+            Err(GeneratorError::VariableCollision(ref first, ref second, ref prefix))
+                if first == "utils@/one" && second == "utils@/two" && prefix == "A_B_UTILS"
+        ));
+    }
+}
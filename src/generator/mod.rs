@@ -1,4 +1,7 @@
-use crate::build_target::{target_registry::TargetRegistry, TargetError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::build_target::{target_registry::TargetRegistry, BuildTarget, TargetError, TargetType};
 use crate::errors::FsError;
 
 #[cfg(target_os = "linux")]
@@ -18,6 +21,93 @@ pub enum GeneratorError {
     CreateRule,
     #[error("Could not find any standards to use when generating build files")]
     StandardNotFound,
+    #[error("Targets {0} and {1} have the same identity {2}. Give their projects different names")]
+    IdentityCollision(String, String, String),
+    #[error("Targets {0} and {1} both build {2}. Rename one of the libraries")]
+    ArtifactCollision(String, String, String),
+    #[error(
+        "Targets {0} and {1} both name their build file variables {2}. Rename one of the targets"
+    )]
+    VariableCollision(String, String, String),
+    #[error("Source {1:?} of target {0} and source {3:?} of target {2} are both compiled to {4:?}. Rename one of the sources")]
+    ObjectCollision(String, PathBuf, String, PathBuf, PathBuf),
+    #[error("Failed to locate the yambs executable for the object cache")]
+    CurrentExecutable(#[source] std::io::Error),
+}
+
+/// Verifies that no two targets of `registry` share an identity or build the same file, since
+/// their rules would silently override each other.
+pub fn verify_unique_targets(registry: &TargetRegistry) -> Result<(), GeneratorError> {
+    for (index, target) in registry.registry.iter().enumerate() {
+        let target = target.borrow();
+        for other in &registry.registry[index + 1..] {
+            let other = other.borrow();
+            if target.identity() == other.identity() {
+                return Err(GeneratorError::IdentityCollision(
+                    target.qualified_name(),
+                    other.qualified_name(),
+                    target.identity(),
+                ));
+            }
+            if artifact(&target) == artifact(&other) {
+                return Err(GeneratorError::ArtifactCollision(
+                    target.qualified_name(),
+                    other.qualified_name(),
+                    artifact(&target).display().to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Verifies that no two sources of `registry` are compiled to the same object file, like
+/// `util.c` and `util.cpp` in the same directory. The objects of every target are placed in the
+/// directory `object_directory` gives for it.
+pub fn verify_unique_objects<F>(
+    registry: &TargetRegistry,
+    object_directory: F,
+) -> Result<(), GeneratorError>
+where
+    F: Fn(&BuildTarget) -> PathBuf,
+{
+    let mut sources_by_object = HashMap::<PathBuf, (String, PathBuf)>::new();
+    for target in &registry.registry {
+        let target_name = target.borrow().qualified_name();
+        let output_directory = object_directory(&target.borrow());
+        for object_target in targets::ObjectTarget::create_object_targets(target, &output_directory)
+        {
+            let object = crate::utility::normalize_path(&object_target.object);
+            if let Some((other_target, other_source)) = sources_by_object.get(&object) {
+                return Err(GeneratorError::ObjectCollision(
+                    other_target.clone(),
+                    other_source.clone(),
+                    target_name,
+                    object_target.source,
+                    object,
+                ));
+            }
+            sources_by_object.insert(object, (target_name.clone(), object_target.source));
+        }
+    }
+    Ok(())
+}
+
+/// Directory of the target directories, relative to the build files.
+pub const TARGETS_DIRECTORY: &str = "deps";
+
+/// The directory of the object files and library of `target`, relative to the build files.
+pub fn target_directory(target: &BuildTarget) -> PathBuf {
+    Path::new(TARGETS_DIRECTORY).join(format!("{}.dir", target.identity()))
+}
+
+/// The file `target` builds, relative to the build files. Libraries are placed in their target
+/// directory, so that libraries with the same name in different projects don't collide.
+pub fn artifact(target: &BuildTarget) -> PathBuf {
+    match target.target_type {
+        TargetType::Executable(ref executable) => PathBuf::from(executable.to_string()),
+        TargetType::Library(ref library) => target_directory(target).join(library.to_string()),
+    }
 }
 
 #[derive(clap::ValueEnum, Debug, Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -49,6 +139,7 @@ pub trait UtilityGenerator<'config> {
 
 pub mod targets {
     use crate::build_target::include_directories::IncludeDirectories;
+    use crate::build_target::target_registry::TargetRegistry;
     use crate::build_target::{DependencySource, TargetNode};
 
     #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -70,7 +161,11 @@ pub mod targets {
 
     #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
     pub struct ProgressTrackingTarget {
+        /// The identity of the target.
         pub target: String,
+        /// The name of the target, which can be used in place of the identity.
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        pub name: String,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         pub object_files: Vec<std::path::PathBuf>,
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -78,32 +173,37 @@ pub mod targets {
     }
 
     impl ProgressTrackingTarget {
-        pub fn from_target(target_node: &TargetNode, output_directory: &std::path::Path) -> Self {
+        pub fn from_target(
+            target_node: &TargetNode,
+            output_directory: &std::path::Path,
+            registry: &TargetRegistry,
+        ) -> Self {
             let target_object_targets =
                 ObjectTarget::create_object_targets(target_node, output_directory)
                     .iter()
                     .map(|o| o.object.to_path_buf())
                     .collect::<Vec<std::path::PathBuf>>();
-            let target_name = target_node.borrow().name();
             let target_dependencies = target_node
                 .borrow()
                 .dependencies
                 .iter()
-                .filter_map(|d| match d.source {
-                    DependencySource::FromSource(ref ds) => Some(ds),
-                    _ => None,
-                })
-                .map(|ds| ds.library.name.to_owned())
+                .filter(|d| d.source.from_source().is_some())
+                .filter_map(|d| d.to_build_target(registry))
+                .map(|dependency| dependency.borrow().identity())
                 .collect::<Vec<String>>();
 
             Self {
-                target: target_name,
+                target: target_node.borrow().identity(),
+                name: target_node.borrow().name(),
                 object_files: target_object_targets,
                 dependencies: target_dependencies,
             }
         }
     }
 
+    /// Directory of the object files of sources outside of the manifest directory.
+    pub const EXTERNAL_OBJECTS_DIRECTORY: &str = "external";
+
     #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
     pub struct ObjectTarget {
         pub target: String,
@@ -113,6 +213,33 @@ pub mod targets {
     }
 
     impl ObjectTarget {
+        /// The object file `source` is compiled to. Sources in the manifest directory mirror
+        /// their path relative to it. Sources outside of it are placed in `external`, in a
+        /// directory named by a hash of the directory of the source, so that sources with the
+        /// same name in different directories get different object files. `..` in the path of
+        /// the source is resolved first, so no object is placed outside of `output_directory`.
+        pub fn object_path(
+            source: &std::path::Path,
+            manifest_directory: &std::path::Path,
+            output_directory: &std::path::Path,
+        ) -> std::path::PathBuf {
+            let source = crate::utility::normalize_path(source);
+            let manifest_directory = crate::utility::normalize_path(manifest_directory);
+            let file_name = source.file_name().unwrap_or_default();
+            let source_directory = source.parent().unwrap_or_else(|| std::path::Path::new(""));
+            let object_directory = match source_directory.strip_prefix(&manifest_directory) {
+                Ok(relative_directory) => output_directory.join(relative_directory),
+                Err(_) => {
+                    let hash =
+                        crate::utility::sha256_hex(source_directory.to_string_lossy().as_bytes());
+                    output_directory
+                        .join(EXTERNAL_OBJECTS_DIRECTORY)
+                        .join(&hash[..16])
+                }
+            };
+            object_directory.join(file_name).with_extension("o")
+        }
+
         pub fn create_object_targets(
             target: &TargetNode,
             output_directory: &std::path::Path,
@@ -124,24 +251,12 @@ pub mod targets {
                 .iter()
                 .filter(|file| file.is_source());
             let dependency_root_path = &borrowed_target.manifest.directory;
-            let target_name = borrowed_target.name();
+            let target_identity = borrowed_target.identity();
 
             for source in sources {
                 let source_file = source.file();
-                let source_dir = source_file
-                    .parent()
-                    .and_then(|p| p.strip_prefix(dependency_root_path).ok());
-
-                let object = {
-                    if let Some(dir) = source_dir {
-                        output_directory
-                            .join(dir)
-                            .join(source_file.file_name().unwrap())
-                    } else {
-                        output_directory.join(source_file.file_name().unwrap())
-                    }
-                }
-                .with_extension("o");
+                let object =
+                    Self::object_path(&source_file, dependency_root_path, output_directory);
                 let include_directories = {
                     let mut include_directories = IncludeDirectories::new();
                     include_directories.add(borrowed_target.include_directory.clone());
@@ -155,18 +270,24 @@ pub mod targets {
                             DependencySource::FromHeaderOnly(ref hd) => {
                                 include_directories.add(hd.include_directory.clone());
                             }
-                            DependencySource::FromPkgConfig(ref pkg) => {
+                            DependencySource::FromPkgConfig(ref pkg)
+                            | DependencySource::FromLibraryFiles(ref pkg) => {
                                 for dir in &pkg.include_directories {
                                     include_directories.add(dir.clone());
                                 }
                             }
+                            DependencySource::FromConan(ref conan) => {
+                                for dir in &conan.include_directories {
+                                    include_directories.add(dir.clone());
+                                }
+                            }
                         }
                     }
                     include_directories
                 };
 
                 let object_target = ObjectTarget {
-                    target: target_name.clone(),
+                    target: target_identity.clone(),
                     object,
                     source: source_file,
                     include_directories,
@@ -178,3 +299,183 @@ pub mod targets {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::build_target::associated_files::SourceFiles;
+    use crate::build_target::{BuildTarget, LibraryType, TargetNode};
+
+    /// A library named `utils` in `project`.
+    fn utils(directory: &str, project: &str, ty: LibraryType) -> TargetNode {
+        BuildTarget::library_fixture(directory, "utils", ty)
+            .with_project(project)
+            .into_node()
+    }
+
+    #[test]
+    fn identity_is_qualified_with_project_or_manifest_directory() {
+        let named = utils("/src/one", "my project", LibraryType::Static);
+        let unnamed =
+            BuildTarget::library_fixture("/src/two", "utils", LibraryType::Static).into_node();
+        assert_eq!(named.borrow().identity(), "my_project.utils");
+        assert_eq!(unnamed.borrow().identity(), "two.utils");
+    }
+
+    #[test]
+    fn targets_with_same_identity_or_artifact_are_rejected() {
+        let registry = TargetRegistry::from_targets(&[
+            utils("/one", "one", LibraryType::Static),
+            utils("/two", "two", LibraryType::Dynamic),
+        ]);
+        assert!(verify_unique_targets(&registry).is_ok());
+
+        let registry = TargetRegistry::from_targets(&[
+            utils("/one", "project", LibraryType::Static),
+            utils("/two", "project", LibraryType::Dynamic),
+        ]);
+        assert!(matches!(
+            verify_unique_targets(&registry),
+            Err(GeneratorError::IdentityCollision(_, _, ref identity)) if identity == "project.utils"
+        ));
+
+        // Libraries are built in their target directories.
+        let registry = TargetRegistry::from_targets(&[
+            utils("/one", "one", LibraryType::Static),
+            utils("/two", "two", LibraryType::Static),
+        ]);
+        assert!(verify_unique_targets(&registry).is_ok());
+        assert_eq!(
+            artifact(&registry.registry[0].borrow()),
+            Path::new("deps").join("one.utils.dir").join("libutils.a")
+        );
+
+        let executable = |directory: &str, project: &str| {
+            BuildTarget::executable_fixture(directory, "app")
+                .with_project(project)
+                .into_node()
+        };
+        let registry =
+            TargetRegistry::from_targets(&[executable("/one", "one"), executable("/two", "two")]);
+        assert!(matches!(
+            verify_unique_targets(&registry),
+            Err(GeneratorError::ArtifactCollision(ref first, ref second, ref artifact))
+                if first == "app@/one" && second == "app@/two" && artifact == "app"
+        ));
+    }
+
+    #[test]
+    fn objects_of_sources_outside_of_manifest_directory_do_not_collide() {
+        let output = Path::new("/build/deps/app.app.dir");
+        assert_eq!(
+            targets::ObjectTarget::object_path(
+                Path::new("/app/src/main.cpp"),
+                Path::new("/app"),
+                output
+            ),
+            output.join("src").join("main.o")
+        );
+
+        let first = targets::ObjectTarget::object_path(
+            Path::new("/shared/a/util.cpp"),
+            Path::new("/app"),
+            output,
+        );
+        let second = targets::ObjectTarget::object_path(
+            Path::new("/shared/b/util.cpp"),
+            Path::new("/app"),
+            output,
+        );
+        assert_ne!(first, second);
+        assert!(first.starts_with(output.join(targets::EXTERNAL_OBJECTS_DIRECTORY)));
+        assert_eq!(first.file_name().unwrap(), "util.o");
+    }
+
+    #[test]
+    fn objects_of_sources_with_parent_directories_stay_in_output_directory() {
+        let output = Path::new("/build/deps/app.app.dir");
+        assert_eq!(
+            targets::ObjectTarget::object_path(
+                Path::new("/app/src/../lib/./util.cpp"),
+                Path::new("/app"),
+                output
+            ),
+            output.join("lib").join("util.o")
+        );
+
+        let escaping = targets::ObjectTarget::object_path(
+            Path::new("/app/../other/util.cpp"),
+            Path::new("/app"),
+            output,
+        );
+        assert!(escaping.starts_with(output.join(targets::EXTERNAL_OBJECTS_DIRECTORY)));
+        assert_eq!(
+            escaping,
+            targets::ObjectTarget::object_path(
+                Path::new("/other/util.cpp"),
+                Path::new("/app"),
+                output
+            )
+        );
+    }
+
+    #[test]
+    fn sources_compiled_to_same_object_are_rejected() {
+        let dir = tempdir::TempDir::new("objects").unwrap();
+        let sources = [
+            dir.path().join("util.c"),
+            dir.path().join("util.cpp"),
+            dir.path().join("src").join("..").join("util.cpp"),
+        ];
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        for source in &sources {
+            std::fs::write(source, "").unwrap();
+        }
+        let target = BuildTarget::library_fixture(
+            dir.path().to_str().unwrap(),
+            "utils",
+            LibraryType::Static,
+        )
+        .into_node();
+        target.borrow_mut().source_files = SourceFiles::from_paths(&sources[..1]).unwrap();
+        let registry = TargetRegistry::from_targets(std::slice::from_ref(&target));
+        assert!(verify_unique_objects(&registry, target_directory).is_ok());
+
+        target.borrow_mut().source_files = SourceFiles::from_paths(&sources[..2]).unwrap();
+        let expected_object = target_directory(&target.borrow()).join("util.o");
+        assert!(matches!(
+            verify_unique_objects(&registry, target_directory),
+            Err(GeneratorError::ObjectCollision(_, ref first, _, ref second, ref object))
+                if *first == sources[0] && *second == sources[1] && *object == expected_object
+        ));
+
+        target.borrow_mut().source_files =
+            SourceFiles::from_paths(&[sources[0].clone(), sources[2].clone()]).unwrap();
+        assert!(matches!(
+            verify_unique_objects(&registry, target_directory),
+            Err(GeneratorError::ObjectCollision(..))
+        ));
+    }
+
+    #[test]
+    fn objects_of_different_targets_are_compared() {
+        let dir = tempdir::TempDir::new("objects").unwrap();
+        let source = dir.path().join("util.cpp");
+        std::fs::write(&source, "").unwrap();
+        let first = utils(dir.path().to_str().unwrap(), "one", LibraryType::Static);
+        let second = utils(dir.path().to_str().unwrap(), "two", LibraryType::Static);
+        for target in [&first, &second] {
+            target.borrow_mut().source_files =
+                SourceFiles::from_paths(std::slice::from_ref(&source)).unwrap();
+        }
+        let registry = TargetRegistry::from_targets(&[first, second]);
+        assert!(verify_unique_objects(&registry, target_directory).is_ok());
+
+        assert!(matches!(
+            verify_unique_objects(&registry, |_| PathBuf::from("objects")),
+            Err(GeneratorError::ObjectCollision(..))
+        ));
+    }
+}
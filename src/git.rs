@@ -0,0 +1,385 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::parser::types::GitData;
+
+pub const GIT_CACHE_DIRECTORY: &str = "git";
+const CHECKOUT_MARKER: &str = ".yambs-checkout";
+
+#[derive(thiserror::Error, Debug)]
+pub enum GitError {
+    #[error("Could not find git executable")]
+    CouldNotFindGit,
+    #[error("Failed to locate user's HOME directory")]
+    NoHomeDirectory,
+    #[error("Failed to run git")]
+    FailedToRunGit(#[source] std::io::Error),
+    #[error("'git {0}' failed: {1}")]
+    GitFailedWithError(String, String),
+    #[error("Only one of rev, tag and branch can be specified for git repository {0}")]
+    AmbiguousReference(String),
+    #[error("Could not find {0} in git repository {1}")]
+    ReferenceNotFound(String, String),
+    #[error("Failed to prepare directory {0:?}")]
+    PrepareDirectory(PathBuf, #[source] std::io::Error),
+}
+
+/// The reference of a git repository to check out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitReference {
+    Rev(String),
+    Tag(String),
+    Branch(String),
+    DefaultBranch,
+}
+
+impl GitReference {
+    pub fn from_data(data: &GitData) -> Result<Self, GitError> {
+        match (&data.rev, &data.tag, &data.branch) {
+            (Some(rev), None, None) => Ok(Self::Rev(rev.clone())),
+            (None, Some(tag), None) => Ok(Self::Tag(tag.clone())),
+            (None, None, Some(branch)) => Ok(Self::Branch(branch.clone())),
+            (None, None, None) => Ok(Self::DefaultBranch),
+            _ => Err(GitError::AmbiguousReference(data.git.clone())),
+        }
+    }
+
+    fn rev_spec(&self) -> String {
+        match self {
+            Self::Rev(rev) => format!("{}^{{commit}}", rev),
+            Self::Tag(tag) => format!("refs/tags/{}^{{commit}}", tag),
+            Self::Branch(branch) => format!("refs/heads/{}^{{commit}}", branch),
+            Self::DefaultBranch => "HEAD^{commit}".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for GitReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rev(rev) => write!(f, "revision {}", rev),
+            Self::Tag(tag) => write!(f, "tag {}", tag),
+            Self::Branch(branch) => write!(f, "branch {}", branch),
+            Self::DefaultBranch => write!(f, "the default branch"),
+        }
+    }
+}
+
+/// A cache of git repositories shared between projects.
+///
+/// Every repository is kept as a bare clone in `db/<name>-<hash>`, from which each requested
+/// commit is checked out to `checkouts/<name>-<hash>/<commit>`. Checkouts never change once
+/// created, so they can be used like a `path` dependency.
+#[derive(Debug, Clone)]
+pub struct GitCache {
+    directory: PathBuf,
+    git: PathBuf,
+}
+
+impl GitCache {
+    pub fn new(directory: &Path) -> Result<Self, GitError> {
+        let git = crate::find_program(
+            Path::new("git"),
+            crate::FindProgramOptions::new().with_path_env().clone(),
+        )
+        .ok_or(GitError::CouldNotFindGit)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            git,
+        })
+    }
+
+    /// The cache located in `~/.yambs/git`.
+    pub fn from_home() -> Result<Self, GitError> {
+        let home_dir = home::home_dir().ok_or(GitError::NoHomeDirectory)?;
+        Self::new(&home_dir.join(".yambs").join(GIT_CACHE_DIRECTORY))
+    }
+
+    /// Resolves `reference` of the repository at `url` to a commit hash.
+    ///
+    /// The repository is fetched, so the latest commit of a branch is found.
+    pub fn resolve(&self, url: &str, reference: &GitReference) -> Result<String, GitError> {
+        let database = self.database(url)?;
+        self.fetch(url, &database)?;
+        self.rev_parse(&database, &reference.rev_spec())
+            .ok_or_else(|| GitError::ReferenceNotFound(reference.to_string(), url.to_string()))
+    }
+
+    /// Checks out `commit` of the repository at `url` and returns the directory of the checkout.
+    ///
+    /// The repository is only fetched if the commit is not already in the cache.
+    pub fn checkout(&self, url: &str, commit: &str) -> Result<PathBuf, GitError> {
+        let database = self.database(url)?;
+        if self
+            .rev_parse(&database, &format!("{}^{{commit}}", commit))
+            .is_none()
+        {
+            self.fetch(url, &database)?;
+        }
+        let commit = self
+            .rev_parse(&database, &format!("{}^{{commit}}", commit))
+            .ok_or_else(|| GitError::ReferenceNotFound(commit.to_string(), url.to_string()))?;
+
+        let checkout = self
+            .directory
+            .join("checkouts")
+            .join(identifier(url))
+            .join(&commit[..12]);
+        if checkout.join(CHECKOUT_MARKER).is_file() {
+            log::debug!("Using existing checkout {}", checkout.display());
+            return Ok(checkout);
+        }
+        if checkout.is_dir() {
+            std::fs::remove_dir_all(&checkout)
+                .map_err(|err| GitError::PrepareDirectory(checkout.clone(), err))?;
+        }
+        log::debug!(
+            "Checking out commit {} of {} to {}",
+            commit,
+            url,
+            checkout.display()
+        );
+        self.run(
+            None,
+            &[
+                "clone".as_ref(),
+                "--quiet".as_ref(),
+                "--shared".as_ref(),
+                "--no-checkout".as_ref(),
+                database.as_os_str(),
+                checkout.as_os_str(),
+            ],
+        )?;
+        self.run(
+            Some(&checkout),
+            &[
+                "checkout".as_ref(),
+                "--quiet".as_ref(),
+                "--detach".as_ref(),
+                commit.as_ref(),
+            ],
+        )?;
+        std::fs::write(checkout.join(CHECKOUT_MARKER), &commit)
+            .map_err(|err| GitError::PrepareDirectory(checkout.clone(), err))?;
+        Ok(checkout)
+    }
+
+    fn database(&self, url: &str) -> Result<PathBuf, GitError> {
+        let database = self.directory.join("db").join(identifier(url));
+        if !database.is_dir() {
+            log::debug!("Cloning {} to {}", url, database.display());
+            let parent = database.parent().unwrap();
+            std::fs::create_dir_all(parent)
+                .map_err(|err| GitError::PrepareDirectory(parent.to_path_buf(), err))?;
+            self.run(
+                None,
+                &[
+                    "clone".as_ref(),
+                    "--quiet".as_ref(),
+                    "--bare".as_ref(),
+                    url.as_ref(),
+                    database.as_os_str(),
+                ],
+            )?;
+        }
+        Ok(database)
+    }
+
+    fn fetch(&self, url: &str, database: &Path) -> Result<(), GitError> {
+        log::debug!("Fetching {}", url);
+        self.run(
+            Some(database),
+            &[
+                "fetch".as_ref(),
+                "--quiet".as_ref(),
+                "--force".as_ref(),
+                "--tags".as_ref(),
+                url.as_ref(),
+                "+refs/heads/*:refs/heads/*".as_ref(),
+            ],
+        )
+        .map(|_| ())
+    }
+
+    fn rev_parse(&self, repository: &Path, rev_spec: &str) -> Option<String> {
+        self.run(
+            Some(repository),
+            &[
+                "rev-parse".as_ref(),
+                "--quiet".as_ref(),
+                "--verify".as_ref(),
+                rev_spec.as_ref(),
+            ],
+        )
+        .ok()
+        .map(|stdout| stdout.trim().to_string())
+    }
+
+    fn run(&self, directory: Option<&Path>, args: &[&std::ffi::OsStr]) -> Result<String, GitError> {
+        let mut command = Command::new(&self.git);
+        if let Some(directory) = directory {
+            command.arg("-C").arg(directory);
+        }
+        let output = command
+            .args(args)
+            .env_remove("GIT_DIR")
+            .output()
+            .map_err(GitError::FailedToRunGit)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let args = args
+                .iter()
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            Err(GitError::GitFailedWithError(
+                args,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+}
+
+/// A file system friendly identifier of a repository URL, unique for each URL.
+fn identifier(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    let hash = &crate::utility::sha256_hex(url.as_bytes())[..16];
+    if name.is_empty() {
+        hash.to_string()
+    } else {
+        format!("{}-{}", name, hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(directory: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "yambs")
+            .env("GIT_AUTHOR_EMAIL", "yambs@example.com")
+            .env("GIT_COMMITTER_NAME", "yambs")
+            .env("GIT_COMMITTER_EMAIL", "yambs@example.com")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn commit(repository: &Path, file: &str, content: &str) -> String {
+        std::fs::write(repository.join(file), content).unwrap();
+        git(repository, &["add", file]);
+        git(repository, &["commit", "--quiet", "-m", content]);
+        git(repository, &["rev-parse", "HEAD"])
+    }
+
+    /// A bare repository with two commits on main, where the first is tagged v1.0.
+    fn bare_repository(directory: &Path) -> (String, String, String) {
+        let work = directory.join("work");
+        std::fs::create_dir_all(&work).unwrap();
+        git(&work, &["init", "--quiet", "--initial-branch=main"]);
+        let first = commit(&work, "file.txt", "first");
+        git(&work, &["tag", "v1.0"]);
+        let second = commit(&work, "file.txt", "second");
+        let bare = directory.join("repo.git");
+        git(
+            directory,
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                work.to_str().unwrap(),
+                bare.to_str().unwrap(),
+            ],
+        );
+        (format!("file://{}", bare.display()), first, second)
+    }
+
+    #[test]
+    fn resolves_references_of_bare_repository() {
+        let dir = tempdir::TempDir::new("git").unwrap();
+        let (url, first, second) = bare_repository(dir.path());
+        let cache = GitCache::new(&dir.path().join("cache")).unwrap();
+
+        assert_eq!(
+            cache.resolve(&url, &GitReference::DefaultBranch).unwrap(),
+            second
+        );
+        assert_eq!(
+            cache
+                .resolve(&url, &GitReference::Branch("main".to_string()))
+                .unwrap(),
+            second
+        );
+        assert_eq!(
+            cache
+                .resolve(&url, &GitReference::Tag("v1.0".to_string()))
+                .unwrap(),
+            first
+        );
+        assert_eq!(
+            cache
+                .resolve(&url, &GitReference::Rev(first[..7].to_string()))
+                .unwrap(),
+            first
+        );
+        assert!(matches!(
+            cache.resolve(&url, &GitReference::Tag("v2.0".to_string())),
+            Err(GitError::ReferenceNotFound(..))
+        ));
+    }
+
+    #[test]
+    fn checks_out_requested_commit() {
+        let dir = tempdir::TempDir::new("git").unwrap();
+        let (url, first, second) = bare_repository(dir.path());
+        let cache = GitCache::new(&dir.path().join("cache")).unwrap();
+
+        let first_checkout = cache.checkout(&url, &first).unwrap();
+        let second_checkout = cache.checkout(&url, &second).unwrap();
+        assert_ne!(first_checkout, second_checkout);
+        assert_eq!(
+            std::fs::read_to_string(first_checkout.join("file.txt")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            std::fs::read_to_string(second_checkout.join("file.txt")).unwrap(),
+            "second"
+        );
+        assert_eq!(cache.checkout(&url, &first).unwrap(), first_checkout);
+    }
+
+    #[test]
+    fn reference_must_be_unambiguous() {
+        let data = GitData {
+            git: "https://example.com/repo.git".to_string(),
+            rev: None,
+            tag: Some("v1.0".to_string()),
+            branch: Some("main".to_string()),
+            origin: Default::default(),
+            version: None,
+        };
+        assert!(matches!(
+            GitReference::from_data(&data),
+            Err(GitError::AmbiguousReference(_))
+        ));
+    }
+
+    #[test]
+    fn identifier_is_readable_and_unique() {
+        let first = identifier("https://example.com/foo/repo.git");
+        let second = identifier("https://example.com/bar/repo.git");
+        assert!(first.starts_with("repo-"));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,597 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::build_target::target_registry::TargetRegistry;
+use crate::build_target::{BuildTarget, DependencySource, LibraryType, TargetType};
+
+pub const CMAKE_DIRECTORY: &str = "cmake";
+
+/// A library exported to CMake as an imported target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CMakeImportedTarget {
+    pub name: String,
+    pub library_type: LibraryType,
+    /// File name of the library, relative to the `lib` directory of the installation.
+    pub location: String,
+    pub link_libraries: Vec<String>,
+    pub compile_options: Vec<String>,
+}
+
+/// A library of a dependency that is not part of the package. It is looked up with `find_library`
+/// where the package is used, instead of being linked by its path on the build machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CMakeFoundLibrary {
+    /// The variable the library is found into.
+    pub variable: String,
+    /// The name of the library, like `z` or `libz.a`.
+    pub name: String,
+    /// Directories the library was found in on the build machine.
+    pub hints: Vec<String>,
+}
+
+/// The CMake package configuration of an installed project.
+///
+/// Renders `<name>Config.cmake` and `<name>ConfigVersion.cmake`, which are installed to
+/// `lib/cmake/<name>` so that CMake projects can `find_package(<name>)` and link against
+/// `<name>::<library>`. Paths are computed from the location of the config file, so the
+/// installation stays relocatable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CMakePackageConfig {
+    pub name: String,
+    pub version: semver::Version,
+    pub targets: Vec<CMakeImportedTarget>,
+    pub pkg_config_modules: Vec<String>,
+    pub found_libraries: Vec<CMakeFoundLibrary>,
+}
+
+impl CMakePackageConfig {
+    pub fn from_registry(name: &str, version: &semver::Version, registry: &TargetRegistry) -> Self {
+        let mut targets = Vec::new();
+        let mut pkg_config_modules = Vec::new();
+        let mut found_libraries = Vec::new();
+
+        for target in &registry.registry {
+            let borrowed_target = target.borrow();
+            if let Some(imported_target) = imported_target_from(
+                name,
+                &borrowed_target,
+                &mut pkg_config_modules,
+                &mut found_libraries,
+            ) {
+                targets.push(imported_target);
+            }
+        }
+        targets.sort_by(|a, b| a.name.cmp(&b.name));
+        pkg_config_modules.sort();
+        pkg_config_modules.dedup();
+        found_libraries.sort_by(|a, b| a.variable.cmp(&b.variable));
+
+        Self {
+            name: name.to_string(),
+            version: version.clone(),
+            targets,
+            pkg_config_modules,
+            found_libraries,
+        }
+    }
+
+    pub fn config_file_name(&self) -> String {
+        format!("{}Config.cmake", self.name)
+    }
+
+    pub fn version_file_name(&self) -> String {
+        format!("{}ConfigVersion.cmake", self.name)
+    }
+
+    pub fn config(&self) -> String {
+        let mut config = String::new();
+        writeln!(config, "# Generated by yambs. DO NOT EDIT.").unwrap();
+        writeln!(
+            config,
+            "get_filename_component(_IMPORT_PREFIX \"${{CMAKE_CURRENT_LIST_DIR}}/../../..\" ABSOLUTE)"
+        )
+        .unwrap();
+
+        if !self.pkg_config_modules.is_empty() {
+            writeln!(config).unwrap();
+            writeln!(config, "include(CMakeFindDependencyMacro)").unwrap();
+            writeln!(config, "find_dependency(PkgConfig)").unwrap();
+            for module in &self.pkg_config_modules {
+                writeln!(
+                    config,
+                    "pkg_check_modules({} REQUIRED IMPORTED_TARGET {})",
+                    module, module
+                )
+                .unwrap();
+            }
+        }
+
+        if !self.found_libraries.is_empty() {
+            writeln!(config).unwrap();
+            for library in &self.found_libraries {
+                let hints = library
+                    .hints
+                    .iter()
+                    .map(|hint| format!(" \"{}\"", hint))
+                    .collect::<String>();
+                let hints = if hints.is_empty() {
+                    hints
+                } else {
+                    format!(" HINTS{}", hints)
+                };
+                writeln!(
+                    config,
+                    "find_library({} NAMES {}{} REQUIRED)",
+                    library.variable, library.name, hints
+                )
+                .unwrap();
+            }
+        }
+
+        for target in &self.targets {
+            let library_type = match target.library_type {
+                LibraryType::Static => "STATIC",
+                LibraryType::Dynamic => "SHARED",
+            };
+            writeln!(config).unwrap();
+            writeln!(config, "if(NOT TARGET {})", target.name).unwrap();
+            writeln!(
+                config,
+                "  add_library({} {} IMPORTED)",
+                target.name, library_type
+            )
+            .unwrap();
+            writeln!(config, "  set_target_properties({} PROPERTIES", target.name).unwrap();
+            writeln!(
+                config,
+                "    IMPORTED_LOCATION \"${{_IMPORT_PREFIX}}/lib/{}\"",
+                target.location
+            )
+            .unwrap();
+            if target.library_type == LibraryType::Dynamic {
+                writeln!(config, "    IMPORTED_NO_SONAME TRUE").unwrap();
+            }
+            writeln!(
+                config,
+                "    INTERFACE_INCLUDE_DIRECTORIES \"${{_IMPORT_PREFIX}}/include\""
+            )
+            .unwrap();
+            if !target.link_libraries.is_empty() {
+                writeln!(
+                    config,
+                    "    INTERFACE_LINK_LIBRARIES \"{}\"",
+                    target.link_libraries.join(";")
+                )
+                .unwrap();
+            }
+            if !target.compile_options.is_empty() {
+                writeln!(
+                    config,
+                    "    INTERFACE_COMPILE_OPTIONS \"{}\"",
+                    target.compile_options.join(";")
+                )
+                .unwrap();
+            }
+            writeln!(config, "  )").unwrap();
+            writeln!(config, "endif()").unwrap();
+        }
+
+        writeln!(config).unwrap();
+        writeln!(config, "unset(_IMPORT_PREFIX)").unwrap();
+        config
+    }
+
+    /// Compatible with requests for the same major version that are not newer than the
+    /// installed version, like CMake's `SameMajorVersion`.
+    pub fn config_version(&self) -> String {
+        indoc::formatdoc!(
+            "
+            # Generated by yambs. DO NOT EDIT.
+            set(PACKAGE_VERSION \"{version}\")
+
+            if(PACKAGE_FIND_VERSION VERSION_GREATER PACKAGE_VERSION)
+              set(PACKAGE_VERSION_COMPATIBLE FALSE)
+            else()
+              if(PACKAGE_FIND_VERSION_MAJOR STREQUAL \"{major}\")
+                set(PACKAGE_VERSION_COMPATIBLE TRUE)
+              else()
+                set(PACKAGE_VERSION_COMPATIBLE FALSE)
+              endif()
+              if(PACKAGE_FIND_VERSION STREQUAL PACKAGE_VERSION)
+                set(PACKAGE_VERSION_EXACT TRUE)
+              endif()
+            endif()
+            ",
+            version = self.version,
+            major = self.version.major,
+        )
+    }
+}
+
+/// The name of the imported target of the library `library_name`, like `project::utils`.
+pub fn imported_target_name(namespace: &str, library_name: &str) -> String {
+    format!("{}::{}", namespace, library_name)
+}
+
+fn imported_target_from(
+    namespace: &str,
+    target: &BuildTarget,
+    pkg_config_modules: &mut Vec<String>,
+    found_libraries: &mut Vec<CMakeFoundLibrary>,
+) -> Option<CMakeImportedTarget> {
+    let library = match target.target_type {
+        TargetType::Library(ref library) => library,
+        TargetType::Executable(_) => return None,
+    };
+
+    let mut link_libraries = Vec::new();
+    for dependency in &target.dependencies {
+        match dependency.source {
+            DependencySource::FromSource(ref source_data) => {
+                link_libraries.push(imported_target_name(namespace, &source_data.library.name));
+            }
+            DependencySource::FromPkgConfig(ref pkg_config_target) => {
+                link_libraries.push(format!("PkgConfig::{}", pkg_config_target.target));
+                pkg_config_modules.push(pkg_config_target.target.clone());
+            }
+            DependencySource::FromLibraryFiles(ref target) => {
+                link_libraries.extend(relocatable_link_libraries(
+                    namespace,
+                    &target.link_arguments(),
+                    found_libraries,
+                ));
+            }
+            DependencySource::FromConan(ref conan_target) => {
+                link_libraries.extend(relocatable_link_libraries(
+                    namespace,
+                    &conan_target.link_arguments(),
+                    found_libraries,
+                ));
+            }
+            // Installed to the include directory of the package together with the headers of
+            // the library.
+            DependencySource::FromHeaderOnly(_) => {}
+        }
+    }
+    if let Some(ref ld_flags) = target.compiler_flags.ld_flags {
+        link_libraries.extend_from_slice(ld_flags.flags());
+    }
+
+    Some(CMakeImportedTarget {
+        name: imported_target_name(namespace, &library.name),
+        library_type: library.ty.clone(),
+        location: library.to_string(),
+        link_libraries,
+        compile_options: compile_options(target),
+    })
+}
+
+/// The link arguments of a dependency that is not part of the package, without paths of the build
+/// machine. Libraries given by `-l` or by their path are looked up with `find_library`, with the
+/// directories they were found in on the build machine as hints.
+fn relocatable_link_libraries(
+    namespace: &str,
+    link_arguments: &[String],
+    found_libraries: &mut Vec<CMakeFoundLibrary>,
+) -> Vec<String> {
+    let library_directories = link_arguments
+        .iter()
+        .filter_map(|argument| argument.strip_prefix("-L"))
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    let mut link_libraries = Vec::new();
+    for argument in link_arguments {
+        let (name, hints) = if argument.starts_with("-L") {
+            continue;
+        } else if let Some(name) = argument.strip_prefix("-l") {
+            (name.to_string(), library_directories.clone())
+        } else if is_library_file(Path::new(argument)) {
+            let path = Path::new(argument);
+            (
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path.parent()
+                    .map(|directory| vec![directory.display().to_string()])
+                    .unwrap_or_default(),
+            )
+        } else {
+            if Path::new(argument).is_absolute() {
+                log::warn!(
+                    "CMake package configuration links with {}, which is only valid on this machine",
+                    argument
+                );
+            }
+            link_libraries.push(argument.clone());
+            continue;
+        };
+        let variable = format!("{}_{}_LIBRARY", namespace, name)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        link_libraries.push(format!("${{{}}}", variable));
+        if !found_libraries
+            .iter()
+            .any(|library| library.variable == variable)
+        {
+            found_libraries.push(CMakeFoundLibrary {
+                variable,
+                name,
+                hints,
+            });
+        }
+    }
+    link_libraries
+}
+
+fn is_library_file(path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.is_absolute()
+        && (file_name.ends_with(".a")
+            || file_name.ends_with(".so")
+            || file_name.contains(".so.")
+            || file_name.ends_with(".dylib"))
+}
+
+/// The defines and preprocessor flags of `target`, which consumers of its headers need as well.
+fn compile_options(target: &BuildTarget) -> Vec<String> {
+    let mut compile_options = target
+        .defines
+        .iter()
+        .map(|define| match define.value {
+            Some(ref value) => format!("-D{}={}", define.macro_, value),
+            None => format!("-D{}", define.macro_),
+        })
+        .collect::<Vec<String>>();
+    if let Some(ref cpp_flags) = target.compiler_flags.cpp_flags {
+        compile_options.extend_from_slice(cpp_flags.flags());
+    }
+    compile_options
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::build_target::include_directories::{
+        IncludeDirectories, IncludeDirectory, IncludeType,
+    };
+    use crate::build_target::pkg_config::{PkgConfigLDFlags, PkgConfigTarget, ProvideMethod};
+    use crate::build_target::HeaderOnlyData;
+    use crate::flags::{CPPFlags, CXXFlags};
+    use crate::parser::types::{Define, PkgConfigExportData};
+
+    fn libraries(target: &str, link_libs: &[&str], link_dirs: &[&str]) -> PkgConfigTarget {
+        PkgConfigTarget {
+            target: target.to_string(),
+            version: None,
+            include_directories: IncludeDirectories::new(),
+            cxx_flags: CXXFlags::new(&[]),
+            method: ProvideMethod::PkgConfigOutput(PkgConfigLDFlags {
+                link_libs: link_libs.iter().map(|lib| lib.to_string()).collect(),
+                link_dirs: link_dirs.iter().map(|dir| dir.to_string()).collect(),
+            }),
+            input_files: Vec::new(),
+            requirement: None,
+        }
+    }
+
+    fn package_config() -> CMakePackageConfig {
+        CMakePackageConfig {
+            name: "project".to_string(),
+            version: semver::Version::new(1, 2, 3),
+            targets: vec![
+                CMakeImportedTarget {
+                    name: "project::bar".to_string(),
+                    library_type: LibraryType::Dynamic,
+                    location: "libbar.so".to_string(),
+                    link_libraries: vec![],
+                    compile_options: vec![],
+                },
+                CMakeImportedTarget {
+                    name: "project::foo".to_string(),
+                    library_type: LibraryType::Static,
+                    location: "libfoo.a".to_string(),
+                    link_libraries: vec![
+                        "project::bar".to_string(),
+                        "PkgConfig::zlib".to_string(),
+                        "${PROJECT_M_LIBRARY}".to_string(),
+                    ],
+                    compile_options: vec!["-DFOO_API=1".to_string()],
+                },
+            ],
+            pkg_config_modules: vec!["zlib".to_string()],
+            found_libraries: vec![CMakeFoundLibrary {
+                variable: "PROJECT_M_LIBRARY".to_string(),
+                name: "m".to_string(),
+                hints: vec!["/opt/m/lib".to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn from_registry_exports_every_library() {
+        let bar = BuildTarget::library_fixture("/bar", "bar", LibraryType::Dynamic);
+        let mut foo = BuildTarget::library_fixture("/foo", "foo", LibraryType::Static)
+            .with_dependency(DependencySource::FromPkgConfig(libraries(
+                "zlib",
+                &["-lz"],
+                &[],
+            )))
+            .with_dependency(DependencySource::FromLibraryFiles(libraries(
+                "m",
+                &["-lm"],
+                &["-L/opt/m/lib"],
+            )))
+            .with_dependency(DependencySource::FromHeaderOnly(HeaderOnlyData {
+                name: "json".to_string(),
+                include_directory: IncludeDirectory {
+                    include_type: IncludeType::System,
+                    path: std::path::PathBuf::from("/opt/json/include"),
+                },
+            }));
+        foo.depend_on(&bar);
+        foo.defines = vec![
+            Define {
+                macro_: "FOO_API".to_string(),
+                value: Some("1".to_string()),
+            },
+            Define {
+                macro_: "FOO_SHARED".to_string(),
+                value: None,
+            },
+        ];
+        foo.compiler_flags.cpp_flags = Some(CPPFlags::from_slice(&["-DFOO_EXTRA".to_string()]));
+        // The flags exported to pkg-config are not part of the CMake target.
+        foo.pkg_config_export = Some(PkgConfigExportData {
+            cflags: vec!["-DPKG_CONFIG_ONLY".to_string()],
+            ..Default::default()
+        });
+        let app = BuildTarget::executable_fixture("/app", "app");
+        let registry =
+            TargetRegistry::from_targets(&[app.into_node(), foo.into_node(), bar.into_node()]);
+
+        assert_eq!(
+            CMakePackageConfig::from_registry("project", &semver::Version::new(1, 2, 3), &registry),
+            CMakePackageConfig {
+                name: "project".to_string(),
+                version: semver::Version::new(1, 2, 3),
+                targets: vec![
+                    CMakeImportedTarget {
+                        name: "project::bar".to_string(),
+                        library_type: LibraryType::Dynamic,
+                        location: "libbar.so".to_string(),
+                        link_libraries: vec![],
+                        compile_options: vec![],
+                    },
+                    CMakeImportedTarget {
+                        name: "project::foo".to_string(),
+                        library_type: LibraryType::Static,
+                        location: "libfoo.a".to_string(),
+                        link_libraries: vec![
+                            "PkgConfig::zlib".to_string(),
+                            "${PROJECT_M_LIBRARY}".to_string(),
+                            "project::bar".to_string(),
+                        ],
+                        compile_options: vec![
+                            "-DFOO_API=1".to_string(),
+                            "-DFOO_SHARED".to_string(),
+                            "-DFOO_EXTRA".to_string(),
+                        ],
+                    },
+                ],
+                pkg_config_modules: vec!["zlib".to_string()],
+                found_libraries: vec![CMakeFoundLibrary {
+                    variable: "PROJECT_M_LIBRARY".to_string(),
+                    name: "m".to_string(),
+                    hints: vec!["/opt/m/lib".to_string()],
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn libraries_outside_of_package_are_found_by_name() {
+        let mut found_libraries = Vec::new();
+        let link_libraries = relocatable_link_libraries(
+            "project",
+            &[
+                "/home/conan/p/lib/libfoo.a".to_string(),
+                "-pthread".to_string(),
+                "-L/opt/lib".to_string(),
+                "-lfoo-extra".to_string(),
+            ],
+            &mut found_libraries,
+        );
+        assert_eq!(
+            link_libraries,
+            vec![
+                "${PROJECT_LIBFOO_A_LIBRARY}".to_string(),
+                "-pthread".to_string(),
+                "${PROJECT_FOO_EXTRA_LIBRARY}".to_string(),
+            ]
+        );
+        assert_eq!(
+            found_libraries,
+            vec![
+                CMakeFoundLibrary {
+                    variable: "PROJECT_LIBFOO_A_LIBRARY".to_string(),
+                    name: "libfoo.a".to_string(),
+                    hints: vec!["/home/conan/p/lib".to_string()],
+                },
+                CMakeFoundLibrary {
+                    variable: "PROJECT_FOO_EXTRA_LIBRARY".to_string(),
+                    name: "foo-extra".to_string(),
+                    hints: vec!["/opt/lib".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn file_names_use_package_name() {
+        let package_config = package_config();
+        assert_eq!(package_config.config_file_name(), "projectConfig.cmake");
+        assert_eq!(
+            package_config.version_file_name(),
+            "projectConfigVersion.cmake"
+        );
+    }
+
+    #[test]
+    fn config_declares_relocatable_imported_targets() {
+        assert_eq!(
+            package_config().config(),
+            indoc::indoc!(
+                r#"
+                # Generated by yambs. DO NOT EDIT.
+                get_filename_component(_IMPORT_PREFIX "${CMAKE_CURRENT_LIST_DIR}/../../.." ABSOLUTE)
+
+                include(CMakeFindDependencyMacro)
+                find_dependency(PkgConfig)
+                pkg_check_modules(zlib REQUIRED IMPORTED_TARGET zlib)
+
+                find_library(PROJECT_M_LIBRARY NAMES m HINTS "/opt/m/lib" REQUIRED)
+
+                if(NOT TARGET project::bar)
+                  add_library(project::bar SHARED IMPORTED)
+                  set_target_properties(project::bar PROPERTIES
+                    IMPORTED_LOCATION "${_IMPORT_PREFIX}/lib/libbar.so"
+                    IMPORTED_NO_SONAME TRUE
+                    INTERFACE_INCLUDE_DIRECTORIES "${_IMPORT_PREFIX}/include"
+                  )
+                endif()
+
+                if(NOT TARGET project::foo)
+                  add_library(project::foo STATIC IMPORTED)
+                  set_target_properties(project::foo PROPERTIES
+                    IMPORTED_LOCATION "${_IMPORT_PREFIX}/lib/libfoo.a"
+                    INTERFACE_INCLUDE_DIRECTORIES "${_IMPORT_PREFIX}/include"
+                    INTERFACE_LINK_LIBRARIES "project::bar;PkgConfig::zlib;${PROJECT_M_LIBRARY}"
+                    INTERFACE_COMPILE_OPTIONS "-DFOO_API=1"
+                  )
+                endif()
+
+                unset(_IMPORT_PREFIX)
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn config_version_accepts_same_major_version() {
+        let config_version = package_config().config_version();
+        assert!(config_version.contains("set(PACKAGE_VERSION \"1.2.3\")"));
+        assert!(config_version.contains("if(PACKAGE_FIND_VERSION_MAJOR STREQUAL \"1\")"));
+    }
+}
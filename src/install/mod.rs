@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::build_target::target_registry::TargetRegistry;
+use crate::build_target::{BuildTarget, DependencySource, Project, TargetType};
+use crate::errors::FsError;
+use crate::generator;
+use crate::utility;
+
+mod cmake;
+mod pkg_config;
+
+pub use cmake::CMakePackageConfig;
+pub use pkg_config::PkgConfigFile;
+
+pub const BIN_DIRECTORY: &str = "bin";
+pub const LIB_DIRECTORY: &str = "lib";
+pub const INCLUDE_DIRECTORY: &str = "include";
+
+#[derive(Debug, thiserror::Error)]
+pub enum InstallError {
+    #[error(transparent)]
+    Fs(#[from] FsError),
+    #[error("Could not find build output {0:?} for target \"{1}\". Has the target been built?")]
+    MissingArtifact(PathBuf, String),
+    #[error("Targets {0} and {1} are both installed as {2}. Rename one of them")]
+    InstallCollision(String, String, String),
+}
+
+/// Installs the build output of the targets in a registry into a prefix, laid out as
+///
+/// ```text
+/// <prefix>
+/// ├── bin
+/// ├── include
+/// └── lib
+///     ├── cmake
+///     └── pkgconfig
+/// ```
+///
+/// The include directories of libraries and of their header only dependencies are merged into
+/// `include`. Libraries that export a pkg-config file get `lib/pkgconfig/lib<name>.pc` written
+/// next to them. If the project is named, a CMake package configuration for all libraries is
+/// written to `lib/cmake/<project name>`.
+pub struct Installer {
+    prefix: PathBuf,
+    buildfile_directory: PathBuf,
+    project: Project,
+}
+
+impl Installer {
+    pub fn new(prefix: &Path, buildfile_directory: &Path, project: &Project) -> Self {
+        Self {
+            prefix: prefix.to_path_buf(),
+            buildfile_directory: buildfile_directory.to_path_buf(),
+            project: project.clone(),
+        }
+    }
+
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    pub fn install(&self, registry: &TargetRegistry) -> Result<(), InstallError> {
+        verify_unique_installation(registry, self.project.name.as_deref())?;
+        let mut installed_include_directories = Vec::<PathBuf>::new();
+
+        for target in &registry.registry {
+            let borrowed_target = target.borrow();
+            self.install_artifact(&borrowed_target)?;
+
+            if borrowed_target.is_library() {
+                for include_directory in library_include_directories(&borrowed_target) {
+                    if include_directory.is_dir()
+                        && !installed_include_directories.contains(&include_directory)
+                    {
+                        log::debug!(
+                            "Installing include directory {} of target \"{}\"",
+                            include_directory.display(),
+                            borrowed_target.name()
+                        );
+                        utility::copy_dir_all(
+                            &include_directory,
+                            &self.prefix.join(INCLUDE_DIRECTORY),
+                        )?;
+                        installed_include_directories.push(include_directory);
+                    }
+                }
+                self.install_pkg_config_file(&borrowed_target, registry)?;
+            }
+        }
+        self.install_cmake_package_config(registry)?;
+        Ok(())
+    }
+
+    fn install_artifact(&self, target: &BuildTarget) -> Result<(), InstallError> {
+        let source = self.buildfile_directory.join(generator::artifact(target));
+        if !source.is_file() {
+            return Err(InstallError::MissingArtifact(source, target.name()));
+        }
+        let destination = self.prefix.join(installed_artifact(target));
+        if let Some(destination_directory) = destination.parent() {
+            utility::create_dir(destination_directory)?;
+        }
+        log::debug!(
+            "Installing {} to {}",
+            source.display(),
+            destination.display()
+        );
+        utility::copy_file(&source, &destination)?;
+        Ok(())
+    }
+
+    fn install_pkg_config_file(
+        &self,
+        target: &BuildTarget,
+        registry: &TargetRegistry,
+    ) -> Result<(), InstallError> {
+        if let Some(pkg_config_file) = PkgConfigFile::from_target(target, registry) {
+            let pkg_config_directory = self
+                .prefix
+                .join(LIB_DIRECTORY)
+                .join(pkg_config::PKG_CONFIG_DIRECTORY);
+            utility::create_dir(&pkg_config_directory)?;
+            let path = self.prefix.join(installed_pkg_config_file(target));
+            log::debug!("Writing pkg-config file {}", path.display());
+            write_file(&path, &pkg_config_file.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn install_cmake_package_config(&self, registry: &TargetRegistry) -> Result<(), InstallError> {
+        let name = match self.project.name {
+            Some(ref name) => name,
+            None => {
+                log::debug!("Project has no name. Skipping CMake package configuration.");
+                return Ok(());
+            }
+        };
+        let version = self
+            .project
+            .version
+            .clone()
+            .unwrap_or_else(|| semver::Version::new(0, 0, 0));
+        let package_config = CMakePackageConfig::from_registry(name, &version, registry);
+        if package_config.targets.is_empty() {
+            return Ok(());
+        }
+
+        let cmake_directory = self
+            .prefix
+            .join(LIB_DIRECTORY)
+            .join(cmake::CMAKE_DIRECTORY)
+            .join(name);
+        utility::create_dir(&cmake_directory)?;
+        let config_path = cmake_directory.join(package_config.config_file_name());
+        log::debug!(
+            "Writing CMake package configuration {}",
+            config_path.display()
+        );
+        write_file(&config_path, &package_config.config())?;
+        write_file(
+            &cmake_directory.join(package_config.version_file_name()),
+            &package_config.config_version(),
+        )?;
+        Ok(())
+    }
+}
+
+/// The include directories installed with the library `target`: its own, and those of its header
+/// only dependencies, since its headers may include theirs. Consumers find all of them in the
+/// include directory of the installation.
+fn library_include_directories(target: &BuildTarget) -> Vec<PathBuf> {
+    std::iter::once(target.include_directory.path.clone())
+        .chain(
+            target
+                .dependencies
+                .iter()
+                .filter_map(|dependency| match dependency.source {
+                    DependencySource::FromHeaderOnly(ref header_only) => {
+                        Some(header_only.include_directory.path.clone())
+                    }
+                    _ => None,
+                }),
+        )
+        .collect()
+}
+
+/// The path of the build output of `target` in an installation, relative to its prefix.
+fn installed_artifact(target: &BuildTarget) -> PathBuf {
+    match target.target_type {
+        TargetType::Executable(ref exe) => Path::new(BIN_DIRECTORY).join(exe.to_string()),
+        TargetType::Library(ref lib) => Path::new(LIB_DIRECTORY).join(lib.to_string()),
+    }
+}
+
+/// The path of the pkg-config file of the library `target` in an installation, relative to its
+/// prefix.
+fn installed_pkg_config_file(target: &BuildTarget) -> PathBuf {
+    Path::new(LIB_DIRECTORY)
+        .join(pkg_config::PKG_CONFIG_DIRECTORY)
+        .join(PkgConfigFile::file_name(&target.name()))
+}
+
+/// The paths `target` occupies in an installation, and the name of its imported CMake target if
+/// the project exports a CMake package configuration, named `project_name`.
+fn installed_names(target: &BuildTarget, project_name: Option<&str>) -> Vec<String> {
+    let mut names = vec![installed_artifact(target).display().to_string()];
+    if let TargetType::Library(ref library) = target.target_type {
+        if target.pkg_config_export.is_some() {
+            names.push(installed_pkg_config_file(target).display().to_string());
+        }
+        if let Some(project_name) = project_name {
+            names.push(format!(
+                "CMake target {}",
+                cmake::imported_target_name(project_name, &library.name)
+            ));
+        }
+    }
+    names
+}
+
+/// Verifies that no two targets of `registry` are installed to the same path or exported as the
+/// same CMake target. Libraries with the same name in different projects are built side by side,
+/// but would overwrite each other in the flat layout of an installation.
+fn verify_unique_installation(
+    registry: &TargetRegistry,
+    project_name: Option<&str>,
+) -> Result<(), InstallError> {
+    let mut targets_by_name = HashMap::<String, String>::new();
+    for target in &registry.registry {
+        let target = target.borrow();
+        for name in installed_names(&target, project_name) {
+            if let Some(other) = targets_by_name.get(&name) {
+                return Err(InstallError::InstallCollision(
+                    other.clone(),
+                    target.qualified_name(),
+                    name,
+                ));
+            }
+            targets_by_name.insert(name, target.qualified_name());
+        }
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), FsError> {
+    let mut file = utility::create_file(path)?;
+    file.write_all(content.as_bytes())
+        .map_err(FsError::WriteToFile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_target::include_directories::{IncludeDirectory, IncludeType};
+    use crate::build_target::{HeaderOnlyData, LibraryType};
+    use crate::parser::types::PkgConfigExportData;
+
+    fn utils(directory: &str, ty: LibraryType) -> BuildTarget {
+        BuildTarget::library_fixture(directory, "utils", ty)
+    }
+
+    #[test]
+    fn header_only_dependencies_are_installed_with_libraries() {
+        let library = utils("/one", LibraryType::Static).with_dependency(
+            DependencySource::FromHeaderOnly(HeaderOnlyData {
+                name: "json".to_string(),
+                include_directory: IncludeDirectory {
+                    include_type: IncludeType::System,
+                    path: PathBuf::from("/opt/json/include"),
+                },
+            }),
+        );
+        assert_eq!(
+            library_include_directories(&library),
+            vec![
+                PathBuf::from("/one/include"),
+                PathBuf::from("/opt/json/include")
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_installed_to_the_same_path_are_rejected() {
+        let registry = TargetRegistry::from_targets(&[
+            utils("/one", LibraryType::Static).into_node(),
+            utils("/two", LibraryType::Dynamic).into_node(),
+        ]);
+        assert!(verify_unique_installation(&registry, None).is_ok());
+        assert!(matches!(
+            verify_unique_installation(&registry, Some("project")),
+            Err(InstallError::InstallCollision(ref first, ref second, ref name))
+                if first == "utils@/one"
+                    && second == "utils@/two"
+                    && name == "CMake target project::utils"
+        ));
+
+        let registry = TargetRegistry::from_targets(&[
+            utils("/one", LibraryType::Static).into_node(),
+            utils("/two", LibraryType::Static).into_node(),
+        ]);
+        assert!(matches!(
+            verify_unique_installation(&registry, None),
+            Err(InstallError::InstallCollision(ref first, ref second, ref path))
+                if first == "utils@/one" && second == "utils@/two" && path == "lib/libutils.a"
+        ));
+
+        let exporting = |directory: &str, ty: LibraryType| {
+            let mut target = utils(directory, ty);
+            target.pkg_config_export = Some(PkgConfigExportData::default());
+            target.into_node()
+        };
+        let registry = TargetRegistry::from_targets(&[
+            exporting("/one", LibraryType::Static),
+            exporting("/two", LibraryType::Dynamic),
+        ]);
+        assert!(matches!(
+            verify_unique_installation(&registry, None),
+            Err(InstallError::InstallCollision(_, _, ref path))
+                if path == "lib/pkgconfig/libutils.pc"
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+
+use crate::build_target::target_registry::TargetRegistry;
+use crate::build_target::{BuildTarget, DependencySource};
+
+pub const PKG_CONFIG_DIRECTORY: &str = "pkgconfig";
+
+/// A pkg-config file describing an installed library.
+///
+/// The paths in the file are relative to the location of the file itself (`${pcfiledir}`), so
+/// the installation can be moved around after it has been made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PkgConfigFile {
+    pub name: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub version: String,
+    pub requires: Vec<String>,
+    pub libs: Vec<String>,
+    pub libs_private: Vec<String>,
+    pub cflags: Vec<String>,
+}
+
+impl PkgConfigFile {
+    pub fn file_name(library_name: &str) -> String {
+        format!("lib{}.pc", library_name)
+    }
+
+    /// Creates the pkg-config file of a library target, if the target has asked for one.
+    ///
+    /// Source dependencies that also export a pkg-config file become `Requires` entries, other
+    /// source dependencies are linked through `Libs.private`. pkg-config dependencies are
+    /// required by their package name, with the version requirement of the manifest. Header only
+    /// dependencies are installed to `${includedir}` together with the headers of the library.
+    pub fn from_target(target: &BuildTarget, registry: &TargetRegistry) -> Option<Self> {
+        let export = target.pkg_config_export.as_ref()?;
+        if !target.is_library() {
+            return None;
+        }
+        let name = target.name();
+
+        let mut requires = Vec::new();
+        let mut libs_private = Vec::new();
+        for dependency in &target.dependencies {
+            match dependency.source {
+                DependencySource::FromSource(ref source_data) => {
+                    let exports_pkg_config = dependency
+                        .to_build_target(registry)
+                        .map(|dependency_target| {
+                            dependency_target.borrow().pkg_config_export.is_some()
+                        })
+                        .unwrap_or(false);
+                    if exports_pkg_config {
+                        requires.push(format!("lib{}", source_data.library.name));
+                    } else {
+                        libs_private.push(format!("-l{}", source_data.library.name));
+                    }
+                }
+                DependencySource::FromPkgConfig(ref pkg_config_target) => {
+                    requires.extend(requires_with_version(
+                        &pkg_config_target.target,
+                        pkg_config_target.requirement.as_ref(),
+                    ));
+                }
+                DependencySource::FromLibraryFiles(ref target) => {
+                    libs_private.extend(target.link_arguments());
+                }
+                DependencySource::FromConan(ref conan_target) => {
+                    libs_private.extend(conan_target.link_arguments());
+                }
+                DependencySource::FromHeaderOnly(_) => {}
+            }
+        }
+        if let Some(ref ld_flags) = target.compiler_flags.ld_flags {
+            libs_private.extend_from_slice(ld_flags.flags());
+        }
+        let mut cflags = vec!["-I${includedir}".to_string()];
+        cflags.extend_from_slice(&export.cflags);
+
+        Some(Self {
+            description: export
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("The {} library", name)),
+            url: export.url.clone(),
+            version: target
+                .project
+                .version
+                .as_ref()
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "0.0.0".to_string()),
+            requires,
+            libs: vec!["-L${libdir}".to_string(), format!("-l{}", name)],
+            libs_private,
+            cflags,
+            name,
+        })
+    }
+}
+
+/// The `Requires` entries of the pkg-config package `name` that express `requirement`, like
+/// `zlib >= 1.2.11, zlib < 2` for `^1.2.11`.
+fn requires_with_version(name: &str, requirement: Option<&semver::VersionReq>) -> Vec<String> {
+    let requires = requirement
+        .map(|requirement| {
+            requirement
+                .comparators
+                .iter()
+                .flat_map(version_bounds)
+                .map(|(operator, version)| format!("{} {} {}", name, operator, version))
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    if requires.is_empty() {
+        vec![name.to_string()]
+    } else {
+        requires
+    }
+}
+
+/// The bounds `comparator` puts on a version, with the operators of pkg-config. A partial
+/// version, like `1.2`, stands for all versions starting with it.
+fn version_bounds(comparator: &semver::Comparator) -> Vec<(&'static str, String)> {
+    let major = comparator.major;
+    let mut version = match (comparator.minor, comparator.patch) {
+        (None, _) => major.to_string(),
+        (Some(minor), None) => format!("{}.{}", major, minor),
+        (Some(minor), Some(patch)) => format!("{}.{}.{}", major, minor, patch),
+    };
+    if !comparator.pre.is_empty() {
+        version = format!("{}-{}", version, comparator.pre);
+    }
+    let is_partial = comparator.patch.is_none();
+    // The first version after all versions starting with the partial version.
+    let after_partial = match comparator.minor {
+        None => (major + 1).to_string(),
+        Some(minor) => format!("{}.{}", major, minor + 1),
+    };
+    match comparator.op {
+        semver::Op::Exact | semver::Op::Wildcard if is_partial => {
+            vec![(">=", version), ("<", after_partial)]
+        }
+        semver::Op::Exact => vec![("=", version)],
+        semver::Op::Greater if is_partial => vec![(">=", after_partial)],
+        semver::Op::Greater => vec![(">", version)],
+        semver::Op::GreaterEq => vec![(">=", version)],
+        semver::Op::Less => vec![("<", version)],
+        semver::Op::LessEq if is_partial => vec![("<", after_partial)],
+        semver::Op::LessEq => vec![("<=", version)],
+        semver::Op::Tilde => {
+            let upper = match comparator.minor {
+                None => (major + 1).to_string(),
+                Some(minor) => format!("{}.{}", major, minor + 1),
+            };
+            vec![(">=", version), ("<", upper)]
+        }
+        semver::Op::Caret => {
+            let upper = match (major, comparator.minor, comparator.patch) {
+                (0, Some(0), Some(patch)) => format!("0.0.{}", patch + 1),
+                (0, Some(minor), _) => format!("0.{}", minor + 1),
+                _ => (major + 1).to_string(),
+            };
+            vec![(">=", version), ("<", upper)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+impl fmt::Display for PkgConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Generated by yambs. DO NOT EDIT.")?;
+        writeln!(f, "prefix=${{pcfiledir}}/../..")?;
+        writeln!(f, "exec_prefix=${{prefix}}")?;
+        writeln!(f, "libdir=${{exec_prefix}}/lib")?;
+        writeln!(f, "includedir=${{prefix}}/include")?;
+        writeln!(f)?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Description: {}", self.description)?;
+        if let Some(ref url) = self.url {
+            writeln!(f, "URL: {}", url)?;
+        }
+        writeln!(f, "Version: {}", self.version)?;
+        if !self.requires.is_empty() {
+            writeln!(f, "Requires: {}", self.requires.join(", "))?;
+        }
+        writeln!(f, "Libs: {}", self.libs.join(" "))?;
+        if !self.libs_private.is_empty() {
+            writeln!(f, "Libs.private: {}", self.libs_private.join(" "))?;
+        }
+        writeln!(f, "Cflags: {}", self.cflags.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::build_target::include_directories::{
+        IncludeDirectories, IncludeDirectory, IncludeType,
+    };
+    use crate::build_target::pkg_config::{PkgConfigLDFlags, PkgConfigTarget, ProvideMethod};
+    use crate::build_target::{HeaderOnlyData, LibraryType};
+    use crate::flags::CXXFlags;
+    use crate::parser::types::PkgConfigExportData;
+
+    fn library(directory: &str, name: &str) -> BuildTarget {
+        BuildTarget::library_fixture(directory, name, LibraryType::Static)
+    }
+
+    fn exporting(mut target: BuildTarget) -> BuildTarget {
+        target.pkg_config_export = Some(PkgConfigExportData::default());
+        target
+    }
+
+    fn libraries(target: &str, link_libs: &[&str]) -> PkgConfigTarget {
+        PkgConfigTarget {
+            target: target.to_string(),
+            version: None,
+            include_directories: IncludeDirectories::new(),
+            cxx_flags: CXXFlags::new(&[]),
+            method: ProvideMethod::PkgConfigOutput(PkgConfigLDFlags {
+                link_libs: link_libs.iter().map(|lib| lib.to_string()).collect(),
+                link_dirs: Vec::new(),
+            }),
+            input_files: Vec::new(),
+            requirement: None,
+        }
+    }
+
+    fn header_only(path: &str) -> DependencySource {
+        DependencySource::FromHeaderOnly(HeaderOnlyData {
+            name: "headers".to_string(),
+            include_directory: IncludeDirectory {
+                include_type: IncludeType::System,
+                path: std::path::PathBuf::from(path),
+            },
+        })
+    }
+
+    #[test]
+    fn from_target_derives_requires_and_private_libraries_from_dependencies() {
+        let exported = exporting(library("/exported", "exported"));
+        let internal = library("/internal", "internal");
+        let mut zlib = libraries("zlib", &["-lz"]);
+        zlib.requirement = Some(semver::VersionReq::parse("^1.2.11").unwrap());
+        let mut foo = exporting(library("/foo", "foo"))
+            .with_dependency(DependencySource::FromPkgConfig(zlib))
+            .with_dependency(DependencySource::FromPkgConfig(libraries(
+                "png",
+                &["-lpng"],
+            )))
+            .with_dependency(DependencySource::FromLibraryFiles(libraries("m", &["-lm"])))
+            .with_dependency(header_only("/opt/json/include"));
+        foo.depend_on(&exported);
+        foo.depend_on(&internal);
+        foo.pkg_config_export.as_mut().unwrap().cflags = vec!["-DFOO_API=1".to_string()];
+        let exported = exported.into_node();
+        let internal = internal.into_node();
+        let foo = foo.into_node();
+        let registry = TargetRegistry::from_targets(&[exported, internal, foo.clone()]);
+
+        let pkg_config_file = PkgConfigFile::from_target(&foo.borrow(), &registry).unwrap();
+        assert_eq!(
+            pkg_config_file.requires,
+            vec![
+                "zlib >= 1.2.11".to_string(),
+                "zlib < 2".to_string(),
+                "png".to_string(),
+                "libexported".to_string()
+            ]
+        );
+        assert_eq!(
+            pkg_config_file.libs_private,
+            vec!["-lm".to_string(), "-linternal".to_string()]
+        );
+        assert_eq!(
+            pkg_config_file.cflags,
+            vec!["-I${includedir}".to_string(), "-DFOO_API=1".to_string()]
+        );
+        assert_eq!(pkg_config_file.description, "The foo library");
+        assert_eq!(pkg_config_file.version, "0.0.0");
+    }
+
+    #[test]
+    fn version_requirements_are_expressed_with_pkg_config_operators() {
+        let requires = |requirement: &str| {
+            requires_with_version(
+                "zlib",
+                Some(&semver::VersionReq::parse(requirement).unwrap()),
+            )
+            .join(", ")
+        };
+        assert_eq!(requires(">=1.2.11"), "zlib >= 1.2.11");
+        assert_eq!(requires(">=1.2, <2"), "zlib >= 1.2, zlib < 2");
+        assert_eq!(requires("=1.2.11"), "zlib = 1.2.11");
+        assert_eq!(requires("=1.2"), "zlib >= 1.2, zlib < 1.3");
+        assert_eq!(requires(">1.2"), "zlib >= 1.3");
+        assert_eq!(requires("<=1"), "zlib < 2");
+        assert_eq!(requires("~1.2.3"), "zlib >= 1.2.3, zlib < 1.3");
+        assert_eq!(requires("^0.2.3"), "zlib >= 0.2.3, zlib < 0.3");
+        assert_eq!(requires("^0.0.3"), "zlib >= 0.0.3, zlib < 0.0.4");
+        assert_eq!(requires("1.*"), "zlib >= 1, zlib < 2");
+        assert_eq!(requires("*"), "zlib");
+        assert_eq!(
+            requires_with_version("zlib", None),
+            vec!["zlib".to_string()]
+        );
+    }
+
+    #[test]
+    fn from_target_skips_targets_without_export() {
+        let registry = TargetRegistry::new();
+        assert_eq!(
+            PkgConfigFile::from_target(&library("/foo", "foo"), &registry),
+            None
+        );
+        let mut app = BuildTarget::executable_fixture("/app", "app");
+        app.pkg_config_export = Some(PkgConfigExportData::default());
+        assert_eq!(PkgConfigFile::from_target(&app, &registry), None);
+    }
+
+    #[test]
+    fn file_name_is_prefixed_with_lib() {
+        assert_eq!(PkgConfigFile::file_name("foo"), "libfoo.pc");
+    }
+
+    #[test]
+    fn display_writes_relocatable_pkg_config_file() {
+        let pkg_config_file = PkgConfigFile {
+            name: "foo".to_string(),
+            description: "The foo library".to_string(),
+            url: Some("https://example.com/foo".to_string()),
+            version: "1.2.3".to_string(),
+            requires: vec!["libbar".to_string(), "zlib".to_string()],
+            libs: vec!["-L${libdir}".to_string(), "-lfoo".to_string()],
+            libs_private: vec!["-lbaz".to_string(), "-pthread".to_string()],
+            cflags: vec!["-I${includedir}".to_string(), "-DFOO_API=1".to_string()],
+        };
+
+        assert_eq!(
+            pkg_config_file.to_string(),
+            indoc::indoc!(
+                "
+                # Generated by yambs. DO NOT EDIT.
+                prefix=${pcfiledir}/../..
+                exec_prefix=${prefix}
+                libdir=${exec_prefix}/lib
+                includedir=${prefix}/include
+
+                Name: foo
+                Description: The foo library
+                URL: https://example.com/foo
+                Version: 1.2.3
+                Requires: libbar, zlib
+                Libs: -L${libdir} -lfoo
+                Libs.private: -lbaz -pthread
+                Cflags: -I${includedir} -DFOO_API=1
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn display_skips_empty_optional_fields() {
+        let pkg_config_file = PkgConfigFile {
+            name: "foo".to_string(),
+            description: "The foo library".to_string(),
+            url: None,
+            version: "0.0.0".to_string(),
+            requires: vec![],
+            libs: vec!["-L${libdir}".to_string(), "-lfoo".to_string()],
+            libs_private: vec![],
+            cflags: vec!["-I${includedir}".to_string()],
+        };
+
+        let rendered = pkg_config_file.to_string();
+        assert!(!rendered.contains("URL:"));
+        assert!(!rendered.contains("Requires:"));
+        assert!(!rendered.contains("Libs.private:"));
+    }
+}
@@ -1,16 +1,21 @@
-use std::env;
-use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 
 pub mod build_target;
+pub mod cache;
 pub mod cli;
 pub mod compiler;
 pub mod errors;
 pub mod flags;
 pub mod generator;
+pub mod git;
+pub mod install;
+pub mod lockfile;
 pub mod logger;
 pub mod manifest;
+pub mod object_cache;
 pub mod output;
+pub mod overrides;
+pub mod package;
 pub mod parser;
 pub mod progress;
 pub mod targets;
@@ -38,54 +43,7 @@ pub struct ProjectConfig {
     pub build_type: BuildType,
     pub generator_type: GeneratorType,
     pub defines: Vec<Define>,
-}
-
-pub enum ModifyMode {
-    Set,
-    Append,
-}
-
-#[derive(PartialEq, Eq, Debug)]
-struct EnvironmentVariable {
-    captured_val: Option<OsString>,
-    key: String,
-    val: Option<OsString>,
-}
-
-impl EnvironmentVariable {
-    pub fn new(key: &str) -> Self {
-        let captured_val = env::var_os(key);
-
-        Self {
-            captured_val: captured_val.clone(),
-            key: key.to_string(),
-            val: captured_val,
-        }
-    }
-
-    pub fn set(&mut self, value: &OsStr, mode: ModifyMode) {
-        match mode {
-            ModifyMode::Set => {
-                self.val = Some(value.to_os_string());
-                env::set_var(&self.key, value);
-            }
-            ModifyMode::Append => {
-                if let Some(ref mut val) = self.val {
-                    #[cfg(target_family = "unix")]
-                    {
-                        val.push(format!(":{}", value.to_string_lossy()));
-                    }
-                    #[cfg(target_family = "windows")]
-                    {
-                        val.push(format!(";{}", value.to_string_lossy()));
-                    }
-                } else {
-                    self.val = Some(value.to_os_string());
-                }
-                env::set_var(&self.key, self.val.as_ref().unwrap());
-            }
-        }
-    }
+    pub object_cache: bool,
 }
 
 // FIXME: Should have check for absolute path. Perhaps better check?
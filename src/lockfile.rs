@@ -0,0 +1,818 @@
+use std::path::{Path, PathBuf};
+
+use crate::cli::configurations::BuildType;
+use crate::git::GitReference;
+use crate::YAMBS_MANIFEST_NAME;
+
+pub const LOCKFILE_NAME: &str = "yambs.lock";
+/// Version of the lockfile format. Version 1 lockfiles have no version and record the
+/// dependencies of all build types together.
+const LOCKFILE_VERSION: u32 = 2;
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by yambs. It is not intended for manual editing.\n";
+
+#[derive(thiserror::Error, Debug)]
+pub enum LockfileError {
+    #[error("Failed to read lockfile {0:?}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse lockfile {0:?}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("Failed to serialize lockfile")]
+    Serialize(#[source] toml::ser::Error),
+    #[error("Failed to write lockfile {0:?}")]
+    Write(PathBuf, #[source] std::io::Error),
+    #[error("Failed to read manifest {0:?}")]
+    ReadManifest(PathBuf, #[source] std::io::Error),
+    #[error("Could not find lockfile {0:?}, which is required with --locked")]
+    Missing(PathBuf),
+    #[error("Resolved dependencies differ from lockfile {0:?}:\n{1}")]
+    Mismatch(PathBuf, String),
+    #[error("Dependencies are overridden, so they can't be checked against lockfile {0:?}:\n{1}")]
+    Overridden(PathBuf, String),
+    #[error("Lockfile {0:?} has version {1}, which is newer than this version of yambs supports")]
+    UnsupportedVersion(PathBuf, u32),
+}
+
+/// A git dependency pinned to an exact commit.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+pub struct LockedGitDependency {
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub commit: String,
+}
+
+impl LockedGitDependency {
+    pub fn new(name: &str, url: &str, reference: &GitReference, commit: &str) -> Self {
+        let (rev, tag, branch) = match reference {
+            GitReference::Rev(rev) => (Some(rev.clone()), None, None),
+            GitReference::Tag(tag) => (None, Some(tag.clone()), None),
+            GitReference::Branch(branch) => (None, None, Some(branch.clone())),
+            GitReference::DefaultBranch => (None, None, None),
+        };
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            rev,
+            tag,
+            branch,
+            commit: commit.to_string(),
+        }
+    }
+
+    fn locks(&self, name: &str, url: &str, reference: &GitReference) -> bool {
+        let other = Self::new(name, url, reference, &self.commit);
+        *self == other
+    }
+}
+
+impl std::fmt::Display for LockedGitDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "git dependency {} from {} at commit {}",
+            self.name, self.url, self.commit
+        )
+    }
+}
+
+/// A source dependency, identified by the manifest it was built from.
+///
+/// Git dependencies are recorded with their URL instead of the path of the checkout, since the
+/// checkout is located in the cache of each user.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+pub struct LockedSourceDependency {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    pub manifest_hash: String,
+}
+
+impl LockedSourceDependency {
+    pub fn from_path(name: &str, path: &Path) -> Result<Self, LockfileError> {
+        Ok(Self {
+            name: name.to_string(),
+            path: Some(path.to_path_buf()),
+            git: None,
+            manifest_hash: manifest_hash(path)?,
+        })
+    }
+
+    pub fn from_git(name: &str, url: &str, checkout: &Path) -> Result<Self, LockfileError> {
+        Ok(Self {
+            name: name.to_string(),
+            path: None,
+            git: Some(url.to_string()),
+            manifest_hash: manifest_hash(checkout)?,
+        })
+    }
+}
+
+impl std::fmt::Display for LockedSourceDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "source dependency {}", self.name)?;
+        if let Some(ref path) = self.path {
+            write!(f, " from {}", path.display())?;
+        }
+        if let Some(ref url) = self.git {
+            write!(f, " from {}", url)?;
+        }
+        write!(f, " with manifest hash {}", self.manifest_hash)
+    }
+}
+
+/// A pkg-config package, with the version and the libraries it resolved to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+pub struct LockedPkgConfigDependency {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub libraries: Vec<String>,
+}
+
+impl std::fmt::Display for LockedPkgConfigDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pkg-config dependency {}", self.name)?;
+        if let Some(ref version) = self.version {
+            write!(f, " {}", version)?;
+        }
+        write!(f, " linking [{}]", self.libraries.join(", "))
+    }
+}
+
+/// A Conan package, with the version and the libraries it resolved to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+pub struct LockedConanDependency {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub libraries: Vec<String>,
+}
+
+impl std::fmt::Display for LockedConanDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "conan dependency {}", self.name)?;
+        if let Some(ref version) = self.version {
+            write!(f, " {}", version)?;
+        }
+        write!(f, " linking [{}]", self.libraries.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+pub struct LockedHeaderOnlyDependency {
+    pub name: String,
+    pub include_directory: PathBuf,
+}
+
+impl std::fmt::Display for LockedHeaderOnlyDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "header only dependency {} with include directory {}",
+            self.name,
+            self.include_directory.display()
+        )
+    }
+}
+
+/// Dependencies resolved for one build type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+struct ResolvedDependencies {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    source: Vec<LockedSourceDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pkg_config: Vec<LockedPkgConfigDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conan: Vec<LockedConanDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    header_only: Vec<LockedHeaderOnlyDependency>,
+}
+
+impl ResolvedDependencies {
+    fn is_empty(&self) -> bool {
+        self.source.is_empty()
+            && self.pkg_config.is_empty()
+            && self.conan.is_empty()
+            && self.header_only.is_empty()
+    }
+
+    fn sorted(&self) -> Self {
+        let mut dependencies = self.clone();
+        dependencies.source.sort();
+        dependencies.pkg_config.sort();
+        dependencies.conan.sort();
+        dependencies.header_only.sort();
+        dependencies
+    }
+
+    fn map_paths(&self, map: &dyn Fn(&Path) -> PathBuf) -> Self {
+        let mut dependencies = self.clone();
+        for source in &mut dependencies.source {
+            source.path = source.path.as_deref().map(map);
+        }
+        for header_only in &mut dependencies.header_only {
+            header_only.include_directory = map(&header_only.include_directory);
+        }
+        dependencies
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+struct LockfileData {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default)]
+    git: Vec<LockedGitDependency>,
+    #[serde(default)]
+    debug: ResolvedDependencies,
+    #[serde(default)]
+    release: ResolvedDependencies,
+    /// Dependencies of version 1 lockfiles, which are not recorded per build type.
+    #[serde(default, flatten)]
+    legacy: ResolvedDependencies,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+impl LockfileData {
+    /// Migrates data read from a lockfile of an older version. The dependencies of a version 1
+    /// lockfile were resolved for whichever build type was configured, so they are taken as the
+    /// dependencies of both build types.
+    fn migrated(mut self, path: &Path) -> Result<Self, LockfileError> {
+        if self.version > LOCKFILE_VERSION {
+            return Err(LockfileError::UnsupportedVersion(
+                path.to_path_buf(),
+                self.version,
+            ));
+        }
+        if self.version < LOCKFILE_VERSION {
+            log::debug!(
+                "Migrating lockfile {} from version {}",
+                path.display(),
+                self.version
+            );
+            let legacy = std::mem::take(&mut self.legacy);
+            if self.debug.is_empty() {
+                self.debug = legacy.clone();
+            }
+            if self.release.is_empty() {
+                self.release = legacy;
+            }
+            self.version = LOCKFILE_VERSION;
+        }
+        Ok(self)
+    }
+
+    fn dependencies(&self, build_type: &BuildType) -> &ResolvedDependencies {
+        match build_type {
+            BuildType::Debug => &self.debug,
+            BuildType::Release => &self.release,
+        }
+    }
+
+    fn dependencies_mut(&mut self, build_type: &BuildType) -> &mut ResolvedDependencies {
+        match build_type {
+            BuildType::Debug => &mut self.debug,
+            BuildType::Release => &mut self.release,
+        }
+    }
+
+    fn sorted(&self) -> Self {
+        let mut git = self.git.clone();
+        git.sort();
+        Self {
+            git,
+            debug: self.debug.sorted(),
+            release: self.release.sorted(),
+            ..self.clone()
+        }
+    }
+
+    fn map_paths(&self, map: &dyn Fn(&Path) -> PathBuf) -> Self {
+        Self {
+            debug: self.debug.map_paths(map),
+            release: self.release.map_paths(map),
+            ..self.clone()
+        }
+    }
+
+    /// Paths as they are stored in the lockfile, relative to the directory of the lockfile where
+    /// possible, so the lockfile can be shared between checkouts.
+    fn relative_to(&self, directory: &Path) -> Self {
+        self.map_paths(&|path| {
+            crate::utility::relative_path(path, directory).unwrap_or_else(|| path.to_path_buf())
+        })
+    }
+
+    /// Paths of the lockfile resolved against the directory of the lockfile.
+    fn resolved_in(&self, directory: &Path) -> Self {
+        self.map_paths(&|path| crate::utility::normalize_path(&directory.join(path)))
+    }
+}
+
+/// The `yambs.lock` file next to the manifest of a project.
+///
+/// The lockfile records the dependency graph as it was resolved: the commits of git
+/// dependencies, the manifests of source dependencies, the versions and libraries of pkg-config
+/// and Conan packages and the include directories of header only dependencies. Since dependencies can
+/// resolve differently for debug and release builds, everything but the git commits is recorded
+/// per build type.
+///
+/// Paths of source and header only dependencies are stored relative to the manifest directory,
+/// unless they only have the root directory in common with it.
+///
+/// Git dependencies found in the lockfile are checked out at the locked commit. Every dependency
+/// resolved while configuring is recorded again, so dependencies no longer in use are dropped
+/// when the lockfile is written. The dependencies of the other build type are kept as they were.
+#[derive(Debug, Clone)]
+pub struct Lockfile {
+    path: PathBuf,
+    build_type: BuildType,
+    locked: LockfileData,
+    resolved: LockfileData,
+}
+
+impl Lockfile {
+    /// Reads the lockfile in `manifest_dir`. A missing lockfile is treated as empty.
+    pub fn from_manifest_dir(
+        manifest_dir: &Path,
+        build_type: &BuildType,
+    ) -> Result<Self, LockfileError> {
+        let path = manifest_dir.join(LOCKFILE_NAME);
+        let locked: LockfileData = if path.is_file() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|err| LockfileError::Read(path.clone(), err))?;
+            toml::from_str::<LockfileData>(&content)
+                .map_err(|err| LockfileError::Parse(path.clone(), err))?
+                .migrated(&path)?
+                .resolved_in(manifest_dir)
+        } else {
+            LockfileData {
+                version: LOCKFILE_VERSION,
+                ..LockfileData::default()
+            }
+        };
+        let mut resolved = locked.clone();
+        resolved.git.clear();
+        *resolved.dependencies_mut(build_type) = ResolvedDependencies::default();
+        Ok(Self {
+            path,
+            build_type: build_type.clone(),
+            locked,
+            resolved,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The commit `reference` of the git dependency was locked to, if any.
+    pub fn locked_git_commit(
+        &self,
+        name: &str,
+        url: &str,
+        reference: &GitReference,
+    ) -> Option<&str> {
+        self.locked
+            .git
+            .iter()
+            .find(|locked| locked.locks(name, url, reference))
+            .map(|locked| locked.commit.as_str())
+    }
+
+    pub fn record_git(&mut self, dependency: LockedGitDependency) {
+        record(&mut self.resolved.git, dependency);
+    }
+
+    pub fn record_source(&mut self, dependency: LockedSourceDependency) {
+        record(
+            &mut self.resolved.dependencies_mut(&self.build_type).source,
+            dependency,
+        );
+    }
+
+    pub fn record_pkg_config(&mut self, dependency: LockedPkgConfigDependency) {
+        record(
+            &mut self.resolved.dependencies_mut(&self.build_type).pkg_config,
+            dependency,
+        );
+    }
+
+    pub fn record_conan(&mut self, dependency: LockedConanDependency) {
+        record(
+            &mut self.resolved.dependencies_mut(&self.build_type).conan,
+            dependency,
+        );
+    }
+
+    pub fn record_header_only(&mut self, dependency: LockedHeaderOnlyDependency) {
+        record(
+            &mut self.resolved.dependencies_mut(&self.build_type).header_only,
+            dependency,
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resolved.git.is_empty()
+            && self.resolved.debug.is_empty()
+            && self.resolved.release.is_empty()
+    }
+
+    /// Checks that the resolved dependencies of the build type are the same as the locked
+    /// dependencies.
+    pub fn verify(&self) -> Result<(), LockfileError> {
+        if !self.path.is_file() {
+            return Err(LockfileError::Missing(self.path.clone()));
+        }
+        let locked = self.locked.sorted();
+        let resolved = self.resolved.sorted();
+        let locked_dependencies = locked.dependencies(&self.build_type);
+        let resolved_dependencies = resolved.dependencies(&self.build_type);
+        let mut differences = Vec::new();
+        differences.extend(difference(&locked.git, &resolved.git));
+        differences.extend(difference(
+            &locked_dependencies.source,
+            &resolved_dependencies.source,
+        ));
+        differences.extend(difference(
+            &locked_dependencies.pkg_config,
+            &resolved_dependencies.pkg_config,
+        ));
+        differences.extend(difference(
+            &locked_dependencies.conan,
+            &resolved_dependencies.conan,
+        ));
+        differences.extend(difference(
+            &locked_dependencies.header_only,
+            &resolved_dependencies.header_only,
+        ));
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(LockfileError::Mismatch(
+                self.path.clone(),
+                differences.join("\n"),
+            ))
+        }
+    }
+
+    /// Writes the resolved dependencies to the lockfile.
+    ///
+    /// Nothing is written if the project has no dependencies to lock and there is no lockfile.
+    pub fn write(&self) -> Result<(), LockfileError> {
+        if self.is_empty() && !self.path.is_file() {
+            return Ok(());
+        }
+        let content = self.render()?;
+        if std::fs::read_to_string(&self.path).ok().as_deref() == Some(content.as_str()) {
+            return Ok(());
+        }
+        log::debug!("Writing lockfile {}", self.path.display());
+        std::fs::write(&self.path, content)
+            .map_err(|err| LockfileError::Write(self.path.clone(), err))
+    }
+
+    fn render(&self) -> Result<String, LockfileError> {
+        let directory = self.path.parent().unwrap_or_else(|| Path::new(""));
+        let data = self.resolved.sorted().relative_to(directory);
+        let mut sections = vec![format!("version = {}\n", LOCKFILE_VERSION)];
+        sections.extend(tables("git", &data.git)?);
+        for (build_type, dependencies) in [("debug", &data.debug), ("release", &data.release)] {
+            let name = |kind: &str| format!("{}.{}", build_type, kind);
+            sections.extend(tables(&name("source"), &dependencies.source)?);
+            sections.extend(tables(&name("pkg_config"), &dependencies.pkg_config)?);
+            sections.extend(tables(&name("conan"), &dependencies.conan)?);
+            sections.extend(tables(&name("header_only"), &dependencies.header_only)?);
+        }
+        Ok(format!("{}\n{}", LOCKFILE_HEADER, sections.join("\n")))
+    }
+}
+
+/// The SHA-256 hash of the manifest in `manifest_dir`.
+pub fn manifest_hash(manifest_dir: &Path) -> Result<String, LockfileError> {
+    let manifest_path = manifest_dir.join(YAMBS_MANIFEST_NAME);
+    let content = std::fs::read(&manifest_path)
+        .map_err(|err| LockfileError::ReadManifest(manifest_path, err))?;
+    Ok(crate::utility::sha256_hex(&content))
+}
+
+/// Every entry as a table of the array of tables `name`.
+fn tables<T: serde::Serialize>(name: &str, entries: &[T]) -> Result<Vec<String>, LockfileError> {
+    entries
+        .iter()
+        .map(|entry| {
+            let content = toml::to_string(entry).map_err(LockfileError::Serialize)?;
+            Ok(format!("[[{}]]\n{}", name, content))
+        })
+        .collect()
+}
+
+fn record<T: PartialEq>(entries: &mut Vec<T>, entry: T) {
+    if !entries.contains(&entry) {
+        entries.push(entry);
+    }
+}
+
+/// Describes the entries removed from `locked` with `-` and the entries added in `resolved`
+/// with `+`.
+fn difference<T>(locked: &[T], resolved: &[T]) -> Vec<String>
+where
+    T: PartialEq + std::fmt::Display,
+{
+    let removed = locked
+        .iter()
+        .filter(|entry| !resolved.contains(entry))
+        .map(|entry| format!("  - {}", entry));
+    let added = resolved
+        .iter()
+        .filter(|entry| !locked.contains(entry))
+        .map(|entry| format!("  + {}", entry));
+    removed.chain(added).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const URL: &str = "https://example.com/greet.git";
+
+    #[test]
+    fn locked_commit_is_found_for_same_reference() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        let tag = GitReference::Tag("v1.0".to_string());
+        lockfile.record_git(LockedGitDependency::new("greet", URL, &tag, "abc123"));
+        lockfile.write().unwrap();
+
+        let lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        assert_eq!(
+            lockfile.locked_git_commit("greet", URL, &tag),
+            Some("abc123")
+        );
+        assert_eq!(
+            lockfile.locked_git_commit("greet", URL, &GitReference::Tag("v2.0".to_string())),
+            None
+        );
+        assert_eq!(
+            lockfile.locked_git_commit("greet", URL, &GitReference::DefaultBranch),
+            None
+        );
+    }
+
+    #[test]
+    fn lockfile_only_contains_resolved_dependencies() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        let branch = GitReference::Branch("main".to_string());
+        lockfile.record_git(LockedGitDependency::new("greet", URL, &branch, "abc123"));
+        lockfile.record_git(LockedGitDependency::new("other", URL, &branch, "def456"));
+        lockfile.write().unwrap();
+
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_git(LockedGitDependency::new("greet", URL, &branch, "abc123"));
+        lockfile.write().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(LOCKFILE_NAME)).unwrap(),
+            indoc::indoc!(
+                r#"
+                # This file is generated by yambs. It is not intended for manual editing.
+
+                version = 2
+
+                [[git]]
+                name = "greet"
+                url = "https://example.com/greet.git"
+                branch = "main"
+                commit = "abc123"
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn verify_reports_changed_dependencies() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_pkg_config(LockedPkgConfigDependency {
+            name: "zlib".to_string(),
+            version: Some("1.2.13".to_string()),
+            libraries: vec!["/usr/lib/libz.so".to_string()],
+        });
+        lockfile.record_header_only(LockedHeaderOnlyDependency {
+            name: "catch2".to_string(),
+            include_directory: PathBuf::from("/opt/catch2/include"),
+        });
+        lockfile.write().unwrap();
+
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_header_only(LockedHeaderOnlyDependency {
+            name: "catch2".to_string(),
+            include_directory: PathBuf::from("/opt/catch2/include"),
+        });
+        lockfile.record_pkg_config(LockedPkgConfigDependency {
+            name: "zlib".to_string(),
+            version: Some("1.2.13".to_string()),
+            libraries: vec!["/usr/lib/libz.so".to_string()],
+        });
+        assert!(lockfile.verify().is_ok());
+
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_header_only(LockedHeaderOnlyDependency {
+            name: "catch2".to_string(),
+            include_directory: PathBuf::from("/opt/catch2/include"),
+        });
+        lockfile.record_pkg_config(LockedPkgConfigDependency {
+            name: "zlib".to_string(),
+            version: Some("1.3".to_string()),
+            libraries: vec!["/usr/local/lib/libz.so".to_string()],
+        });
+        match lockfile.verify() {
+            Err(LockfileError::Mismatch(_, differences)) => assert_eq!(
+                differences,
+                "  - pkg-config dependency zlib 1.2.13 linking [/usr/lib/libz.so]\n  \
+                 + pkg-config dependency zlib 1.3 linking [/usr/local/lib/libz.so]"
+            ),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn dependencies_are_locked_per_build_type() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let zlib = |directory: &str| LockedPkgConfigDependency {
+            name: "zlib".to_string(),
+            version: Some("1.3.1".to_string()),
+            libraries: vec![format!("/opt/vcpkg/{}/libz.a", directory)],
+        };
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_pkg_config(zlib("debug/lib"));
+        lockfile.write().unwrap();
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Release).unwrap();
+        lockfile.record_pkg_config(zlib("lib"));
+        lockfile.write().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(LOCKFILE_NAME)).unwrap(),
+            indoc::indoc!(
+                r#"
+                # This file is generated by yambs. It is not intended for manual editing.
+
+                version = 2
+
+                [[debug.pkg_config]]
+                name = "zlib"
+                version = "1.3.1"
+                libraries = ["/opt/vcpkg/debug/lib/libz.a"]
+
+                [[release.pkg_config]]
+                name = "zlib"
+                version = "1.3.1"
+                libraries = ["/opt/vcpkg/lib/libz.a"]
+                "#
+            )
+        );
+
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_pkg_config(zlib("debug/lib"));
+        assert!(lockfile.verify().is_ok());
+    }
+
+    #[test]
+    fn paths_are_stored_relative_to_manifest_directory() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let project = dir.path().join("project");
+        let greet = dir.path().join("greet");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&greet).unwrap();
+        std::fs::write(greet.join(YAMBS_MANIFEST_NAME), "[library.greet]\n").unwrap();
+        let greet_dependency = LockedSourceDependency::from_path("greet", &greet).unwrap();
+        let catch2 = |include_directory: &Path| LockedHeaderOnlyDependency {
+            name: "catch2".to_string(),
+            include_directory: include_directory.to_path_buf(),
+        };
+        let mut lockfile = Lockfile::from_manifest_dir(&project, &BuildType::Debug).unwrap();
+        lockfile.record_source(greet_dependency.clone());
+        lockfile.record_header_only(catch2(&project.join("third_party/catch2")));
+        lockfile.write().unwrap();
+
+        let content = std::fs::read_to_string(project.join(LOCKFILE_NAME)).unwrap();
+        assert!(content.contains("path = \"../greet\""), "{}", content);
+        assert!(
+            content.contains("include_directory = \"third_party/catch2\""),
+            "{}",
+            content
+        );
+
+        let mut lockfile = Lockfile::from_manifest_dir(&project, &BuildType::Debug).unwrap();
+        lockfile.record_source(greet_dependency);
+        lockfile.record_header_only(catch2(&project.join("third_party/catch2")));
+        assert!(lockfile.verify().is_ok());
+    }
+
+    #[test]
+    fn version_1_lockfile_is_migrated_to_both_build_types() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        std::fs::write(
+            dir.path().join(LOCKFILE_NAME),
+            indoc::indoc!(
+                r#"
+                [[git]]
+                name = "greet"
+                url = "https://example.com/greet.git"
+                commit = "abc123"
+
+                [[pkg_config]]
+                name = "zlib"
+                version = "1.3.1"
+                libraries = ["/usr/lib/libz.so"]
+                "#
+            ),
+        )
+        .unwrap();
+        let zlib = LockedPkgConfigDependency {
+            name: "zlib".to_string(),
+            version: Some("1.3.1".to_string()),
+            libraries: vec!["/usr/lib/libz.so".to_string()],
+        };
+
+        for build_type in [BuildType::Debug, BuildType::Release] {
+            let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &build_type).unwrap();
+            assert_eq!(
+                lockfile.locked_git_commit("greet", URL, &GitReference::DefaultBranch),
+                Some("abc123")
+            );
+            lockfile.record_git(LockedGitDependency::new(
+                "greet",
+                URL,
+                &GitReference::DefaultBranch,
+                "abc123",
+            ));
+            lockfile.record_pkg_config(zlib.clone());
+            assert!(lockfile.verify().is_ok());
+        }
+
+        let mut lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.record_git(LockedGitDependency::new(
+            "greet",
+            URL,
+            &GitReference::DefaultBranch,
+            "abc123",
+        ));
+        lockfile.record_pkg_config(zlib);
+        lockfile.write().unwrap();
+        let content = std::fs::read_to_string(dir.path().join(LOCKFILE_NAME)).unwrap();
+        assert!(content.contains("version = 2\n"), "{}", content);
+        assert!(content.contains("[[debug.pkg_config]]"), "{}", content);
+        assert!(content.contains("[[release.pkg_config]]"), "{}", content);
+    }
+
+    #[test]
+    fn newer_lockfile_version_is_an_error() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        std::fs::write(dir.path().join(LOCKFILE_NAME), "version = 3\n").unwrap();
+        assert!(matches!(
+            Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug),
+            Err(LockfileError::UnsupportedVersion(_, 3))
+        ));
+    }
+
+    #[test]
+    fn verify_requires_lockfile() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        assert!(matches!(lockfile.verify(), Err(LockfileError::Missing(_))));
+    }
+
+    #[test]
+    fn source_dependency_is_identified_by_manifest_hash() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        std::fs::write(dir.path().join(YAMBS_MANIFEST_NAME), "[library.greet]\n").unwrap();
+        let first = LockedSourceDependency::from_path("greet", dir.path()).unwrap();
+        std::fs::write(dir.path().join(YAMBS_MANIFEST_NAME), "[library.hello]\n").unwrap();
+        let second = LockedSourceDependency::from_path("greet", dir.path()).unwrap();
+        assert_eq!(first.manifest_hash.len(), 64);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn no_lockfile_is_written_without_dependencies() {
+        let dir = tempdir::TempDir::new("lockfile").unwrap();
+        let lockfile = Lockfile::from_manifest_dir(dir.path(), &BuildType::Debug).unwrap();
+        lockfile.write().unwrap();
+        assert!(!dir.path().join(LOCKFILE_NAME).exists());
+    }
+}
@@ -11,20 +11,34 @@ use std::path::Path;
 use yambs::toolchain::ToolchainError;
 
 use parser::types::Language;
-use yambs::build_target::{target_registry::TargetRegistry, BuildTarget};
-use yambs::cli::command_line::{BuildOpts, CommandLine, ManifestDirectory, RemakeOpts, Subcommand};
+use yambs::build_target::dependency_graph::{self, DependencyGraph};
+use yambs::build_target::{target_registry::TargetRegistry, BuildTarget, Project, Resolution};
+use yambs::cache::{CachedConfiguration, ConfigureCache, Fingerprint};
+use yambs::cli::command_line::{
+    BuildOpts, CacheCompileOpts, CommandLine, DetectOpts, GraphFormat, GraphOpts,
+    ManifestDirectory, PackageOpts, RemakeOpts, ResolveOpts, ShowOpts, Subcommand,
+    ToolchainCommand, TreeFormat, TreeOpts,
+};
 use yambs::cli::configurations::BuildType;
+use yambs::cli::BuildDirectory;
 use yambs::compiler::Compiler;
 use yambs::generator::{
     makefile::make::BuildProcess, makefile::Make, Generator, GeneratorType, MakefileGenerator,
 };
+use yambs::install::Installer;
+use yambs::lockfile::{Lockfile, LockfileError};
 use yambs::logger;
 use yambs::manifest;
+use yambs::object_cache::{CompileCommand, ObjectCache, ObjectCacheError};
 use yambs::output;
 use yambs::output::Output;
+use yambs::overrides::Overrides;
+use yambs::package::{Package, Strip};
 use yambs::parser;
 use yambs::progress;
-use yambs::toolchain::{NormalizedToolchain, TOOLCHAIN_FILE_NAME};
+use yambs::toolchain::detect::Detection;
+use yambs::toolchain::identity::ToolchainIdentity;
+use yambs::toolchain::{NamedToolchain, NormalizedToolchain, TOOLCHAIN_FILE_NAME};
 use yambs::ProjectConfig;
 use yambs::YAMBS_MANIFEST_NAME;
 use yambs::{YAMBS_BUILD_DIR_VAR, YAMBS_BUILD_TYPE, YAMBS_MANIFEST_DIR};
@@ -41,8 +55,39 @@ fn main() -> anyhow::Result<()> {
 
     if let Some(subcommand) = command_line.subcommand {
         match subcommand {
-            Subcommand::Build(mut build_opts) => do_build(&mut build_opts, &output)?,
+            Subcommand::Build(mut build_opts) => {
+                use_toolchain_build_directory(
+                    &mut build_opts.build_directory,
+                    &build_opts.manifest_dir,
+                    build_opts.configuration.toolchain.as_deref(),
+                )?;
+                do_build(&build_opts, &output)?
+            }
             Subcommand::Remake(ref remake_opts) => do_remake(remake_opts)?,
+            Subcommand::Package(mut package_opts) => {
+                let build_opts = &mut package_opts.build_opts;
+                use_toolchain_build_directory(
+                    &mut build_opts.build_directory,
+                    &build_opts.manifest_dir,
+                    build_opts.configuration.toolchain.as_deref(),
+                )?;
+                do_package(&package_opts, &output)?
+            }
+            Subcommand::Tree(mut tree_opts) => {
+                use_resolve_build_directory(&mut tree_opts.resolve)?;
+                do_tree(&tree_opts)?
+            }
+            Subcommand::Graph(mut graph_opts) => {
+                use_resolve_build_directory(&mut graph_opts.resolve)?;
+                do_graph(&graph_opts)?
+            }
+            Subcommand::CacheCompile(ref cache_compile_opts) => {
+                do_cache_compile(cache_compile_opts, &output)?
+            }
+            Subcommand::Toolchain(ref toolchain_opts) => match toolchain_opts.command {
+                ToolchainCommand::Detect(ref detect_opts) => do_detect(detect_opts, &output)?,
+                ToolchainCommand::Show(ref show_opts) => do_show(show_opts)?,
+            },
         }
     } else {
         CommandLine::command().print_help()?;
@@ -52,6 +97,21 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Puts the build files of a toolchain selected with --toolchain in a subdirectory of the build
+/// directory named after it, so that a project can be built with several toolchains side by side.
+fn use_toolchain_build_directory(
+    build_directory: &mut BuildDirectory,
+    manifest_dir: &ManifestDirectory,
+    toolchain: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(toolchain) = toolchain {
+        let named_toolchain = NamedToolchain::locate(toolchain, manifest_dir.as_path())?;
+        *build_directory =
+            BuildDirectory::from(build_directory.as_path().join(named_toolchain.name));
+    }
+    Ok(())
+}
+
 fn log_invoked_command() {
     log::info!(
         "Command line: {}",
@@ -64,15 +124,19 @@ fn log_invoked_command() {
     )
 }
 
-fn initialize_preset_variables(opts: &BuildOpts) -> anyhow::Result<()> {
+fn initialize_preset_variables(
+    build_directory: &BuildDirectory,
+    manifest_dir: &ManifestDirectory,
+    build_type: &BuildType,
+) -> anyhow::Result<()> {
     YAMBS_BUILD_DIR_VAR
-        .set(opts.build_directory.clone())
+        .set(build_directory.clone())
         .map_err(|_| anyhow::anyhow!("Error occured fetching build directory"))?;
     YAMBS_MANIFEST_DIR
-        .set(opts.manifest_dir.clone())
+        .set(manifest_dir.clone())
         .map_err(|_| anyhow::anyhow!("Error occurred fetching manifest directory"))?;
     YAMBS_BUILD_TYPE
-        .set(opts.configuration.build_type.clone())
+        .set(build_type.clone())
         .map_err(|_| anyhow::anyhow!("Error occured fetching build type"))?;
     Ok(())
 }
@@ -131,66 +195,113 @@ pub fn construct_generator(
     }
 }
 
+struct ConfiguredProject {
+    manifest: manifest::ParsedManifest,
+    registry: TargetRegistry,
+    buildfile_directory: std::path::PathBuf,
+}
+
 fn do_build(opts: &BuildOpts, output: &Output) -> anyhow::Result<()> {
     let logger = logger::Logger::init(opts.build_directory.as_path(), log::LevelFilter::Trace)?;
     log_invoked_command();
-
-    initialize_preset_variables(opts)?;
     log::trace!("do_build");
 
-    let mut dependency_registry = TargetRegistry::new();
-    let manifest_path = locate_manifest(&opts.manifest_dir)?;
-    let manifest = parser::parse(&manifest_path).with_context(|| "Failed to parse manifest")?;
-
-    // override the command line settings if there are configurations set in the manifest
-    let std = if let Some(ref std) = opts.configuration.standard {
-        log::info!("Using standard {} given on command line", std.to_string());
-        std.clone()
-    } else {
-        if let Some(std) = manifest
-            .data
-            .project_config
-            .as_ref()
-            .and_then(|pc| pc.std.clone())
-        {
-            log::info!("Using standard {} found in manifest", std.to_string());
-            std
-        } else {
-            anyhow::bail!(
-                "No standard is set! Please set one either through command line or in manifest."
-            );
-        }
-    };
+    let project = configure_project(opts, output)?;
+    build_project(&project.buildfile_directory, output, opts, &logger)?;
+    Ok(())
+}
 
-    let language = if let Some(language) = manifest
-        .data
-        .project_config
-        .as_ref()
-        .and_then(|pc| pc.language.clone())
-    {
-        log::info!("Using language {} found in manifest", language.to_string());
-        language
-    } else {
-        log::warn!("No language specified. Using C++");
-        Language::CXX
-    };
+fn do_package(opts: &PackageOpts, output: &Output) -> anyhow::Result<()> {
+    let build_opts = &opts.build_opts;
+    let logger = logger::Logger::init(
+        build_opts.build_directory.as_path(),
+        log::LevelFilter::Trace,
+    )?;
+    log_invoked_command();
+    log::trace!("do_package");
+
+    let project = configure_project(build_opts, output)?;
+    let project_config = project.manifest.data.project_config.as_ref();
+    let name = project_config
+        .and_then(|pc| pc.name.as_ref())
+        .context("Packaging requires project_config.name to be set in the manifest")?;
+    let version = project_config
+        .and_then(|pc| pc.version.as_ref())
+        .context("Packaging requires project_config.version to be set in the manifest")?;
+
+    if !build_project(&project.buildfile_directory, output, build_opts, &logger)? {
+        anyhow::bail!("Build failed. No package was created.");
+    }
 
-    let project_config = ProjectConfig {
-        std,
-        language,
-        build_directory: opts.build_directory.clone(),
-        build_type: opts.configuration.build_type.clone(),
-        generator_type: opts.configuration.generator_type.clone(),
-        defines: opts.configuration.defines.clone(),
-    };
+    let package = Package::new(name, version, &build_opts.configuration.build_type);
+    let staging_directory = build_opts
+        .build_directory
+        .as_path()
+        .join("package")
+        .join(package.stem());
+    if staging_directory.is_dir() {
+        std::fs::remove_dir_all(&staging_directory).with_context(|| {
+            format!(
+                "Failed to remove old staging directory {}",
+                staging_directory.display()
+            )
+        })?;
+    }
+    let installer = Installer::new(
+        &staging_directory,
+        &project.buildfile_directory,
+        &Project::from_manifest_data(&project.manifest.data),
+    );
+    installer
+        .install(&project.registry)
+        .with_context(|| "Failed to stage project for packaging")?;
+    if build_opts.configuration.build_type == BuildType::Release {
+        let toolchain = find_toolchain(
+            &build_opts.manifest_dir,
+            build_opts.configuration.toolchain.as_deref(),
+        )?;
+        if let Some(strip) = toolchain.strip {
+            let debug_directory = build_opts
+                .build_directory
+                .as_path()
+                .join("package")
+                .join(format!("{}.debug", package.stem()));
+            let strip = Strip {
+                strip,
+                objcopy: toolchain.objcopy,
+            };
+            strip
+                .strip_binaries(installer.prefix(), &debug_directory)
+                .with_context(|| "Failed to strip packaged binaries")?;
+            if strip.objcopy.is_some() {
+                output.status(&format!(
+                    "Debug information kept in {}",
+                    debug_directory.display()
+                ));
+            }
+        }
+    }
+    let archive = package
+        .create_archive(installer.prefix(), build_opts.build_directory.as_path())
+        .with_context(|| "Failed to create package archive")?;
+    output.status(&format!("Created package {}", archive.display()));
+    Ok(())
+}
 
+fn find_toolchain(
+    manifest_dir: &ManifestDirectory,
+    toolchain: Option<&str>,
+) -> anyhow::Result<NormalizedToolchain> {
+    if let Some(toolchain) = toolchain {
+        let named_toolchain = NamedToolchain::locate(toolchain, manifest_dir.as_path())?;
+        return detect_toolchain_file(&named_toolchain.path);
+    }
     // FIXME: The logic here is quirky. It is easy to mess up and understand the flow.
     // Can it be simplified?
     // There should be made an integration test for this to check if it is working as intended.
     let toolchain = {
         match detect_toolchain_file(
-            &opts
-                .manifest_dir
+            &manifest_dir
                 .as_path()
                 .join(".yambs")
                 .join(TOOLCHAIN_FILE_NAME),
@@ -221,7 +332,7 @@ fn do_build(opts: &BuildOpts, output: &Output) -> anyhow::Result<()> {
             match tc_err {
                 ToolchainError::FailedToParseToolchainFile(_, _) => return Err(e),
                 _ => {
-                    println!("Warning: Did not find any toolchain file. Attempt using CXX value");
+                    eprintln!("Warning: Did not find any toolchain file. Attempt using CXX value");
                     match NormalizedToolchain::new() {
                         Ok(tc) => tc,
                         Err(_) => {
@@ -240,23 +351,350 @@ fn do_build(opts: &BuildOpts, output: &Output) -> anyhow::Result<()> {
         }
     };
 
+    Ok(toolchain)
+}
+
+fn configure_project(opts: &BuildOpts, output: &Output) -> anyhow::Result<ConfiguredProject> {
+    initialize_preset_variables(
+        &opts.build_directory,
+        &opts.manifest_dir,
+        &opts.configuration.build_type,
+    )?;
+    log::trace!("configure_project");
+
+    let manifest_path = locate_manifest(&opts.manifest_dir)?;
+    let manifest = parser::parse(&manifest_path).with_context(|| "Failed to parse manifest")?;
+
+    let cache = ConfigureCache::new(
+        opts.build_directory.as_path(),
+        &opts.configuration.build_type,
+    );
+    if let Some(cached) = cache.load(&opts.configuration, opts.locked) {
+        log::debug!("Using cached configuration from {}", cache.path().display());
+        output.status(&format!(
+            "Configuration is up to date ({} build targets)",
+            cached.registry.number_of_targets()
+        ));
+        return Ok(ConfiguredProject {
+            manifest,
+            registry: cached.registry,
+            buildfile_directory: cached.buildfile_directory,
+        });
+    }
+
+    // override the command line settings if there are configurations set in the manifest
+    let std = if let Some(ref std) = opts.configuration.standard {
+        log::info!("Using standard {} given on command line", std.to_string());
+        std.clone()
+    } else {
+        if let Some(std) = manifest
+            .data
+            .project_config
+            .as_ref()
+            .and_then(|pc| pc.std.clone())
+        {
+            log::info!("Using standard {} found in manifest", std.to_string());
+            std
+        } else {
+            anyhow::bail!(
+                "No standard is set! Please set one either through command line or in manifest."
+            );
+        }
+    };
+
+    let language = if let Some(language) = manifest
+        .data
+        .project_config
+        .as_ref()
+        .and_then(|pc| pc.language.clone())
+    {
+        log::info!("Using language {} found in manifest", language.to_string());
+        language
+    } else {
+        log::warn!("No language specified. Using C++");
+        Language::CXX
+    };
+
+    let project_config = ProjectConfig {
+        std,
+        language,
+        build_directory: opts.build_directory.clone(),
+        build_type: opts.configuration.build_type.clone(),
+        generator_type: opts.configuration.generator_type.clone(),
+        defines: opts.configuration.defines.clone(),
+        object_cache: opts.configuration.object_cache,
+    };
+
+    let mut toolchain =
+        find_toolchain(&opts.manifest_dir, opts.configuration.toolchain.as_deref())?;
+    if opts.configuration.no_launcher {
+        toolchain.launcher = None;
+    } else if let Some(ref launcher) = toolchain.launcher {
+        log::info!("Using launcher {}", launcher.path.display());
+    }
+    let identity = toolchain
+        .identity()
+        .with_context(|| "Failed to identify toolchain")?;
+    check_toolchain_identity(&identity, opts.build_directory.as_path(), output)?;
     let toolchain = Rc::new(RefCell::new(toolchain));
 
     evaluate_compiler(&toolchain, &project_config)?;
 
     let mut generator = construct_generator(&project_config, &toolchain)?;
-    parse_and_register_dependencies(
-        &manifest,
-        output,
-        &mut dependency_registry,
-        &toolchain,
-        &opts.configuration.build_type,
-    )
-    .with_context(|| "An error occured when registering project dependencies")?;
+    let (dependency_registry, lockfile, overrides) =
+        resolve_dependencies(&manifest, &toolchain, &opts.configuration.build_type)?;
+    output.status(&format!(
+        "Registered {} build targets",
+        dependency_registry.number_of_targets()
+    ));
+    for overridden in overrides.used() {
+        output.warning(&format!("Dependency {}", overridden));
+    }
+    // An overridden dependency is not the one the lockfile describes, so the lockfile is left
+    // untouched while any override is in use.
+    if opts.locked {
+        if !overrides.used().is_empty() {
+            return Err(LockfileError::Overridden(
+                lockfile.path().to_path_buf(),
+                overrides
+                    .used()
+                    .iter()
+                    .map(|overridden| format!("  {}", overridden))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            )
+            .into());
+        }
+        lockfile.verify()?;
+    } else if overrides.used().is_empty() {
+        lockfile.write()?;
+    } else {
+        output.warning(&format!(
+            "{} is not updated while dependencies are overridden",
+            lockfile.path().display()
+        ));
+    }
 
     let buildfile_directory = generate_build_files(&mut generator, &dependency_registry, opts)?;
+    if overrides.used().is_empty() {
+        cache
+            .store(&CachedConfiguration {
+                fingerprint: Fingerprint::new(
+                    &opts.configuration,
+                    opts.locked,
+                    &manifest.manifest.directory,
+                    &dependency_registry,
+                    &identity.compilers(),
+                ),
+                registry: dependency_registry.clone(),
+                buildfile_directory: buildfile_directory.clone(),
+            })
+            .with_context(|| "Failed to cache configuration")?;
+    } else {
+        cache
+            .invalidate()
+            .with_context(|| "Failed to invalidate cached configuration")?;
+    }
+
+    Ok(ConfiguredProject {
+        manifest,
+        registry: dependency_registry,
+        buildfile_directory,
+    })
+}
+
+/// Records the identity of the toolchain in the build directory. If it was configured with
+/// another toolchain, the objects are recompiled, since the command signatures include the
+/// identity.
+fn check_toolchain_identity(
+    identity: &ToolchainIdentity,
+    build_directory: &Path,
+    output: &Output,
+) -> anyhow::Result<()> {
+    if let Some(previous) = ToolchainIdentity::load(build_directory) {
+        if previous.fingerprint != identity.fingerprint {
+            output.warning(&format!(
+                "{} was configured with another toolchain. Everything is rebuilt.",
+                build_directory.display()
+            ));
+            for change in identity.changes(&previous) {
+                output.warning_without_prefix(&format!("  {}", change));
+            }
+        }
+    }
+    identity.store(build_directory)?;
+    Ok(())
+}
+
+/// The dependencies of a project, resolved as they are for a build.
+struct ResolvedProject {
+    manifest: manifest::ParsedManifest,
+    registry: TargetRegistry,
+    /// Directory the build files of the build type are put in.
+    output_directory: std::path::PathBuf,
+}
+
+fn use_resolve_build_directory(opts: &mut ResolveOpts) -> anyhow::Result<()> {
+    use_toolchain_build_directory(
+        &mut opts.build_directory,
+        &opts.manifest_dir,
+        opts.toolchain.as_deref(),
+    )
+}
+
+fn resolve_project(opts: &ResolveOpts) -> anyhow::Result<ResolvedProject> {
+    initialize_preset_variables(&opts.build_directory, &opts.manifest_dir, &opts.build_type)?;
+    let manifest_path = locate_manifest(&opts.manifest_dir)?;
+    let manifest = parser::parse(&manifest_path).with_context(|| "Failed to parse manifest")?;
+    let toolchain = Rc::new(RefCell::new(find_toolchain(
+        &opts.manifest_dir,
+        opts.toolchain.as_deref(),
+    )?));
+    let (registry, _, _) = resolve_dependencies(&manifest, &toolchain, &opts.build_type)?;
+    Ok(ResolvedProject {
+        manifest,
+        registry,
+        output_directory: opts
+            .build_directory
+            .as_path()
+            .join(opts.build_type.to_string()),
+    })
+}
 
-    build_project(&buildfile_directory, output, opts, &logger)?;
+fn do_tree(opts: &TreeOpts) -> anyhow::Result<()> {
+    let project = resolve_project(&opts.resolve)?;
+    let graph = DependencyGraph::from_registry(
+        &project.registry,
+        &project.manifest.manifest.directory,
+        &project.output_directory,
+    );
+    let trees = match opts.invert {
+        Some(ref name) => {
+            let trees = graph.inverted_tree(name);
+            if trees.is_empty() {
+                anyhow::bail!("No target or dependency named {} was found", name);
+            }
+            trees
+        }
+        None => graph.tree(),
+    };
+    match opts.format {
+        TreeFormat::Text => print!("{}", dependency_graph::render_trees(&trees)),
+        TreeFormat::Json => println!("{}", serde_json::to_string_pretty(&trees)?),
+    }
+    Ok(())
+}
+
+fn do_graph(opts: &GraphOpts) -> anyhow::Result<()> {
+    let project = resolve_project(&opts.resolve)?;
+    let mut graph = DependencyGraph::from_registry(
+        &project.registry,
+        &project.manifest.manifest.directory,
+        &project.output_directory,
+    );
+    if opts.objects {
+        graph.add_object_files(&project.registry, &project.output_directory);
+    }
+    match opts.format {
+        GraphFormat::Dot => print!("{}", graph.render_dot()),
+        GraphFormat::Mermaid => print!("{}", graph.render_mermaid()),
+    }
+    Ok(())
+}
+
+fn do_cache_compile(opts: &CacheCompileOpts, output: &Output) -> anyhow::Result<()> {
+    let status = match CompileCommand::parse(opts.launcher.as_deref(), &opts.command) {
+        Some(command) => {
+            match ObjectCache::from_environment().and_then(|cache| cache.compile(&command)) {
+                Ok((status, _)) => status,
+                Err(err) => {
+                    // Errors in the source are reported by the compiler itself.
+                    if !matches!(err, ObjectCacheError::FailedToPreprocess(..)) {
+                        output.warning(&format!("Object cache is not used: {}", err));
+                    }
+                    command.run()?
+                }
+            }
+        }
+        None => {
+            let (program, arguments) = match opts.launcher {
+                Some(ref launcher) => (launcher.as_os_str(), &opts.command[..]),
+                None => (std::ffi::OsStr::new(&opts.command[0]), &opts.command[1..]),
+            };
+            std::process::Command::new(program)
+                .args(arguments)
+                .status()
+                .with_context(|| format!("Failed to run {}", opts.command[0]))?
+        }
+    };
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn do_detect(opts: &DetectOpts, output: &Output) -> anyhow::Result<()> {
+    let detection = Detection::from_path_env();
+    if opts.list {
+        print!("{}", detection.render_list());
+        return Ok(());
+    }
+
+    let (cxx, cc) = detection
+        .preferred()
+        .context("Found no pair of C++ and C compilers in PATH")?;
+    let toolchain_file = opts
+        .output
+        .clone()
+        .unwrap_or_else(|| Detection::default_output(opts.manifest_dir.as_path()));
+    if toolchain_file.exists() && !opts.force {
+        anyhow::bail!(
+            "{} already exists. Use --force to overwrite it.",
+            toolchain_file.display()
+        );
+    }
+    if let Some(parent) = toolchain_file.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&toolchain_file, detection.render_toolchain_file(cxx, cc))
+        .with_context(|| format!("Failed to write {}", toolchain_file.display()))?;
+    output.status(&format!(
+        "Wrote toolchain file {} for {} and {}",
+        toolchain_file.display(),
+        cxx.path.display(),
+        cc.path.display()
+    ));
+    Ok(())
+}
+
+fn do_show(opts: &ShowOpts) -> anyhow::Result<()> {
+    let identity = find_toolchain(&opts.manifest_dir, opts.toolchain.as_deref())?
+        .identity()
+        .with_context(|| "Failed to identify toolchain")?;
+    print!("{}", identity);
+    if let Some(ref build_directory) = opts.build_directory {
+        let mut build_directory = build_directory.clone();
+        use_toolchain_build_directory(
+            &mut build_directory,
+            &opts.manifest_dir,
+            opts.toolchain.as_deref(),
+        )?;
+        match ToolchainIdentity::load(build_directory.as_path()) {
+            Some(previous) if previous.fingerprint == identity.fingerprint => println!(
+                "{} is configured with this toolchain",
+                build_directory.as_path().display()
+            ),
+            Some(previous) => {
+                println!(
+                    "{} is configured with another toolchain ({})",
+                    build_directory.as_path().display(),
+                    previous.fingerprint
+                );
+                for change in identity.changes(&previous) {
+                    println!("  {}", change);
+                }
+            }
+            None => println!("{} is not configured", build_directory.as_path().display()),
+        }
+    }
     Ok(())
 }
 
@@ -293,12 +731,35 @@ fn generate_build_files(
     Ok(buildfile_directory)
 }
 
+/// Resolves the dependencies of the manifest, honouring the lockfile and overrides.
+fn resolve_dependencies(
+    manifest: &manifest::ParsedManifest,
+    toolchain: &Rc<RefCell<NormalizedToolchain>>,
+    build_type: &BuildType,
+) -> anyhow::Result<(TargetRegistry, Lockfile, Overrides)> {
+    let mut overrides = Overrides::load(&manifest.manifest.directory, &manifest.data.overrides)?;
+    let mut lockfile = Lockfile::from_manifest_dir(&manifest.manifest.directory, build_type)?;
+    let mut dependency_registry = TargetRegistry::new();
+    parse_and_register_dependencies(
+        manifest,
+        &mut dependency_registry,
+        toolchain,
+        build_type,
+        &mut Resolution {
+            lockfile: &mut lockfile,
+            overrides: &mut overrides,
+        },
+    )
+    .with_context(|| "An error occured when registering project dependencies")?;
+    Ok((dependency_registry, lockfile, overrides))
+}
+
 fn parse_and_register_dependencies(
     manifest: &manifest::ParsedManifest,
-    output: &Output,
     dep_registry: &mut TargetRegistry,
     toolchain: &Rc<RefCell<NormalizedToolchain>>,
     build_type: &BuildType,
+    resolution: &mut Resolution,
 ) -> anyhow::Result<()> {
     log::trace!("parse_and_register_dependencies");
     let manifest_path = manifest.manifest.directory.join(YAMBS_MANIFEST_NAME);
@@ -318,15 +779,14 @@ fn parse_and_register_dependencies(
             );
         }
         BuildTarget::target_node_from_source(
-            &manifest.manifest.directory,
+            manifest,
             build_target,
             dep_registry,
             toolchain,
             build_type,
+            resolution,
         )?;
     }
-    let number_of_targets = dep_registry.number_of_targets();
-    output.status(&format!("Registered {} build targets", number_of_targets));
     Ok(())
 }
 
@@ -349,7 +809,7 @@ fn build_project(
     output: &Output,
     opts: &BuildOpts,
     logger: &logger::Logger,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
     log::trace!("build_project");
     let output_clone = output.clone();
     let progress_path = buildfile_directory.to_path_buf();
@@ -382,17 +842,19 @@ fn build_project(
 
     let exit_status = make_thread.join().unwrap().unwrap();
     let process_code = exit_status.code();
-    match process_code {
+    let build_succeeded = match process_code {
         Some(0) => {
             let msg = format!("{}", "Build SUCCESS".green());
             pb.finish_with_message(msg);
+            true
         }
         _ => {
             let msg = format!("{}", "Build FAILED".red());
             pb.fail_with_message(msg);
+            false
         }
-    }
+    };
     let log_path = logger.path();
     output.status(&format!("Build log available at {:?}", log_path.display()));
-    Ok(())
+    Ok(build_succeeded)
 }
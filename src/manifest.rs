@@ -35,6 +35,9 @@ pub struct ParsedManifest {
 pub struct ManifestData {
     pub project_config: Option<types::ProjectConfig>,
     pub targets: Vec<targets::Target>,
+    /// The `[override]` table. Only the overrides of the root manifest are used.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub overrides: std::collections::BTreeMap<String, PathBuf>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -130,6 +133,7 @@ impl ManifestData {
                         compiler_flags: data.common_raw.compiler_flags,
                         lib_type: data.lib_type,
                         defines: data.common_raw.defines,
+                        pkg_config: data.pkg_config,
                     });
                     target_libraries.push(target_library);
                 }
@@ -152,6 +156,7 @@ impl ManifestData {
         Ok(Self {
             project_config,
             targets,
+            overrides: contents.overrides.unwrap_or_default(),
         })
     }
 }
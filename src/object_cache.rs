@@ -0,0 +1,659 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use crate::compiler::CompilerInfo;
+use crate::{find_program, FindProgramOptions};
+
+pub const OBJECT_CACHE_DIRECTORY: &str = "objects";
+/// Environment variable that sets the directory of the object cache.
+pub const OBJECT_CACHE_DIR_VAR: &str = "YAMBS_OBJECT_CACHE_DIR";
+/// Environment variable that sets the maximum size of the object cache, like "500M" or "5G".
+pub const OBJECT_CACHE_SIZE_VAR: &str = "YAMBS_OBJECT_CACHE_SIZE";
+const DEFAULT_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Stands in for the object file in cached dependency files, since the same object can be
+/// restored to a different path.
+const OBJECT_PLACEHOLDER: &str = "@YAMBS_OBJECT@";
+const ENTRY_OBJECT: &str = "object.o";
+const ENTRY_DEPFILE: &str = "object.d";
+const ENTRY_STDERR: &str = "stderr";
+/// File with the approximate total size of the entries, so that the cache only has to be walked
+/// when it may have grown past its maximum size.
+const SIZE_INDEX: &str = "size";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ObjectCacheError {
+    #[error("Failed to locate user's HOME directory")]
+    NoHomeDirectory,
+    #[error("Invalid size of object cache \"{0}\"")]
+    InvalidSize(String),
+    #[error("Failed to run {0:?}")]
+    FailedToRun(PathBuf, #[source] std::io::Error),
+    #[error("Failed to preprocess {0:?}: {1}")]
+    FailedToPreprocess(PathBuf, String),
+    #[error("Failed to identify compiler {0:?}")]
+    FailedToIdentifyCompiler(PathBuf, #[source] crate::compiler::CompilerError),
+    #[error("Failed to access object cache entry {0:?}")]
+    Entry(PathBuf, #[source] std::io::Error),
+}
+
+/// A command that compiles a single source file to an object file, as run by the generated
+/// build files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileCommand {
+    pub launcher: Option<PathBuf>,
+    pub compiler: PathBuf,
+    pub arguments: Vec<String>,
+    pub object: PathBuf,
+    pub depfile: Option<PathBuf>,
+}
+
+impl CompileCommand {
+    /// Parses `command`, starting with the compiler. Returns `None` for commands that don't
+    /// compile to exactly one object file, which can not be cached.
+    pub fn parse(launcher: Option<&Path>, command: &[String]) -> Option<Self> {
+        let (compiler, arguments) = command.split_first()?;
+        let mut object = None;
+        let mut depfile = None;
+        let mut writes_depfile = false;
+        let mut compiles = false;
+        let mut iter = arguments.iter();
+        while let Some(argument) = iter.next() {
+            match argument.as_str() {
+                "-c" => compiles = true,
+                "-o" => object = Some(PathBuf::from(iter.next()?)),
+                "-MF" => depfile = Some(PathBuf::from(iter.next()?)),
+                "-MD" | "-MMD" => writes_depfile = true,
+                "-E" | "-S" | "-" => return None,
+                _ => {}
+            }
+        }
+        let object = object.filter(|_| compiles)?;
+        let depfile = if writes_depfile {
+            depfile.or_else(|| Some(object.with_extension("d")))
+        } else {
+            None
+        };
+        Some(Self {
+            launcher: launcher.map(Path::to_path_buf),
+            compiler: PathBuf::from(compiler),
+            arguments: arguments.to_vec(),
+            object,
+            depfile,
+        })
+    }
+
+    /// The arguments that make the compiler print the preprocessed source instead.
+    fn preprocess_arguments(&self) -> Vec<String> {
+        let mut arguments = vec!["-E".to_string()];
+        let mut iter = self.arguments.iter();
+        while let Some(argument) = iter.next() {
+            match argument.as_str() {
+                "-c" | "-MD" | "-MMD" | "-MP" => {}
+                "-o" | "-MF" | "-MT" | "-MQ" => {
+                    iter.next();
+                }
+                _ => arguments.push(argument.clone()),
+            }
+        }
+        arguments
+    }
+
+    /// The arguments that affect the object file. The paths of the object and dependency files
+    /// are left out, so that objects can be restored to another build directory.
+    fn key_arguments(&self) -> Vec<&str> {
+        let mut arguments = Vec::new();
+        let mut iter = self.arguments.iter();
+        while let Some(argument) = iter.next() {
+            match argument.as_str() {
+                "-o" | "-MF" => {
+                    iter.next();
+                }
+                _ => arguments.push(argument.as_str()),
+            }
+        }
+        arguments
+    }
+
+    fn command(&self) -> Command {
+        match self.launcher {
+            Some(ref launcher) => {
+                let mut command = Command::new(launcher);
+                command.arg(&self.compiler);
+                command
+            }
+            None => Command::new(&self.compiler),
+        }
+    }
+
+    /// Runs the command, with the output of the compiler passed through.
+    pub fn run(&self) -> Result<ExitStatus, ObjectCacheError> {
+        self.command()
+            .args(&self.arguments)
+            .status()
+            .map_err(|err| ObjectCacheError::FailedToRun(self.compiler.clone(), err))
+    }
+}
+
+/// Whether an object was restored from the cache or compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Hit,
+    Miss,
+}
+
+/// A local, content-addressed cache of object files shared between builds.
+///
+/// Objects are keyed by a hash of the preprocessed source, the compile command and the identity of
+/// the compiler, and stored in `<hash prefix>/<hash>` together with their dependency file and
+/// the diagnostics of the compiler. When the cache grows past its maximum size, the least
+/// recently used entries are removed.
+#[derive(Debug, Clone)]
+pub struct ObjectCache {
+    directory: PathBuf,
+    max_size: u64,
+}
+
+impl ObjectCache {
+    pub fn new(directory: &Path, max_size: u64) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            max_size,
+        }
+    }
+
+    /// The object cache in `$YAMBS_OBJECT_CACHE_DIR`, or `~/.yambs/objects`, with its maximum
+    /// size from `$YAMBS_OBJECT_CACHE_SIZE`.
+    pub fn from_environment() -> Result<Self, ObjectCacheError> {
+        let directory = match std::env::var_os(OBJECT_CACHE_DIR_VAR) {
+            Some(directory) => PathBuf::from(directory),
+            None => home::home_dir()
+                .ok_or(ObjectCacheError::NoHomeDirectory)?
+                .join(".yambs")
+                .join(OBJECT_CACHE_DIRECTORY),
+        };
+        let max_size = match std::env::var(OBJECT_CACHE_SIZE_VAR) {
+            Ok(size) => parse_size(&size)?,
+            Err(_) => DEFAULT_MAX_SIZE,
+        };
+        Ok(Self::new(&directory, max_size))
+    }
+
+    /// Produces the object file of `command`, from the cache if possible. The exit status is the
+    /// one of the compiler, or success if the object was restored.
+    pub fn compile(
+        &self,
+        command: &CompileCommand,
+    ) -> Result<(ExitStatus, Outcome), ObjectCacheError> {
+        let key = self.key(command)?;
+        let entry = self.entry_directory(&key);
+        if self.restore(&entry, command)? {
+            log::debug!("Restored {} from object cache", command.object.display());
+            return Ok((success(), Outcome::Hit));
+        }
+
+        let output = command
+            .command()
+            .args(&command.arguments)
+            .stderr(std::process::Stdio::piped())
+            .output()
+            .map_err(|err| ObjectCacheError::FailedToRun(command.compiler.clone(), err))?;
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        if output.status.success() {
+            let size = self.store(&entry, command, &output.stderr)?;
+            self.record_stored(size)?;
+        }
+        Ok((output.status, Outcome::Miss))
+    }
+
+    fn key(&self, command: &CompileCommand) -> Result<String, ObjectCacheError> {
+        let compiler_info = self.compiler_info(&command.compiler)?;
+        let output = Command::new(&command.compiler)
+            .args(command.preprocess_arguments())
+            .output()
+            .map_err(|err| ObjectCacheError::FailedToRun(command.compiler.clone(), err))?;
+        if !output.status.success() {
+            return Err(ObjectCacheError::FailedToPreprocess(
+                command.object.clone(),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
+        let mut content = format!(
+            "{}\n{}\n{}\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            compiler_info.compiler_type.to_string(),
+            compiler_info.compiler_version,
+            command.key_arguments().join("\0"),
+        )
+        .into_bytes();
+        content.extend_from_slice(&output.stdout);
+        Ok(crate::utility::sha256_hex(&content))
+    }
+
+    /// The identity of `compiler`. Identifying a compiler requires running it, so the identity is
+    /// kept in the cache for as long as the executable is unchanged.
+    fn compiler_info(&self, compiler: &Path) -> Result<CompilerInfo, ObjectCacheError> {
+        let executable = if compiler.components().count() == 1 {
+            let mut search_options = FindProgramOptions::new();
+            search_options.with_path_env();
+            find_program(compiler, search_options).unwrap_or_else(|| compiler.to_path_buf())
+        } else {
+            compiler.to_path_buf()
+        };
+        let modification_time = std::fs::metadata(&executable)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let path = self.directory.join("compilers").join(format!(
+            "{}.json",
+            crate::utility::sha256_hex(
+                format!("{}{:?}", executable.display(), modification_time).as_bytes()
+            )
+        ));
+        if let Some(compiler_info) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CompilerInfo>(&content).ok())
+        {
+            return Ok(compiler_info);
+        }
+
+        let compiler_info = CompilerInfo::new(&executable)
+            .map_err(|err| ObjectCacheError::FailedToIdentifyCompiler(executable.clone(), err))?;
+        if let Ok(content) = serde_json::to_string(&compiler_info) {
+            write_atomically(&path, content.as_bytes())?;
+        }
+        Ok(compiler_info)
+    }
+
+    fn entry_directory(&self, key: &str) -> PathBuf {
+        self.directory.join(&key[..2]).join(key)
+    }
+
+    fn restore(&self, entry: &Path, command: &CompileCommand) -> Result<bool, ObjectCacheError> {
+        let object = entry.join(ENTRY_OBJECT);
+        if !object.is_file() {
+            return Ok(false);
+        }
+        let depfile = match command.depfile {
+            Some(ref depfile) => match std::fs::read_to_string(entry.join(ENTRY_DEPFILE)) {
+                Ok(content) => Some((
+                    depfile,
+                    content.replace(OBJECT_PLACEHOLDER, &command.object.display().to_string()),
+                )),
+                Err(_) => return Ok(false),
+            },
+            None => None,
+        };
+
+        std::fs::copy(&object, &command.object)
+            .map_err(|err| ObjectCacheError::Entry(command.object.clone(), err))?;
+        if let Some((depfile, content)) = depfile {
+            std::fs::write(depfile, content)
+                .map_err(|err| ObjectCacheError::Entry(depfile.clone(), err))?;
+        }
+        if let Ok(stderr) = std::fs::read(entry.join(ENTRY_STDERR)) {
+            eprint!("{}", String::from_utf8_lossy(&stderr));
+        }
+        // Mark the entry as recently used.
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(&object)
+            .and_then(|file| file.set_modified(std::time::SystemTime::now()));
+        Ok(true)
+    }
+
+    /// Stores the outputs of `command` in `entry`, and returns the size of the new entry.
+    fn store(
+        &self,
+        entry: &Path,
+        command: &CompileCommand,
+        stderr: &[u8],
+    ) -> Result<u64, ObjectCacheError> {
+        let staging = entry.with_extension(format!("tmp-{}", std::process::id()));
+        std::fs::create_dir_all(&staging)
+            .map_err(|err| ObjectCacheError::Entry(staging.clone(), err))?;
+        let result = (|| {
+            std::fs::copy(&command.object, staging.join(ENTRY_OBJECT))?;
+            if let Some(ref depfile) = command.depfile {
+                let content = std::fs::read_to_string(depfile)?;
+                std::fs::write(
+                    staging.join(ENTRY_DEPFILE),
+                    content.replace(&command.object.display().to_string(), OBJECT_PLACEHOLDER),
+                )?;
+            }
+            if !stderr.is_empty() {
+                std::fs::write(staging.join(ENTRY_STDERR), stderr)?;
+            }
+            let size = directory_size(&staging);
+            std::fs::rename(&staging, entry).map(|_| size)
+        })();
+        match result {
+            Ok(size) => Ok(size),
+            Err(_) => {
+                // Another build may have stored the same entry in the meantime.
+                let _ = std::fs::remove_dir_all(&staging);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Adds `size` to the size index, and evicts entries if the cache may have grown past its
+    /// maximum size. The index is approximate when builds store entries concurrently, so it is
+    /// rewritten with the actual size whenever the cache is walked.
+    fn record_stored(&self, size: u64) -> Result<(), ObjectCacheError> {
+        let indexed_size = std::fs::read_to_string(self.directory.join(SIZE_INDEX))
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok());
+        match indexed_size {
+            Some(indexed_size) if indexed_size + size <= self.max_size => {
+                self.write_size_index(indexed_size + size)
+            }
+            _ => self.evict(),
+        }
+    }
+
+    fn write_size_index(&self, size: u64) -> Result<(), ObjectCacheError> {
+        write_atomically(
+            &self.directory.join(SIZE_INDEX),
+            size.to_string().as_bytes(),
+        )
+    }
+
+    /// Removes the least recently used entries until the cache is below 90% of its maximum size.
+    fn evict(&self) -> Result<(), ObjectCacheError> {
+        let mut entries = self.entries()?;
+        let mut size = entries.iter().map(|entry| entry.size).sum::<u64>();
+        if size > self.max_size {
+            entries.sort_by_key(|entry| entry.last_used);
+            let target_size = self.max_size / 10 * 9;
+            for entry in entries {
+                if size <= target_size {
+                    break;
+                }
+                log::debug!("Evicting {} from object cache", entry.path.display());
+                match std::fs::remove_dir_all(&entry.path) {
+                    Ok(()) => {}
+                    // Another build evicted the entry first.
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(ObjectCacheError::Entry(entry.path.clone(), err)),
+                }
+                size -= entry.size;
+            }
+        }
+        self.write_size_index(size)
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>, ObjectCacheError> {
+        let mut entries = Vec::new();
+        let prefixes = match std::fs::read_dir(&self.directory) {
+            Ok(prefixes) => prefixes,
+            Err(_) => return Ok(entries),
+        };
+        for prefix in prefixes.filter_map(Result::ok) {
+            if prefix.file_name().len() != 2 {
+                continue;
+            }
+            let directories = std::fs::read_dir(prefix.path())
+                .map_err(|err| ObjectCacheError::Entry(prefix.path(), err))?;
+            for directory in directories.filter_map(Result::ok) {
+                let path = directory.path();
+                let object = match std::fs::metadata(path.join(ENTRY_OBJECT)) {
+                    Ok(object) => object,
+                    Err(_) => continue,
+                };
+                entries.push(Entry {
+                    last_used: object.modified().ok(),
+                    size: directory_size(&path),
+                    path,
+                });
+            }
+        }
+        Ok(entries)
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    last_used: Option<std::time::SystemTime>,
+}
+
+/// The total size of the files directly in `directory`.
+fn directory_size(directory: &Path) -> u64 {
+    std::fs::read_dir(directory)
+        .map(|files| {
+            files
+                .filter_map(Result::ok)
+                .filter_map(|file| file.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+fn success() -> ExitStatus {
+    #[cfg(target_family = "unix")]
+    {
+        std::os::unix::process::ExitStatusExt::from_raw(0)
+    }
+    #[cfg(target_family = "windows")]
+    {
+        std::os::windows::process::ExitStatusExt::from_raw(0)
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), ObjectCacheError> {
+    let staging = path.with_extension(format!("tmp-{}", std::process::id()));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| ObjectCacheError::Entry(parent.to_path_buf(), err))?;
+    }
+    std::fs::write(&staging, content)
+        .and_then(|_| std::fs::rename(&staging, path))
+        .map_err(|err| ObjectCacheError::Entry(path.to_path_buf(), err))
+}
+
+/// Parses a size in bytes, optionally with a K, M or G suffix.
+pub fn parse_size(size: &str) -> Result<u64, ObjectCacheError> {
+    let size = size.trim();
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((index, 'K')) | Some((index, 'k')) => (&size[..index], 1024),
+        Some((index, 'M')) | Some((index, 'm')) => (&size[..index], 1024 * 1024),
+        Some((index, 'G')) | Some((index, 'g')) => (&size[..index], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|number| number * multiplier)
+        .map_err(|_| ObjectCacheError::InvalidSize(size.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn command(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_compile_command() {
+        let compile_command = CompileCommand::parse(
+            None,
+            &command(&[
+                "g++",
+                "-O2",
+                "-MMD",
+                "-MP",
+                "-Iinclude",
+                "main.cpp",
+                "-c",
+                "-o",
+                "out/main.o",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(compile_command.compiler, PathBuf::from("g++"));
+        assert_eq!(compile_command.object, PathBuf::from("out/main.o"));
+        assert_eq!(compile_command.depfile, Some(PathBuf::from("out/main.d")));
+        assert_eq!(
+            compile_command.preprocess_arguments(),
+            command(&["-E", "-O2", "-Iinclude", "main.cpp"])
+        );
+        assert_eq!(
+            compile_command.key_arguments(),
+            vec!["-O2", "-MMD", "-MP", "-Iinclude", "main.cpp", "-c"]
+        );
+
+        assert!(CompileCommand::parse(None, &command(&["g++", "main.o", "-o", "app"])).is_none());
+        assert!(CompileCommand::parse(None, &command(&["g++", "-E", "main.cpp"])).is_none());
+    }
+
+    #[test]
+    fn parses_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("500M").unwrap(), 500 * 1024 * 1024);
+        assert_eq!(parse_size("5G").unwrap(), 5 * 1024 * 1024 * 1024);
+        assert!(parse_size("five").is_err());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let dir = tempdir::TempDir::new("object_cache").unwrap();
+        let cache = ObjectCache::new(dir.path(), 100);
+        for (index, key) in ["aa01", "bb02", "cc03"].iter().enumerate() {
+            let entry = cache.entry_directory(key);
+            std::fs::create_dir_all(&entry).unwrap();
+            let object = entry.join(ENTRY_OBJECT);
+            std::fs::write(&object, [0u8; 40]).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&object)
+                .unwrap()
+                .set_modified(
+                    std::time::SystemTime::UNIX_EPOCH
+                        + std::time::Duration::from_secs(index as u64 + 1),
+                )
+                .unwrap();
+        }
+
+        cache.evict().unwrap();
+        assert!(!cache.entry_directory("aa01").exists());
+        assert!(cache.entry_directory("bb02").exists());
+        assert!(cache.entry_directory("cc03").exists());
+    }
+
+    /// A compiler that preprocesses by printing the source, and compiles by writing the object and
+    /// dependency file. Every compilation is logged to `compilations` next to it.
+    fn stub_compiler(directory: &Path) -> PathBuf {
+        let path = directory.join("stub-g++");
+        std::fs::write(
+            &path,
+            "#!/bin/sh\n\
+             case \"$*\" in\n\
+             *--version*) echo 'g++ (GCC) 13.2.0'; exit 0 ;;\n\
+             *-E*) for argument in \"$@\"; do source=\"$argument\"; done; cat \"$source\"; exit 0 ;;\n\
+             esac\n\
+             echo compiled >> \"$(dirname \"$0\")/compilations\"\n\
+             while [ $# -gt 0 ]; do\n\
+             case \"$1\" in\n\
+             -o) object=\"$2\"; shift ;;\n\
+             -MF) depfile=\"$2\"; shift ;;\n\
+             esac\n\
+             shift\n\
+             done\n\
+             echo object > \"$object\"\n\
+             echo \"$object: main.cpp\" > \"$depfile\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn compile_command(compiler: &Path, build_directory: &Path, source: &Path) -> CompileCommand {
+        std::fs::create_dir_all(build_directory).unwrap();
+        CompileCommand::parse(
+            None,
+            &command(&[
+                compiler.to_str().unwrap(),
+                "-O2",
+                "-MMD",
+                source.to_str().unwrap(),
+                "-c",
+                "-o",
+                build_directory.join("main.o").to_str().unwrap(),
+                "-MF",
+                build_directory.join("main.d").to_str().unwrap(),
+            ]),
+        )
+        .unwrap()
+    }
+
+    fn compilations(directory: &Path) -> usize {
+        std::fs::read_to_string(directory.join("compilations"))
+            .map(|content| content.lines().count())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn restores_objects_compiled_by_other_builds() {
+        let dir = tempdir::TempDir::new("object_cache").unwrap();
+        let compiler = stub_compiler(dir.path());
+        let source = dir.path().join("main.cpp");
+        std::fs::write(&source, "int main() {}\n").unwrap();
+        let cache = ObjectCache::new(&dir.path().join("cache"), 1024 * 1024);
+
+        let first = compile_command(&compiler, &dir.path().join("first"), &source);
+        let (status, outcome) = cache.compile(&first).unwrap();
+        assert!(status.success());
+        assert_eq!(outcome, Outcome::Miss);
+        assert_eq!(compilations(dir.path()), 1);
+
+        let second = compile_command(&compiler, &dir.path().join("second"), &source);
+        let (status, outcome) = cache.compile(&second).unwrap();
+        assert!(status.success());
+        assert_eq!(outcome, Outcome::Hit);
+        assert_eq!(compilations(dir.path()), 1);
+        assert_eq!(std::fs::read_to_string(&second.object).unwrap(), "object\n");
+        assert_eq!(
+            std::fs::read_to_string(second.depfile.as_ref().unwrap()).unwrap(),
+            format!("{}: main.cpp\n", second.object.display())
+        );
+
+        std::fs::write(&source, "int main() { return 1; }\n").unwrap();
+        let (_, outcome) = cache.compile(&second).unwrap();
+        assert_eq!(outcome, Outcome::Miss);
+        assert_eq!(compilations(dir.path()), 2);
+    }
+
+    #[test]
+    fn cache_is_only_walked_when_size_index_exceeds_maximum_size() {
+        let dir = tempdir::TempDir::new("object_cache").unwrap();
+        let cache = ObjectCache::new(dir.path(), 100);
+        let entry = cache.entry_directory("aa01");
+        std::fs::create_dir_all(&entry).unwrap();
+        std::fs::write(entry.join(ENTRY_OBJECT), [0u8; 40]).unwrap();
+
+        // Without an index, the cache is walked to create it.
+        cache.record_stored(40).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(SIZE_INDEX)).unwrap(),
+            "40"
+        );
+        cache.record_stored(50).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(SIZE_INDEX)).unwrap(),
+            "90"
+        );
+        // Past the maximum size, the index is corrected from the actual entries.
+        cache.record_stored(20).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(SIZE_INDEX)).unwrap(),
+            "40"
+        );
+        assert!(entry.exists());
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::YAMBS_MANIFEST_NAME;
+
+pub const OVERRIDES_FILE_NAME: &str = "overrides.toml";
+
+#[derive(thiserror::Error, Debug)]
+pub enum OverrideError {
+    #[error("Failed to read overrides file {0:?}")]
+    Read(PathBuf, #[source] std::io::Error),
+    #[error("Failed to parse overrides file {0:?}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+    #[error("Override of {0} in {1:?} points to {2:?}, which does not exist")]
+    ReplacementNotFound(String, PathBuf, PathBuf, #[source] std::io::Error),
+    #[error("Override of {0} in {1:?} points to {2:?}, which has no {manifest}", manifest = YAMBS_MANIFEST_NAME)]
+    NoManifest(String, PathBuf, PathBuf),
+}
+
+/// What an override replaces: every source dependency with a name, or the source dependency
+/// located in a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverrideKey {
+    Name(String),
+    Path(PathBuf),
+}
+
+impl OverrideKey {
+    /// Keys containing a path separator are paths, relative to the file they are given in.
+    fn new(key: &str, base_dir: &Path) -> Self {
+        if key.contains(std::path::MAIN_SEPARATOR) || key.contains('/') {
+            let path = base_dir.join(key);
+            Self::Path(path.canonicalize().unwrap_or(path))
+        } else {
+            Self::Name(key.to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for OverrideKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "{}", name),
+            Self::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A dependency redirected to another directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    pub key: OverrideKey,
+    /// The replacement directory. Canonical once the override is used.
+    pub replacement: PathBuf,
+    /// The file the override is given in.
+    pub origin: PathBuf,
+}
+
+impl Override {
+    /// Checks that the replacement is a directory with a manifest, and resolves its path.
+    fn validated(&self) -> Result<Self, OverrideError> {
+        let replacement = self.replacement.canonicalize().map_err(|err| {
+            OverrideError::ReplacementNotFound(
+                self.key.to_string(),
+                self.origin.clone(),
+                self.replacement.clone(),
+                err,
+            )
+        })?;
+        if !replacement.join(YAMBS_MANIFEST_NAME).is_file() {
+            return Err(OverrideError::NoManifest(
+                self.key.to_string(),
+                self.origin.clone(),
+                replacement,
+            ));
+        }
+        Ok(Self {
+            replacement,
+            ..self.clone()
+        })
+    }
+}
+
+impl std::fmt::Display for Override {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is overridden with {} (from {})",
+            self.key,
+            self.replacement.display(),
+            self.origin.display()
+        )
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct OverridesFile {
+    #[serde(rename = "override", default)]
+    overrides: BTreeMap<String, PathBuf>,
+}
+
+/// Replacement directories for source and git dependencies, used to build against a local
+/// checkout of a dependency without editing the manifests that reference it.
+///
+/// Overrides are given in the `[override]` table of the root manifest and in
+/// `~/.yambs/overrides.toml`. The root manifest takes precedence. An override is only checked
+/// when a dependency uses it, so a stale entry doesn't affect projects that don't depend on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    overrides: Vec<Override>,
+    used: Vec<Override>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the overrides of the root manifest in `manifest_dir` and of the user.
+    pub fn load(
+        manifest_dir: &Path,
+        manifest_overrides: &BTreeMap<String, PathBuf>,
+    ) -> Result<Self, OverrideError> {
+        let mut overrides = Self::from_table(
+            manifest_overrides,
+            manifest_dir,
+            &manifest_dir.join(YAMBS_MANIFEST_NAME),
+        );
+        if let Some(home_dir) = home::home_dir() {
+            let user_overrides =
+                Self::from_file(&home_dir.join(".yambs").join(OVERRIDES_FILE_NAME))?;
+            overrides.overrides.extend(user_overrides.overrides);
+        }
+        Ok(overrides)
+    }
+
+    /// Reads the `[override]` table of `path`. A missing file has no overrides.
+    pub fn from_file(path: &Path) -> Result<Self, OverrideError> {
+        if !path.is_file() {
+            return Ok(Self::new());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| OverrideError::Read(path.to_path_buf(), err))?;
+        let file: OverridesFile = toml::from_str(&content)
+            .map_err(|err| OverrideError::Parse(path.to_path_buf(), err))?;
+        Ok(Self::from_table(
+            &file.overrides,
+            path.parent().unwrap(),
+            path,
+        ))
+    }
+
+    fn from_table(table: &BTreeMap<String, PathBuf>, base_dir: &Path, origin: &Path) -> Self {
+        let overrides = table
+            .iter()
+            .map(|(key, replacement)| Override {
+                key: OverrideKey::new(key, base_dir),
+                replacement: base_dir.join(replacement),
+                origin: origin.to_path_buf(),
+            })
+            .collect();
+        Self {
+            overrides,
+            used: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// The first override of the dependency `name`, located in `path` for source dependencies.
+    pub fn find(&self, name: &str, path: Option<&Path>) -> Option<&Override> {
+        self.overrides.iter().find(|candidate| match candidate.key {
+            OverrideKey::Name(ref key) => key == name,
+            OverrideKey::Path(ref key) => path == Some(key.as_path()),
+        })
+    }
+
+    /// The directory the dependency `name` is overridden with, if any. `path` is the directory of
+    /// a source dependency. The override is checked and remembered as used.
+    pub fn replacement_for(
+        &mut self,
+        name: &str,
+        path: Option<&Path>,
+    ) -> Result<Option<PathBuf>, OverrideError> {
+        let found = match self.find(name, path) {
+            Some(found) => found.validated()?,
+            None => return Ok(None),
+        };
+        log::warn!("Dependency {}", found);
+        let replacement = found.replacement.clone();
+        if !self.used.contains(&found) {
+            self.used.push(found);
+        }
+        Ok(Some(replacement))
+    }
+
+    /// The overrides used while resolving dependencies.
+    pub fn used(&self) -> &[Override] {
+        &self.used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn create_project(directory: &Path) -> PathBuf {
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(directory.join(YAMBS_MANIFEST_NAME), "").unwrap();
+        directory.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn overrides_match_dependency_names_and_paths() {
+        let dir = tempdir::TempDir::new("overrides").unwrap();
+        let project = create_project(&dir.path().join("project"));
+        let vendored = create_project(&dir.path().join("project").join("vendor").join("fmt"));
+        let local_fmt = create_project(&dir.path().join("fmt"));
+        let local_mylib = create_project(&dir.path().join("mylib"));
+        let table = BTreeMap::from([
+            ("mylib".to_string(), PathBuf::from("../mylib")),
+            ("./vendor/fmt".to_string(), PathBuf::from("../fmt")),
+        ]);
+
+        let mut overrides =
+            Overrides::from_table(&table, &project, &project.join(YAMBS_MANIFEST_NAME));
+
+        assert_eq!(
+            overrides.replacement_for("mylib", None).unwrap(),
+            Some(local_mylib)
+        );
+        assert_eq!(
+            overrides.replacement_for("fmt", Some(&vendored)).unwrap(),
+            Some(local_fmt.clone())
+        );
+        assert_eq!(
+            overrides.replacement_for("fmt", Some(&local_fmt)).unwrap(),
+            None
+        );
+        assert_eq!(overrides.replacement_for("other", None).unwrap(), None);
+        assert_eq!(overrides.used().len(), 2);
+    }
+
+    #[test]
+    fn overrides_are_read_from_override_table_of_file() {
+        let dir = tempdir::TempDir::new("overrides").unwrap();
+        let local_mylib = create_project(&dir.path().join("mylib"));
+        let file = dir.path().join(OVERRIDES_FILE_NAME);
+        std::fs::write(&file, "[override]\nmylib = \"mylib\"\n").unwrap();
+
+        let mut overrides = Overrides::from_file(&file).unwrap();
+        assert_eq!(
+            overrides.replacement_for("mylib", None).unwrap(),
+            Some(local_mylib)
+        );
+        assert_eq!(overrides.used()[0].origin, file);
+        assert!(Overrides::from_file(&dir.path().join("missing.toml"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn override_without_manifest_is_an_error_when_used() {
+        let dir = tempdir::TempDir::new("overrides").unwrap();
+        std::fs::create_dir_all(dir.path().join("empty")).unwrap();
+        let origin = dir.path().join(YAMBS_MANIFEST_NAME);
+        let table = BTreeMap::from([
+            ("mylib".to_string(), PathBuf::from("empty")),
+            ("other".to_string(), PathBuf::from("missing")),
+        ]);
+
+        let mut overrides = Overrides::from_table(&table, dir.path(), &origin);
+        assert_eq!(overrides.replacement_for("unrelated", None).unwrap(), None);
+        assert!(matches!(
+            overrides.replacement_for("mylib", None),
+            Err(OverrideError::NoManifest(..))
+        ));
+        assert!(matches!(
+            overrides.replacement_for("other", None),
+            Err(OverrideError::ReplacementNotFound(..))
+        ));
+        assert!(overrides.used().is_empty());
+    }
+}
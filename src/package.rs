@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::build_target::SHARED_LIBRARY_FILE_EXTENSION;
+use crate::cli::configurations::BuildType;
+use crate::errors::FsError;
+use crate::install::{BIN_DIRECTORY, LIB_DIRECTORY};
+use crate::utility;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PackageError {
+    #[error(transparent)]
+    Fs(#[from] FsError),
+    #[error("Failed to add {0:?} to package archive")]
+    AppendToArchive(PathBuf, #[source] std::io::Error),
+    #[error("Failed to finish package archive {0:?}")]
+    FinishArchive(PathBuf, #[source] std::io::Error),
+    #[error("Failed to run {0:?}")]
+    FailedToRunBinaryUtility(PathBuf, #[source] std::io::Error),
+    #[error("{0:?} failed on {1:?}:\n{2}")]
+    BinaryUtilityFailed(PathBuf, PathBuf, String),
+}
+
+/// A relocatable package of an installed project.
+///
+/// The archive is named `<name>-<version>-<build type>.tar.gz` and holds a single top level
+/// directory with the same stem. Entries are added in sorted order with normalized ownership,
+/// permissions and modification times, so that packaging the same staging directory twice gives
+/// byte-identical archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    name: String,
+    version: semver::Version,
+    build_type: BuildType,
+}
+
+impl Package {
+    pub fn new(name: &str, version: &semver::Version, build_type: &BuildType) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.clone(),
+            build_type: build_type.clone(),
+        }
+    }
+
+    pub fn stem(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            self.name,
+            self.version,
+            self.build_type.to_string()
+        )
+    }
+
+    pub fn archive_name(&self) -> String {
+        format!("{}.tar.gz", self.stem())
+    }
+
+    pub fn create_archive(
+        &self,
+        staging_directory: &Path,
+        output_directory: &Path,
+    ) -> Result<PathBuf, PackageError> {
+        utility::create_dir(output_directory)?;
+        let archive_path = output_directory.join(self.archive_name());
+        let archive_file = utility::create_file(&archive_path)?;
+
+        let mut builder = tar::Builder::new(GzEncoder::new(archive_file, Compression::default()));
+        builder.mode(tar::HeaderMode::Deterministic);
+
+        let stem = PathBuf::from(self.stem());
+        builder
+            .append_dir(&stem, staging_directory)
+            .map_err(|e| PackageError::AppendToArchive(staging_directory.to_path_buf(), e))?;
+        for entry in collect_entries(staging_directory)? {
+            let path = staging_directory.join(&entry);
+            let name = stem.join(&entry);
+            log::debug!("Adding {} to package as {}", path.display(), name.display());
+            if path.is_dir() {
+                builder.append_dir(&name, &path)
+            } else {
+                builder.append_path_with_name(&path, &name)
+            }
+            .map_err(|e| PackageError::AppendToArchive(path.clone(), e))?;
+        }
+
+        builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .map_err(|e| PackageError::FinishArchive(archive_path.clone(), e))?;
+        Ok(archive_path)
+    }
+}
+
+/// The binary utilities of the toolchain, which strip the binaries of release packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strip {
+    pub strip: PathBuf,
+    pub objcopy: Option<PathBuf>,
+}
+
+impl Strip {
+    /// Strips the executables in `bin` and the shared libraries in `lib` of `staging_directory`,
+    /// and returns them. With `objcopy`, the debug information of every binary is kept in
+    /// `<debug_directory>/<binary>.debug` first, and the stripped binary links to it with
+    /// `.gnu_debuglink`.
+    pub fn strip_binaries(
+        &self,
+        staging_directory: &Path,
+        debug_directory: &Path,
+    ) -> Result<Vec<PathBuf>, PackageError> {
+        let binaries = collect_entries(staging_directory)?
+            .into_iter()
+            .filter(|entry| is_strippable(entry))
+            .map(|entry| staging_directory.join(entry))
+            .filter(|path| path.is_file())
+            .collect::<Vec<PathBuf>>();
+        for binary in &binaries {
+            let file_name = binary.file_name().unwrap().to_string_lossy().to_string();
+            let debug_file = debug_directory.join(format!("{}.debug", file_name));
+            if let Some(ref objcopy) = self.objcopy {
+                utility::create_dir(debug_directory)?;
+                run_binary_utility(
+                    objcopy,
+                    &[
+                        "--only-keep-debug".as_ref(),
+                        binary.as_os_str(),
+                        debug_file.as_os_str(),
+                    ],
+                    binary,
+                )?;
+            }
+            log::debug!("Stripping {}", binary.display());
+            run_binary_utility(&self.strip, &[binary.as_os_str()], binary)?;
+            if let Some(ref objcopy) = self.objcopy {
+                let debug_link = format!("--add-gnu-debuglink={}", debug_file.display());
+                run_binary_utility(objcopy, &[debug_link.as_ref(), binary.as_os_str()], binary)?;
+            }
+        }
+        Ok(binaries)
+    }
+}
+
+/// Executables and shared libraries, given relative to the staging directory.
+fn is_strippable(entry: &Path) -> bool {
+    let file_name = entry
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let shared_library_extension = format!(".{}", SHARED_LIBRARY_FILE_EXTENSION);
+    match entry.parent().and_then(|parent| parent.to_str()) {
+        Some(BIN_DIRECTORY) => true,
+        Some(LIB_DIRECTORY) => {
+            file_name.ends_with(&shared_library_extension)
+                || file_name.contains(&format!("{}.", shared_library_extension))
+        }
+        _ => false,
+    }
+}
+
+fn run_binary_utility(
+    program: &Path,
+    args: &[&std::ffi::OsStr],
+    binary: &Path,
+) -> Result<(), PackageError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| PackageError::FailedToRunBinaryUtility(program.to_path_buf(), e))?;
+    if !output.status.success() {
+        return Err(PackageError::BinaryUtilityFailed(
+            program.to_path_buf(),
+            binary.to_path_buf(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Returns every file and directory below `directory`, relative to it and sorted, so that the
+// archive layout does not depend on the order the file system happens to list entries in.
+fn collect_entries(directory: &Path) -> Result<Vec<PathBuf>, FsError> {
+    let mut entries = Vec::new();
+    let mut directories_to_visit = vec![directory.to_path_buf()];
+
+    while let Some(current_directory) = directories_to_visit.pop() {
+        let read_dir = std::fs::read_dir(&current_directory)
+            .map_err(|e| FsError::ReadFromFile(current_directory.clone(), e))?;
+        for entry in read_dir {
+            let path = entry
+                .map_err(|e| FsError::ReadFromFile(current_directory.clone(), e))?
+                .path();
+            if path.is_dir() {
+                directories_to_visit.push(path.clone());
+            }
+            entries.push(path.strip_prefix(directory).unwrap().to_path_buf());
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn create_staging_directory(root: &Path) -> PathBuf {
+        let staging = root.join("staging");
+        std::fs::create_dir_all(staging.join("bin")).unwrap();
+        std::fs::create_dir_all(staging.join("lib")).unwrap();
+        std::fs::create_dir_all(staging.join("include").join("project")).unwrap();
+        std::fs::write(staging.join("bin").join("x"), "executable").unwrap();
+        std::fs::write(staging.join("lib").join("libproject.a"), "archive").unwrap();
+        std::fs::write(
+            staging.join("include").join("project").join("project.h"),
+            "#pragma once",
+        )
+        .unwrap();
+        staging
+    }
+
+    fn package() -> Package {
+        Package::new(
+            "project",
+            &semver::Version::new(1, 2, 3),
+            &BuildType::Release,
+        )
+    }
+
+    #[test]
+    fn archive_name_contains_name_version_and_build_type() {
+        assert_eq!(package().archive_name(), "project-1.2.3-release.tar.gz");
+    }
+
+    #[test]
+    fn create_archive_is_deterministic() {
+        let tempdir = tempdir::TempDir::new("package").unwrap();
+        let staging = create_staging_directory(tempdir.path());
+        let package = package();
+
+        let first = package
+            .create_archive(&staging, &tempdir.path().join("first"))
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        std::fs::write(staging.join("bin").join("x"), "executable").unwrap();
+        let second = package
+            .create_archive(&staging, &tempdir.path().join("second"))
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(first).unwrap(),
+            std::fs::read(second).unwrap()
+        );
+    }
+
+    #[test]
+    fn create_archive_places_sorted_entries_below_package_stem() {
+        let tempdir = tempdir::TempDir::new("package").unwrap();
+        let staging = create_staging_directory(tempdir.path());
+
+        let archive_path = package().create_archive(&staging, tempdir.path()).unwrap();
+        let mut bytes = Vec::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(archive_path).unwrap())
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                "project-1.2.3-release",
+                "project-1.2.3-release/bin",
+                "project-1.2.3-release/bin/x",
+                "project-1.2.3-release/include",
+                "project-1.2.3-release/include/project",
+                "project-1.2.3-release/include/project/project.h",
+                "project-1.2.3-release/lib",
+                "project-1.2.3-release/lib/libproject.a",
+            ]
+        );
+    }
+
+    /// A binary utility that records its arguments in `log`.
+    fn stub_utility(path: &Path, log: &Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::write(
+            path,
+            format!(
+                "#!/bin/sh\necho \"$(basename $0) $*\" >> {}\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_path_buf()
+    }
+
+    #[test]
+    fn strip_binaries_strips_executables_and_shared_libraries_and_keeps_debug_information() {
+        let tempdir = tempdir::TempDir::new("package").unwrap();
+        let staging = create_staging_directory(tempdir.path());
+        std::fs::write(staging.join("lib").join("libproject.so.1"), "shared").unwrap();
+        let log = tempdir.path().join("log");
+        let strip = Strip {
+            strip: stub_utility(&tempdir.path().join("strip"), &log),
+            objcopy: Some(stub_utility(&tempdir.path().join("objcopy"), &log)),
+        };
+        let debug_directory = tempdir.path().join("debug");
+
+        let stripped = strip.strip_binaries(&staging, &debug_directory).unwrap();
+
+        let executable = staging.join("bin").join("x");
+        let library = staging.join("lib").join("libproject.so.1");
+        assert_eq!(stripped, vec![executable.clone(), library.clone()]);
+        let mut expected = String::new();
+        for binary in [executable, library] {
+            let debug_file = debug_directory.join(format!(
+                "{}.debug",
+                binary.file_name().unwrap().to_string_lossy()
+            ));
+            expected.push_str(&format!(
+                "objcopy --only-keep-debug {} {}\nstrip {}\nobjcopy --add-gnu-debuglink={} {}\n",
+                binary.display(),
+                debug_file.display(),
+                binary.display(),
+                debug_file.display(),
+                binary.display()
+            ));
+        }
+        assert_eq!(std::fs::read_to_string(log).unwrap(), expected);
+    }
+}
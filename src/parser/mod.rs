@@ -85,7 +85,10 @@ mod tests {
     use crate::flags::CompilerFlags;
     use crate::manifest::ManifestData;
     use crate::targets::{Dependency, Executable, Library, Target};
-    use types::{Define, DependencyData, IncludeSearchType, LibraryType, SourceData};
+    use types::{
+        Define, DependencyData, GitData, IncludeSearchType, LibraryType, PkgConfigExportData,
+        SourceData,
+    };
 
     struct TestFixture {
         pub tempdir: tempdir::TempDir,
@@ -135,6 +138,7 @@ mod tests {
             };
             let expected = ManifestData {
                 project_config: None,
+                overrides: std::collections::BTreeMap::new(),
                 targets: vec![Target::Executable(executable)],
             };
             assert_eq!(manifest, expected);
@@ -182,6 +186,7 @@ mod tests {
             };
             let expected = ManifestData {
                 project_config: None,
+                overrides: std::collections::BTreeMap::new(),
                 targets: vec![Target::Executable(executable)],
             };
             assert_eq!(manifest, expected);
@@ -233,6 +238,7 @@ mod tests {
             };
             let expected = ManifestData {
                 project_config: None,
+                overrides: std::collections::BTreeMap::new(),
                 targets: vec![
                     Target::Executable(executable_x),
                     Target::Executable(executable_y),
@@ -270,9 +276,11 @@ mod tests {
             defines: Vec::new(),
             compiler_flags: CompilerFlags::new(),
             lib_type: LibraryType::default(),
+            pkg_config: None,
         };
         let expected = ManifestData {
             project_config: None,
+            overrides: std::collections::BTreeMap::new(),
             targets: vec![Target::Library(library)],
         };
         assert_eq!(manifest, expected);
@@ -298,7 +306,8 @@ mod tests {
 
     [library.MyLibraryData.dependencies]
     SomeProject = {{ path = "{}" }}
-    SomeSecondProject = {{ path = "{}" }}
+    SomeSecondProject = {{ path = "{}", version = ">=1.2, <2" }}
+    SomeGitProject = {{ git = "https://example.com/SomeGitProject.git", tag = "v1.0" }}
     "#,
             dep_project_path.display(),
             second_dep_project_path.display()
@@ -314,11 +323,23 @@ mod tests {
                 manifest_dir.join(std::path::PathBuf::from("generator.cpp")),
             ],
             dependencies: vec![
+                Dependency {
+                    name: "SomeGitProject".to_string(),
+                    data: DependencyData::Git(GitData {
+                        git: "https://example.com/SomeGitProject.git".to_string(),
+                        rev: None,
+                        tag: Some("v1.0".to_string()),
+                        branch: None,
+                        origin: IncludeSearchType::Include,
+                        version: None,
+                    }),
+                },
                 Dependency {
                     name: "SomeProject".to_string(),
                     data: DependencyData::Source(SourceData {
                         path: dep_project_path,
                         origin: IncludeSearchType::Include,
+                        version: None,
                     }),
                 },
                 Dependency {
@@ -326,15 +347,18 @@ mod tests {
                     data: DependencyData::Source(SourceData {
                         path: second_dep_project_path,
                         origin: IncludeSearchType::Include,
+                        version: Some(semver::VersionReq::parse(">=1.2, <2").unwrap()),
                     }),
                 },
             ],
             defines: Vec::new(),
             compiler_flags: CompilerFlags::new(),
             lib_type: LibraryType::default(),
+            pkg_config: None,
         };
         let expected = ManifestData {
             project_config: None,
+            overrides: std::collections::BTreeMap::new(),
             targets: vec![Target::Library(library)],
         };
         assert_eq!(manifest, expected);
@@ -378,6 +402,7 @@ mod tests {
             };
             let expected = ManifestData {
                 project_config: None,
+                overrides: std::collections::BTreeMap::new(),
                 targets: vec![Target::Executable(executable)],
             };
             assert_eq!(manifest, expected);
@@ -421,11 +446,66 @@ mod tests {
             ],
             compiler_flags: CompilerFlags::new(),
             lib_type: LibraryType::default(),
+            pkg_config: None,
         };
         let expected = ManifestData {
             project_config: None,
+            overrides: std::collections::BTreeMap::new(),
             targets: vec![Target::Library(library)],
         };
         assert_eq!(manifest, expected);
     }
+
+    #[test]
+    fn parse_produces_manifest_with_project_name_and_version() {
+        let fixture = TestFixture::new();
+        let manifest_dir = fixture.tempdir.path().to_path_buf();
+
+        fixture.create_dummy_file(&std::path::PathBuf::from("x.cpp"));
+
+        let input = r#"
+    [project_config]
+    name = "MyProject"
+    version = "1.2.3"
+
+    [executable.x]
+    sources = ['x.cpp']
+    "#;
+
+        let manifest = parse_toml(input, &manifest_dir).unwrap();
+        let project_config = manifest.project_config.unwrap();
+        assert_eq!(project_config.name, Some("MyProject".to_string()));
+        assert_eq!(project_config.version, Some(semver::Version::new(1, 2, 3)));
+    }
+
+    #[test]
+    fn parse_produces_manifest_with_library_pkg_config_export() {
+        let fixture = TestFixture::new();
+        let manifest_dir = fixture.tempdir.path().to_path_buf();
+
+        fixture.create_dummy_file(&std::path::PathBuf::from("x.cpp"));
+
+        let input = r#"
+    [library.MyLibraryData]
+    sources = ['x.cpp']
+
+    [library.MyLibraryData.pkg_config]
+    description = "My library"
+    cflags = ["-DMY_LIBRARY"]
+    "#;
+
+        let manifest = parse_toml(input, &manifest_dir).unwrap();
+        let library = match manifest.targets[0] {
+            Target::Library(ref library) => library.clone(),
+            _ => panic!("Expected a library"),
+        };
+        assert_eq!(
+            library.pkg_config,
+            Some(PkgConfigExportData {
+                description: Some("My library".to_string()),
+                url: None,
+                cflags: vec!["-DMY_LIBRARY".to_string()],
+            })
+        );
+    }
 }
@@ -82,6 +82,14 @@ impl Preprocessor {
     }
 }
 
+/// The environment variables referenced with `${env:NAME}` in the manifest.
+pub fn environment_variables(manifest_content: &str) -> Vec<String> {
+    ENV_VAR_REGEX
+        .captures_iter(manifest_content)
+        .map(|captures| captures.name("env").unwrap().as_str().to_string())
+        .collect()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseEnvError {
     #[error("Environment variable is empty: {0}")]
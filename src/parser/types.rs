@@ -20,6 +20,8 @@ pub enum ParseStandardError {
 #[derive(Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ProjectConfig {
+    pub name: Option<String>,
+    pub version: Option<semver::Version>,
     pub std: Option<Standard>,
     pub language: Option<Language>,
 }
@@ -201,6 +203,9 @@ pub struct RawManifestData {
     pub executables: Option<std::collections::BTreeMap<String, RawExecutableData>>,
     #[serde(rename = "library")]
     pub libraries: Option<std::collections::BTreeMap<String, RawLibraryData>>,
+    /// Replacement directories of dependencies, keyed by dependency name or path.
+    #[serde(rename = "override")]
+    pub overrides: Option<std::collections::BTreeMap<String, PathBuf>>,
 }
 
 #[derive(Debug, serde::Deserialize, PartialEq, Eq)]
@@ -226,6 +231,18 @@ pub struct RawLibraryData {
     pub common_raw: RawCommonData,
     #[serde(default, rename = "type")]
     pub lib_type: LibraryType,
+    pub pkg_config: Option<PkgConfigExportData>,
+}
+
+/// Settings for the pkg-config file generated for a library when it is installed.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PkgConfigExportData {
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Flags that consumers of the library need when compiling against it.
+    #[serde(default)]
+    pub cflags: Vec<String>,
 }
 
 #[derive(Debug, serde::Deserialize, PartialEq, Eq)]
@@ -268,6 +285,67 @@ pub struct SourceData {
     pub path: std::path::PathBuf,
     #[serde(default)]
     pub origin: IncludeSearchType,
+    /// Requirement on `project_config.version` of the dependency.
+    pub version: Option<semver::VersionReq>,
+}
+
+/// A source dependency fetched from a git repository. At most one of `rev`, `tag` and `branch`
+/// can be given. Without any of them, the default branch of the repository is used.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct GitData {
+    pub git: String,
+    pub rev: Option<String>,
+    pub tag: Option<String>,
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub origin: IncludeSearchType,
+    /// Requirement on `project_config.version` of the dependency.
+    pub version: Option<semver::VersionReq>,
+}
+
+/// A package installed by vcpkg. The dependency is resolved through the `.pc` file of the
+/// package if there is one, and from the `include` and `lib` directories of the triplet otherwise.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct VcpkgData {
+    /// The name of the vcpkg port that installs the package.
+    pub vcpkg: String,
+    /// The triplet to use, instead of `VCPKG_DEFAULT_TRIPLET` or the triplet of the host.
+    pub triplet: Option<String>,
+    /// Libraries to link with when the package has no `.pc` file. Defaults to the name of the
+    /// dependency.
+    pub libraries: Option<Vec<String>>,
+    /// The kind of library to link with.
+    pub link: Option<LibraryType>,
+    /// Requirement on the version of the package.
+    pub version: Option<semver::VersionReq>,
+}
+
+/// A package installed by Conan, resolved from the files generated by Conan.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct ConanData {
+    /// The directory Conan generated files to, usually `build/<build type>/generators`.
+    pub conan: PathBuf,
+    /// Requirement on the version of the package.
+    pub version: Option<semver::VersionReq>,
+}
+
+/// Libraries and headers of the system, looked up in the search directories of the toolchain.
+/// Meant for libraries without a `.pc` file.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct SystemData {
+    pub system: SystemLookup,
+    /// The kind of library to link with.
+    pub link: Option<LibraryType>,
+}
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct SystemLookup {
+    /// Libraries to link with, like `z` for `libz`.
+    #[serde(default)]
+    pub libs: Vec<String>,
+    /// Headers that must be found, like `zlib.h`.
+    #[serde(default)]
+    pub headers: Vec<String>,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
@@ -279,12 +357,24 @@ pub struct HeaderOnlyData {
 pub struct PkgConfigData {
     #[serde(rename = "pkg_config_search_dir")]
     pub search_dir: PathBuf,
+    /// Overrides of variables in the `.pc` file, like `pkg-config --define-variable`.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub define_variable: std::collections::BTreeMap<String, String>,
+    /// The kind of library to link with. Static linking also links the private dependencies of
+    /// the package.
+    pub link: Option<LibraryType>,
+    /// Requirement on the version of the package.
+    pub version: Option<semver::VersionReq>,
 }
 
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum DependencyData {
     Source(SourceData),
+    Git(GitData),
+    Vcpkg(VcpkgData),
+    Conan(ConanData),
+    System(SystemData),
     HeaderOnly(HeaderOnlyData),
     PkgConfig(PkgConfigData),
 }
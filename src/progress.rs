@@ -58,7 +58,10 @@ impl Progress {
         targets: &[generator::targets::ProgressTrackingTarget],
         target: &str,
     ) -> Vec<std::path::PathBuf> {
-        let progress_target = targets.iter().find(|t| t.target == target).unwrap();
+        let progress_target = targets
+            .iter()
+            .find(|t| t.target == target || t.name == target)
+            .unwrap();
         let mut object_files = Vec::<std::path::PathBuf>::new();
         for dependency in &progress_target.dependencies {
             let target_dependency = targets.iter().find(|t| t.target == *dependency).unwrap();
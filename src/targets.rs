@@ -51,6 +51,7 @@ pub struct Library {
     pub compiler_flags: CompilerFlags,
     pub lib_type: types::LibraryType,
     pub defines: Vec<types::Define>,
+    pub pkg_config: Option<types::PkgConfigExportData>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -83,6 +84,46 @@ impl Dependency {
                 );
                 dependency = Dependency::from_source(name, source_data, manifest_dir);
             }
+            types::DependencyData::Git(ref git_data) => {
+                log::debug!(
+                    "Found git dependency {} from {} with origin {:?}",
+                    name,
+                    git_data.git,
+                    git_data.origin
+                );
+                dependency = Dependency::from_git(name, git_data, manifest_dir);
+            }
+            types::DependencyData::Vcpkg(ref vcpkg_data) => {
+                log::debug!(
+                    "Found vcpkg dependency {} from port {}",
+                    name,
+                    vcpkg_data.vcpkg
+                );
+                dependency = Ok(Dependency {
+                    name: name.to_string(),
+                    data: data.clone(),
+                });
+            }
+            types::DependencyData::Conan(ref conan_data) => {
+                log::debug!(
+                    "Found conan dependency {} generated to {}",
+                    name,
+                    conan_data.conan.display()
+                );
+                dependency = Dependency::from_conan_data(name, conan_data, manifest_dir);
+            }
+            types::DependencyData::System(ref system_data) => {
+                log::debug!(
+                    "Found system dependency {} with libraries {:?} and headers {:?}",
+                    name,
+                    system_data.system.libs,
+                    system_data.system.headers
+                );
+                dependency = Ok(Dependency {
+                    name: name.to_string(),
+                    data: data.clone(),
+                });
+            }
             types::DependencyData::HeaderOnly(ref header_only_data) => {
                 log::debug!(
                     "Found header only dependency {} with include directory \"{}\"",
@@ -111,6 +152,7 @@ impl Dependency {
         let canonicalized_data = types::DependencyData::Source(types::SourceData {
             path: canonicalized_path,
             origin: source_data.origin.clone(),
+            version: source_data.version.clone(),
         });
         Ok(Self {
             name: name.to_string(),
@@ -118,6 +160,26 @@ impl Dependency {
         })
     }
 
+    /// Repositories given as a relative path on the file system are made absolute, so they
+    /// refer to the same repository regardless of where yambs is invoked from.
+    fn from_git(
+        name: &str,
+        git_data: &types::GitData,
+        manifest_dir: &std::path::Path,
+    ) -> Result<Self, DependencyError> {
+        let mut git_data = git_data.clone();
+        if is_local_path(&git_data.git) {
+            let path = std::path::PathBuf::from(&git_data.git);
+            let canonicalized_path = crate::canonicalize_source(manifest_dir, &path)
+                .map_err(|err| DependencyError::FailedToCanonicalizePath(path, err))?;
+            git_data.git = canonicalized_path.display().to_string();
+        }
+        Ok(Self {
+            name: name.to_string(),
+            data: types::DependencyData::Git(git_data),
+        })
+    }
+
     fn from_header_only(
         name: &str,
         header_only_data: &types::HeaderOnlyData,
@@ -140,6 +202,24 @@ impl Dependency {
         })
     }
 
+    fn from_conan_data(
+        name: &str,
+        conan_data: &types::ConanData,
+        manifest_dir: &Path,
+    ) -> Result<Self, DependencyError> {
+        let generators_directory = crate::canonicalize_source(manifest_dir, &conan_data.conan)
+            .map_err(|err| {
+                DependencyError::FailedToCanonicalizePath(conan_data.conan.clone(), err)
+            })?;
+        Ok(Self {
+            name: name.to_string(),
+            data: types::DependencyData::Conan(types::ConanData {
+                conan: generators_directory,
+                version: conan_data.version.clone(),
+            }),
+        })
+    }
+
     fn from_pkgconfig_data(
         name: &str,
         pkgconfig_data: &types::PkgConfigData,
@@ -152,7 +232,17 @@ impl Dependency {
 
         Ok(Self {
             name: name.to_string(),
-            data: types::DependencyData::PkgConfig(PkgConfigData { search_dir }),
+            data: types::DependencyData::PkgConfig(PkgConfigData {
+                search_dir,
+                define_variable: pkgconfig_data.define_variable.clone(),
+                link: pkgconfig_data.link.clone(),
+                version: pkgconfig_data.version.clone(),
+            }),
         })
     }
 }
+
+/// A repository URL without a scheme or an scp-like `host:path` is a path on the file system.
+fn is_local_path(url: &str) -> bool {
+    !url.contains("://") && (url.starts_with('/') || url.starts_with('.') || !url.contains(':'))
+}
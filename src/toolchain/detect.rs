@@ -0,0 +1,433 @@
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use super::TOOLCHAIN_FILE_NAME;
+use crate::compiler::{CompilerInfo, Linker, Type};
+use crate::parser::types::Language;
+use crate::{find_program, FindProgramOptions};
+
+/// Linkers that can be selected with `-fuse-ld`, by the name of their executable.
+const LINKERS: &[(&str, Linker)] = &[
+    ("ld", Linker::Ld),
+    ("ld.gold", Linker::Gold),
+    ("ld.lld", Linker::LLD),
+    ("mold", Linker::Mold),
+];
+
+/// A compiler found while detecting toolchains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerCandidate {
+    pub path: PathBuf,
+    pub language: Language,
+    pub compiler_info: CompilerInfo,
+    /// Name of the compiler with the language left out, like `gcc-13` for both `g++-13` and
+    /// `gcc-13`, which pairs the C++ and C compilers of a toolchain.
+    family: String,
+}
+
+/// A linker found while detecting toolchains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkerCandidate {
+    pub path: PathBuf,
+    pub linker: Linker,
+}
+
+/// The compilers and tools available in the search directories, usually those of `$PATH`.
+#[derive(Debug, Clone, Default)]
+pub struct Detection {
+    pub compilers: Vec<CompilerCandidate>,
+    pub linkers: Vec<LinkerCandidate>,
+    pub archiver: Option<PathBuf>,
+    pub pkg_config: Option<PathBuf>,
+    pub launcher: Option<PathBuf>,
+}
+
+impl Detection {
+    pub fn from_path_env() -> Self {
+        let search_directories = std::env::var_os("PATH")
+            .map(|paths| std::env::split_paths(&paths).collect::<Vec<PathBuf>>())
+            .unwrap_or_default();
+        Self::scan(&search_directories)
+    }
+
+    /// Detects gcc and clang compilers, including versioned and target prefixed ones like
+    /// `g++-13` or `aarch64-linux-gnu-gcc`, and the linkers, archiver and pkg-config in
+    /// `search_directories`. Like in a shell, only the first of several programs with the same name
+    /// is used.
+    pub fn scan(search_directories: &[PathBuf]) -> Self {
+        let mut compilers: Vec<CompilerCandidate> = Vec::new();
+        for directory in search_directories {
+            let mut entries = match std::fs::read_dir(directory) {
+                Ok(entries) => entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file())
+                    .collect::<Vec<PathBuf>>(),
+                Err(_) => continue,
+            };
+            entries.sort();
+            for path in entries {
+                let file_name = match path.file_name() {
+                    Some(file_name) => file_name.to_string_lossy().to_string(),
+                    None => continue,
+                };
+                let (language, family) = match parse_compiler_name(&file_name) {
+                    Some(parsed) => parsed,
+                    None => continue,
+                };
+                if compilers
+                    .iter()
+                    .any(|compiler| compiler.path.file_name() == path.file_name())
+                {
+                    continue;
+                }
+                match CompilerInfo::new(&path) {
+                    Ok(compiler_info) => compilers.push(CompilerCandidate {
+                        path,
+                        language,
+                        compiler_info,
+                        family,
+                    }),
+                    Err(err) => log::debug!("Skipping {}: {}", path.display(), err),
+                }
+            }
+        }
+
+        let find = |program: &str| {
+            let mut search_options = FindProgramOptions::new();
+            for directory in search_directories {
+                search_options.search_directory(directory);
+            }
+            find_program(Path::new(program), search_options)
+        };
+        let linkers = LINKERS
+            .iter()
+            .filter_map(|(program, linker)| {
+                find(program).map(|path| LinkerCandidate {
+                    path,
+                    linker: linker.clone(),
+                })
+            })
+            .collect();
+        let launcher = ["ccache", "sccache"]
+            .iter()
+            .find_map(|program| find(program));
+
+        Self {
+            compilers,
+            linkers,
+            archiver: find("ar"),
+            pkg_config: find("pkg-config"),
+            launcher,
+        }
+    }
+
+    /// The pairs of C++ and C compilers of the same family, like `g++-13` and `gcc-13`.
+    pub fn toolchains(&self) -> Vec<(&CompilerCandidate, &CompilerCandidate)> {
+        self.compilers
+            .iter()
+            .filter(|compiler| compiler.language == Language::CXX)
+            .filter_map(|cxx| {
+                self.compilers
+                    .iter()
+                    .find(|cc| cc.language == Language::C && cc.family == cxx.family)
+                    .map(|cc| (cxx, cc))
+            })
+            .collect()
+    }
+
+    /// The toolchain to write a toolchain file for: the one of `$CXX` if it was detected, and
+    /// otherwise the newest compiler for the host.
+    pub fn preferred(&self) -> Option<(&CompilerCandidate, &CompilerCandidate)> {
+        let toolchains = self.toolchains();
+        if let Some(cxx) = std::env::var_os("CXX") {
+            let cxx = PathBuf::from(cxx);
+            if let Some(toolchain) = toolchains.iter().find(|(candidate, _)| {
+                candidate.path == cxx
+                    || Some(candidate.path.as_os_str()) == cxx.file_name()
+                    || candidate.path.file_name() == Some(cxx.as_os_str())
+            }) {
+                return Some(*toolchain);
+            }
+        }
+        toolchains
+            .into_iter()
+            .filter(|(cxx, _)| cxx.is_for_host())
+            .max_by(|(a, _), (b, _)| {
+                version(a)
+                    .cmp(&version(b))
+                    // gcc is the default compiler of most systems.
+                    .then_with(|| {
+                        (a.compiler_info.compiler_type == Type::Gcc)
+                            .cmp(&(b.compiler_info.compiler_type == Type::Gcc))
+                    })
+                    // Prefer the unversioned name, like g++ over g++-13.
+                    .then_with(|| b.family.len().cmp(&a.family.len()))
+            })
+    }
+
+    /// A toolchain file for `cxx` and `cc`, with the other detected options in comments.
+    pub fn render_toolchain_file(&self, cxx: &CompilerCandidate, cc: &CompilerCandidate) -> String {
+        let mut content = format!(
+            "# Generated by 'yambs toolchain detect'.\n\
+             # C++ compiler {} {}, C compiler {} {}.\n",
+            cxx.compiler_info.compiler_type.to_string(),
+            cxx.compiler_info.compiler_version,
+            cc.compiler_info.compiler_type.to_string(),
+            cc.compiler_info.compiler_version,
+        );
+        let others = self
+            .toolchains()
+            .into_iter()
+            .filter(|(other, _)| other.path != cxx.path)
+            .map(|(other, _)| format!("#   {}\n", other.describe()))
+            .collect::<String>();
+        if !others.is_empty() {
+            content.push_str("# Other detected C++ compilers:\n");
+            content.push_str(&others);
+        }
+
+        let linker_comment = if self.linkers.is_empty() {
+            "# linker = \"ld\"\n".to_string()
+        } else {
+            format!(
+                "# Detected linkers: {}.\n# linker = \"{}\"\n",
+                self.linkers
+                    .iter()
+                    .map(|linker| format!(
+                        "{} ({})",
+                        linker_name(&linker.linker),
+                        linker.path.display()
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                linker_name(&self.linkers[self.linkers.len() - 1].linker),
+            )
+        };
+
+        content.push_str(&format!(
+            "\n[CXX]\ncompiler = {}\n\
+             # Linker selected with -fuse-ld. Without it, the default of the compiler is used.\n\
+             {linker}\
+             # C++ standard library: \"libstdc++\" or \"libc++\".\n\
+             # stdlib = \"{}\"\n",
+            toml_path(&cxx.path),
+            match cxx.compiler_info.compiler_type {
+                Type::Gcc => "libstdc++",
+                Type::Clang => "libc++",
+            },
+            linker = linker_comment,
+        ));
+        content.push_str(&format!(
+            "\n[CC]\ncompiler = {}\n{linker}",
+            toml_path(&cc.path),
+            linker = linker_comment,
+        ));
+
+        content.push_str("\n[common]\n");
+        match self.archiver {
+            Some(ref archiver) => {
+                content.push_str(&format!("archiver = {}\n", toml_path(archiver)))
+            }
+            None => content.push_str("# archiver = \"/usr/bin/ar\"\n"),
+        }
+        match self.pkg_config {
+            Some(ref pkg_config) => {
+                content.push_str(&format!("pkg-config = {}\n", toml_path(pkg_config)))
+            }
+            None => content.push_str("# pkg-config = \"/usr/bin/pkg-config\"\n"),
+        }
+        content.push_str(
+            "# Compiler cache that compile commands are run through. Found in PATH if not set.\n",
+        );
+        match self.launcher {
+            Some(ref launcher) => {
+                content.push_str(&format!("# launcher = {}\n", toml_path(launcher)))
+            }
+            None => content.push_str("# launcher = \"ccache\"\n"),
+        }
+        content
+    }
+
+    /// Lists everything that was detected.
+    pub fn render_list(&self) -> String {
+        let mut content = String::new();
+        for (language, title) in [
+            (Language::CXX, "C++ compilers"),
+            (Language::C, "C compilers"),
+        ] {
+            content.push_str(&format!("{}:\n", title));
+            for compiler in self
+                .compilers
+                .iter()
+                .filter(|compiler| compiler.language == language)
+            {
+                content.push_str(&format!("  {}\n", compiler.describe()));
+            }
+        }
+        content.push_str("Linkers:\n");
+        for linker in &self.linkers {
+            content.push_str(&format!(
+                "  {} ({})\n",
+                linker.path.display(),
+                linker_name(&linker.linker)
+            ));
+        }
+        let optional = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "not found".to_string())
+        };
+        content.push_str(&format!("Archiver: {}\n", optional(&self.archiver)));
+        content.push_str(&format!("pkg-config: {}\n", optional(&self.pkg_config)));
+        content.push_str(&format!("Launcher: {}\n", optional(&self.launcher)));
+        content
+    }
+
+    /// Where `yambs toolchain detect` writes the toolchain file by default.
+    pub fn default_output(manifest_dir: &Path) -> PathBuf {
+        manifest_dir.join(".yambs").join(TOOLCHAIN_FILE_NAME)
+    }
+}
+
+impl CompilerCandidate {
+    /// Whether the compiler builds for the host, which compilers with a target prefix, like
+    /// `aarch64-linux-gnu-g++`, usually don't.
+    fn is_for_host(&self) -> bool {
+        self.family.starts_with("gcc") || self.family.starts_with("clang")
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} ({} {})",
+            self.path.display(),
+            self.compiler_info.compiler_type.to_string(),
+            self.compiler_info.compiler_version
+        )
+    }
+}
+
+fn version(compiler: &CompilerCandidate) -> Option<semver::Version> {
+    semver::Version::parse(&compiler.compiler_info.compiler_version).ok()
+}
+
+fn linker_name(linker: &Linker) -> &'static str {
+    match linker {
+        Linker::Ld => "ld",
+        Linker::Gold => "gold",
+        Linker::LLD => "lld",
+        Linker::Mold => "mold",
+        Linker::Inferred => "inferred",
+    }
+}
+
+fn toml_path(path: &Path) -> String {
+    toml::Value::String(path.display().to_string()).to_string()
+}
+
+/// The language and family of a compiler executable named `file_name`, like `(C++, gcc-13)` for
+/// `g++-13`, or `None` if it is not the name of a gcc or clang compiler.
+fn parse_compiler_name(file_name: &str) -> Option<(Language, String)> {
+    lazy_static::lazy_static! {
+        static ref COMPILER_NAME: Regex = Regex::new(
+            r"^(?P<prefix>[A-Za-z0-9_.]+(?:-[A-Za-z0-9_.]+)*-)?(?P<compiler>gcc|g\+\+|clang|clang\+\+)(?P<suffix>-[0-9]+(?:\.[0-9]+)*)?$"
+        )
+        .expect("Could not compile regular expression");
+    }
+    let captures = COMPILER_NAME.captures(file_name)?;
+    let (language, family) = match &captures["compiler"] {
+        "g++" => (Language::CXX, "gcc"),
+        "gcc" => (Language::C, "gcc"),
+        "clang++" => (Language::CXX, "clang"),
+        _ => (Language::C, "clang"),
+    };
+    Some((
+        language,
+        format!(
+            "{}{}{}",
+            captures.name("prefix").map_or("", |prefix| prefix.as_str()),
+            family,
+            captures.name("suffix").map_or("", |suffix| suffix.as_str())
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Type;
+
+    fn candidate(path: &str, compiler_type: Type, version: &str) -> CompilerCandidate {
+        let file_name = Path::new(path).file_name().unwrap().to_str().unwrap();
+        let (language, family) = parse_compiler_name(file_name).unwrap();
+        CompilerCandidate {
+            path: PathBuf::from(path),
+            language,
+            compiler_info: CompilerInfo {
+                compiler_type,
+                compiler_version: version.to_string(),
+            },
+            family,
+        }
+    }
+
+    #[test]
+    fn parses_compiler_names() {
+        assert_eq!(
+            parse_compiler_name("g++-13"),
+            Some((Language::CXX, "gcc-13".to_string()))
+        );
+        assert_eq!(
+            parse_compiler_name("clang-17"),
+            Some((Language::C, "clang-17".to_string()))
+        );
+        assert_eq!(
+            parse_compiler_name("aarch64-linux-gnu-g++"),
+            Some((Language::CXX, "aarch64-linux-gnu-gcc".to_string()))
+        );
+        assert_eq!(parse_compiler_name("gcc-ar"), None);
+        assert_eq!(parse_compiler_name("x86_64-linux-gnu-gcc-ar-13"), None);
+        assert_eq!(parse_compiler_name("clang-format"), None);
+    }
+
+    #[test]
+    fn pairs_compilers_and_renders_a_toolchain_file() {
+        let detection = Detection {
+            compilers: vec![
+                candidate("/usr/bin/g++-12", Type::Gcc, "12.3.0"),
+                candidate("/usr/bin/gcc-12", Type::Gcc, "12.3.0"),
+                candidate("/usr/bin/g++-13", Type::Gcc, "13.2.0"),
+                candidate("/usr/bin/gcc-13", Type::Gcc, "13.2.0"),
+                candidate("/usr/bin/aarch64-linux-gnu-g++", Type::Gcc, "14.1.0"),
+                candidate("/usr/bin/aarch64-linux-gnu-gcc", Type::Gcc, "14.1.0"),
+                candidate("/usr/bin/clang++", Type::Clang, "17.0.6"),
+            ],
+            linkers: vec![LinkerCandidate {
+                path: PathBuf::from("/usr/bin/mold"),
+                linker: Linker::Mold,
+            }],
+            archiver: Some(PathBuf::from("/usr/bin/ar")),
+            pkg_config: None,
+            launcher: None,
+        };
+        assert_eq!(detection.toolchains().len(), 3);
+
+        let (cxx, cc) = detection
+            .toolchains()
+            .into_iter()
+            .find(|(cxx, _)| cxx.path == Path::new("/usr/bin/g++-13"))
+            .unwrap();
+        assert_eq!(cc.path, PathBuf::from("/usr/bin/gcc-13"));
+        let content = detection.render_toolchain_file(cxx, cc);
+        let toolchain: super::super::Toolchain = toml::from_str(&content).unwrap();
+        assert_eq!(toolchain.cxx.compiler, PathBuf::from("/usr/bin/g++-13"));
+        assert_eq!(toolchain.cc.compiler, PathBuf::from("/usr/bin/gcc-13"));
+        assert_eq!(
+            toolchain.common.archiver,
+            Some(PathBuf::from("/usr/bin/ar"))
+        );
+        assert!(content.contains("# linker = \"mold\""));
+        assert!(content.contains("#   /usr/bin/g++-12 (gcc 12.3.0)"));
+    }
+}
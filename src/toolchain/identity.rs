@@ -0,0 +1,280 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{NormalizedToolchain, ToolchainError};
+use crate::compiler::{self, Linker, StdLibCC, StdLibCXX};
+use crate::{find_program, FindProgramOptions};
+
+/// File in the build directory with the identity of the toolchain it was configured with.
+pub const TOOLCHAIN_IDENTITY_FILE_NAME: &str = "toolchain.json";
+
+/// What a compiler produces objects with: the compiler itself, the target it builds for, and the
+/// standard library and linker it is used with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerIdentity {
+    /// The compiler executable, with symbolic links resolved.
+    pub path: PathBuf,
+    /// The compiler executable as it is invoked. Not part of the identity, since `g++` and the
+    /// `g++-12` it links to are the same compiler.
+    #[serde(skip)]
+    pub executable: PathBuf,
+    /// Full output of `--version`.
+    pub version: String,
+    pub target: Option<String>,
+    pub sysroot: Option<PathBuf>,
+    /// Output of `-dumpmachine`.
+    pub machine: String,
+    pub stdlib: String,
+    pub linker: Linker,
+}
+
+/// Identity of a toolchain. Objects compiled by toolchains with different fingerprints must not
+/// be linked together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolchainIdentity {
+    pub fingerprint: String,
+    #[serde(rename = "CXX")]
+    pub cxx: CompilerIdentity,
+    #[serde(rename = "CC")]
+    pub cc: CompilerIdentity,
+}
+
+impl ToolchainIdentity {
+    pub fn new(toolchain: &NormalizedToolchain) -> Result<Self, ToolchainError> {
+        let cxx = &toolchain.cxx;
+        let cxx = CompilerIdentity::new(
+            &cxx.compiler.compiler_exe,
+            &cxx.compiler.target_flags(),
+            cxx.compiler.target.as_deref(),
+            cxx.compiler.sysroot.as_deref(),
+            match cxx.compiler.stdlib {
+                StdLibCXX::LibStdCXX => "libstdc++",
+                StdLibCXX::LibCXX => "libc++",
+            },
+            &cxx.linker,
+        )?;
+        let cc = &toolchain.cc;
+        let cc = CompilerIdentity::new(
+            &cc.compiler.compiler_exe,
+            &cc.compiler.target_flags(),
+            cc.compiler.target.as_deref(),
+            cc.compiler.sysroot.as_deref(),
+            match cc.compiler.stdlib {
+                StdLibCC::Libc => "libc",
+            },
+            &cc.linker,
+        )?;
+        Ok(Self::from_compilers(cxx, cc))
+    }
+
+    fn from_compilers(cxx: CompilerIdentity, cc: CompilerIdentity) -> Self {
+        let content = serde_json::to_string(&(&cxx, &cc))
+            .expect("Compiler identities are always serializable");
+        Self {
+            fingerprint: crate::utility::sha256_hex(content.as_bytes()),
+            cxx,
+            cc,
+        }
+    }
+
+    /// The identity of the toolchain `build_directory` was last configured with, if any.
+    pub fn load(build_directory: &Path) -> Option<Self> {
+        let content =
+            std::fs::read_to_string(build_directory.join(TOOLCHAIN_IDENTITY_FILE_NAME)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(identity) => Some(identity),
+            Err(err) => {
+                log::debug!("Ignoring unreadable toolchain identity: {}", err);
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, build_directory: &Path) -> Result<(), ToolchainError> {
+        let path = build_directory.join(TOOLCHAIN_IDENTITY_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .expect("Toolchain identities are always serializable");
+        std::fs::create_dir_all(build_directory)
+            .and_then(|_| std::fs::write(&path, content))
+            .map_err(|err| ToolchainError::FailedToStoreIdentity(path, err))
+    }
+
+    /// The compiler executables as they are invoked, whose content is part of the identity.
+    pub fn compilers(&self) -> Vec<PathBuf> {
+        vec![self.cxx.executable.clone(), self.cc.executable.clone()]
+    }
+
+    /// Describes how the toolchain differs from `previous`.
+    pub fn changes(&self, previous: &Self) -> Vec<String> {
+        let mut changes = self.cxx.changes(&previous.cxx, "C++ compiler");
+        changes.extend(self.cc.changes(&previous.cc, "C compiler"));
+        changes
+    }
+}
+
+impl std::fmt::Display for ToolchainIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Fingerprint: {}", self.fingerprint)?;
+        write!(f, "C++ compiler: {}", self.cxx)?;
+        write!(f, "C compiler: {}", self.cc)
+    }
+}
+
+impl CompilerIdentity {
+    fn new(
+        compiler_exe: &Path,
+        target_flags: &[String],
+        target: Option<&str>,
+        sysroot: Option<&Path>,
+        stdlib: &str,
+        linker: &Linker,
+    ) -> Result<Self, ToolchainError> {
+        let executable = locate_executable(compiler_exe);
+        Ok(Self {
+            path: std::fs::canonicalize(&executable).unwrap_or_else(|_| executable.clone()),
+            executable,
+            version: compiler::compiler_version_raw(compiler_exe)
+                .map_err(ToolchainError::CouldNotGetCompiler)?
+                .trim()
+                .to_string(),
+            target: target.map(str::to_string),
+            sysroot: sysroot.map(Path::to_path_buf),
+            machine: compiler::machine(compiler_exe, target_flags)
+                .map_err(ToolchainError::CouldNotGetCompiler)?,
+            stdlib: stdlib.to_string(),
+            linker: linker.clone(),
+        })
+    }
+
+    fn version_line(&self) -> &str {
+        self.version.lines().next().unwrap_or_default()
+    }
+
+    fn changes(&self, previous: &Self, name: &str) -> Vec<String> {
+        let linker = |identity: &Self| format!("{:?}", identity.linker).to_lowercase();
+        let fields = [
+            (
+                "path",
+                previous.path.display().to_string(),
+                self.path.display().to_string(),
+            ),
+            (
+                "version",
+                previous.version_line().to_string(),
+                self.version_line().to_string(),
+            ),
+            (
+                "target",
+                format!("{:?}", previous.target),
+                format!("{:?}", self.target),
+            ),
+            (
+                "sysroot",
+                format!("{:?}", previous.sysroot),
+                format!("{:?}", self.sysroot),
+            ),
+            ("machine", previous.machine.clone(), self.machine.clone()),
+            (
+                "standard library",
+                previous.stdlib.clone(),
+                self.stdlib.clone(),
+            ),
+            ("linker", linker(previous), linker(self)),
+        ];
+        let mut changes = fields
+            .iter()
+            .filter(|(_, previous, current)| previous != current)
+            .map(|(field, previous, current)| {
+                format!("{} {}: {} -> {}", name, field, previous, current)
+            })
+            .collect::<Vec<String>>();
+        if previous.version_line() == self.version_line() && previous.version != self.version {
+            changes.push(format!("{} version output changed", name));
+        }
+        changes
+    }
+}
+
+impl std::fmt::Display for CompilerIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.path.display())?;
+        writeln!(f, "  Version: {}", self.version_line())?;
+        writeln!(f, "  Machine: {}", self.machine)?;
+        if let Some(ref target) = self.target {
+            writeln!(f, "  Target: {}", target)?;
+        }
+        if let Some(ref sysroot) = self.sysroot {
+            writeln!(f, "  Sysroot: {}", sysroot.display())?;
+        }
+        writeln!(f, "  Standard library: {}", self.stdlib)?;
+        writeln!(
+            f,
+            "  Linker: {}",
+            format!("{:?}", self.linker).to_lowercase()
+        )
+    }
+}
+
+/// The path of `compiler_exe`, looked up in `PATH` if it is a bare program name.
+fn locate_executable(compiler_exe: &Path) -> PathBuf {
+    if compiler_exe.components().count() == 1 && !compiler_exe.exists() {
+        let mut search_options = FindProgramOptions::new();
+        search_options.with_path_env();
+        find_program(compiler_exe, search_options).unwrap_or_else(|| compiler_exe.to_path_buf())
+    } else {
+        compiler_exe.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gcc(version: &str) -> CompilerIdentity {
+        CompilerIdentity {
+            path: PathBuf::from(format!("/usr/bin/x86_64-linux-gnu-g++-{}", version)),
+            executable: PathBuf::new(),
+            version: format!("g++ (Debian {0}-1) {0}\nCopyright (C) 2022", version),
+            target: None,
+            sysroot: None,
+            machine: "x86_64-linux-gnu".to_string(),
+            stdlib: "libstdc++".to_string(),
+            linker: Linker::Inferred,
+        }
+    }
+
+    #[test]
+    fn identity_changes_with_the_compiler() {
+        let previous = ToolchainIdentity::from_compilers(gcc("12.2.0"), gcc("12.2.0"));
+        assert_eq!(
+            previous,
+            ToolchainIdentity::from_compilers(gcc("12.2.0"), gcc("12.2.0"))
+        );
+
+        let mut cxx = gcc("13.1.0");
+        cxx.linker = Linker::Mold;
+        let current = ToolchainIdentity::from_compilers(cxx, gcc("12.2.0"));
+        assert_ne!(previous.fingerprint, current.fingerprint);
+        assert_eq!(
+            current.changes(&previous),
+            vec![
+                "C++ compiler path: /usr/bin/x86_64-linux-gnu-g++-12.2.0 -> /usr/bin/x86_64-linux-gnu-g++-13.1.0",
+                "C++ compiler version: g++ (Debian 12.2.0-1) 12.2.0 -> g++ (Debian 13.1.0-1) 13.1.0",
+                "C++ compiler linker: inferred -> mold",
+            ]
+        );
+    }
+
+    #[test]
+    fn identity_is_stored_in_build_directory() {
+        let build_directory = tempdir::TempDir::new("identity").unwrap();
+        assert_eq!(ToolchainIdentity::load(build_directory.path()), None);
+        let identity = ToolchainIdentity::from_compilers(gcc("12.2.0"), gcc("12.2.0"));
+        identity.store(build_directory.path()).unwrap();
+        assert_eq!(
+            ToolchainIdentity::load(build_directory.path()),
+            Some(identity)
+        );
+    }
+}
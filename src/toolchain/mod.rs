@@ -0,0 +1,558 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::build_target::pkg_config::PkgConfig;
+use crate::build_target::system::{SearchDirectories, SystemError};
+use crate::compiler::{CCCompiler, CXXCompiler, CompilerError, Linker, StdLibCC, StdLibCXX};
+use crate::{find_program, FindProgramOptions};
+
+pub mod detect;
+pub mod identity;
+
+pub const TOOLCHAIN_FILE_NAME: &str = "toolchain.toml";
+/// Directory in `.yambs` with the named toolchains, selected with `--toolchain`.
+pub const TOOLCHAINS_DIRECTORY: &str = "toolchains";
+
+#[derive(PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct Archiver {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum ArchiverError {
+    #[error("No archiver found")]
+    NoArchiverFound,
+    #[error("Archiver does not exist")]
+    ArchiverDoesNotExist,
+}
+
+impl Archiver {
+    pub fn new() -> Result<Self, ArchiverError> {
+        let archiver_exe = {
+            if let Some(archiver_from_env) = Self::try_from_environment_variable() {
+                log::debug!("Found archiver in $AR. Using this.");
+                Ok(archiver_from_env)
+            } else {
+                log::debug!("Did not find archiver in $AR. Will try to find 'ar' in common installation places.");
+                let mut search_options = FindProgramOptions::new();
+                search_options.with_path_env();
+                if let Some(archiver) = find_program(Path::new("ar"), search_options) {
+                    Ok(archiver)
+                } else {
+                    return Err(ArchiverError::NoArchiverFound);
+                }
+            }
+        }?;
+        Ok(Self { path: archiver_exe })
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, ArchiverError> {
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn try_from_environment_variable() -> Option<PathBuf> {
+        env::var_os("AR").map(PathBuf::from)
+    }
+}
+
+/// Compiler cache, like ccache or sccache, that compile commands are run through.
+#[derive(PartialEq, Eq, Debug, Clone, Deserialize, Serialize)]
+pub struct Launcher {
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum LauncherError {
+    #[error("Launcher {0} does not exist")]
+    LauncherDoesNotExist(PathBuf),
+}
+
+impl Launcher {
+    /// Programs that are used as launcher when none is given in the toolchain file, in order of
+    /// preference.
+    const PROGRAMS: &'static [&'static str] = &["ccache", "sccache"];
+
+    /// Finds a compiler cache in $PATH.
+    pub fn new() -> Option<Self> {
+        Self::PROGRAMS.iter().find_map(|program| {
+            let mut search_options = FindProgramOptions::new();
+            search_options.with_path_env();
+            find_program(Path::new(program), search_options).map(|path| Self { path })
+        })
+    }
+
+    /// A launcher given by `path`. A plain program name, like "ccache", is looked up in $PATH.
+    pub fn from_path(path: &Path) -> Result<Self, LauncherError> {
+        if path.is_file() {
+            return Ok(Self {
+                path: path.to_path_buf(),
+            });
+        }
+        if path.components().count() == 1 {
+            let mut search_options = FindProgramOptions::new();
+            search_options.with_path_env();
+            if let Some(path) = find_program(path, search_options) {
+                return Ok(Self { path });
+            }
+        }
+        Err(LauncherError::LauncherDoesNotExist(path.to_path_buf()))
+    }
+
+    pub fn is_ccache(&self) -> bool {
+        self.path.file_stem() == Some(std::ffi::OsStr::new("ccache"))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Deserialize)]
+pub struct ToolchainCXXData {
+    pub compiler: PathBuf,
+    pub linker: Option<Linker>,
+    #[serde(default)]
+    pub stdlib: StdLibCXX,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ToolchainCXX {
+    pub compiler: CXXCompiler,
+    pub linker: Linker,
+}
+
+impl ToolchainCXX {
+    pub fn new() -> Result<Self, ToolchainError> {
+        Ok(Self {
+            compiler: CXXCompiler::new().map_err(ToolchainError::CouldNotGetCompiler)?,
+            linker: Linker::new(),
+        })
+    }
+
+    pub fn from_toolchain_cxx_data(
+        toolchain_cxx_data: &ToolchainCXXData,
+    ) -> Result<Self, ToolchainError> {
+        let linker = if let Some(ref linker) = toolchain_cxx_data.linker {
+            linker.clone()
+        } else {
+            Linker::default()
+        };
+        Ok(Self {
+            compiler: CXXCompiler::from_toolchain_cxx_data(toolchain_cxx_data)
+                .map_err(ToolchainError::CouldNotGetCompiler)?,
+            linker,
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ToolchainCC {
+    pub compiler: CCCompiler,
+    pub linker: Linker,
+}
+
+impl ToolchainCC {
+    pub fn new() -> Result<Self, ToolchainError> {
+        Ok(Self {
+            compiler: CCCompiler::new().map_err(ToolchainError::CouldNotGetCompiler)?,
+            linker: Linker::new(),
+        })
+    }
+
+    pub fn from_toolchain_cc_data(
+        toolchain_cc_data: &ToolchainCCData,
+    ) -> Result<Self, ToolchainError> {
+        let linker = if let Some(ref linker) = toolchain_cc_data.linker {
+            linker.clone()
+        } else {
+            Linker::default()
+        };
+        Ok(Self {
+            compiler: CCCompiler::from_toolchain_cc_data(toolchain_cc_data)
+                .map_err(ToolchainError::CouldNotGetCompiler)?,
+            linker,
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Deserialize)]
+pub struct ToolchainCCData {
+    pub compiler: PathBuf,
+    pub linker: Option<Linker>,
+    #[serde(default)]
+    pub stdlib: StdLibCC,
+}
+
+#[derive(PartialEq, Eq, Debug, Deserialize)]
+struct Toolchain {
+    #[serde(rename = "CXX")]
+    pub cxx: ToolchainCXXData,
+    #[serde(rename = "CC")]
+    pub cc: ToolchainCCData,
+    pub common: CommonToolchainData,
+}
+
+impl Toolchain {
+    fn new(path: &Path) -> Result<Self, ToolchainError> {
+        if !path.is_file() {
+            return Err(ToolchainError::NotAFile);
+        }
+        if let Some(file_name) = path.file_name() {
+            if Path::new(file_name).extension() != Some(std::ffi::OsStr::new("toml")) {
+                return Err(ToolchainError::IncorrectFilename);
+            }
+            let toolchain_file_content = String::from_utf8(
+                fs::read(path)
+                    .map_err(|e| ToolchainError::FailedToParseTomlFile(e, path.to_path_buf()))?,
+            )
+            .map_err(ToolchainError::FailedToConvertUtf8)?;
+            toml::from_str(&toolchain_file_content)
+                .map_err(|e| ToolchainError::FailedToParseToolchainFile(e, path.to_path_buf()))
+        } else {
+            Err(ToolchainError::CouldNotGetFilename)
+        }
+    }
+
+    fn to_toolchain(&self) -> Result<NormalizedToolchain, ToolchainError> {
+        let archiver = {
+            if let Some(ref archiver) = self.common.archiver {
+                log::debug!("Using archiver found from toolchain file");
+                Archiver::from_path(archiver)
+            } else {
+                Archiver::new()
+            }
+        }
+        .map_err(ToolchainError::Archiver)?;
+
+        let mut pkg_config = {
+            if let Some(ref pkg_config) = self.common.pkg_config {
+                log::debug!("Using pkg_config found from toolchain file");
+                PkgConfig::from_path(pkg_config)
+            } else {
+                PkgConfig::new()
+            }
+        };
+        if let Some(pkg_config_sysroot_dir) = self
+            .common
+            .pkg_config_sysroot_dir
+            .as_ref()
+            .or(self.common.sysroot.as_ref())
+        {
+            pkg_config.set_sysroot(pkg_config_sysroot_dir, self.common.target.as_deref());
+        }
+
+        let launcher = if let Some(ref launcher) = self.common.launcher {
+            log::debug!("Using launcher found from toolchain file");
+            Some(Launcher::from_path(launcher).map_err(ToolchainError::Launcher)?)
+        } else {
+            Launcher::new()
+        };
+
+        let mut cxx = ToolchainCXX::from_toolchain_cxx_data(&self.cxx)?;
+        cxx.compiler.target = self.common.target.clone();
+        cxx.compiler.sysroot = self.common.sysroot.clone();
+        let mut cc = ToolchainCC::from_toolchain_cc_data(&self.cc)?;
+        cc.compiler.target = self.common.target.clone();
+        cc.compiler.sysroot = self.common.sysroot.clone();
+
+        Ok(NormalizedToolchain {
+            cxx,
+            cc,
+            archiver,
+            pkg_config,
+            launcher,
+            ranlib: self.common.ranlib.clone(),
+            strip: self.common.strip.clone(),
+            objcopy: self.common.objcopy.clone(),
+            search_directories: std::cell::OnceCell::new(),
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Deserialize)]
+struct CommonToolchainData {
+    pub archiver: Option<PathBuf>,
+    #[serde(rename = "pkg-config")]
+    pub pkg_config: Option<PathBuf>,
+    pub launcher: Option<PathBuf>,
+    /// Target triple to build for, when cross compiling.
+    pub target: Option<String>,
+    pub sysroot: Option<PathBuf>,
+    pub ranlib: Option<PathBuf>,
+    pub strip: Option<PathBuf>,
+    pub objcopy: Option<PathBuf>,
+    /// Sysroot that pkg-config packages are looked up in. Defaults to `sysroot`.
+    pub pkg_config_sysroot_dir: Option<PathBuf>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct NormalizedToolchain {
+    pub cxx: ToolchainCXX,
+    pub cc: ToolchainCC,
+    pub archiver: Archiver,
+    pub pkg_config: PkgConfig,
+    pub launcher: Option<Launcher>,
+    pub ranlib: Option<PathBuf>,
+    pub strip: Option<PathBuf>,
+    pub objcopy: Option<PathBuf>,
+    /// Queried from the C compiler the first time a system dependency is looked up.
+    search_directories: std::cell::OnceCell<SearchDirectories>,
+}
+
+impl NormalizedToolchain {
+    pub fn new() -> Result<Self, ToolchainError> {
+        Ok(Self {
+            cxx: ToolchainCXX::new()?,
+            cc: ToolchainCC::new()?,
+            archiver: Archiver::new().map_err(ToolchainError::Archiver)?,
+            pkg_config: PkgConfig::new(),
+            launcher: Launcher::new(),
+            ranlib: None,
+            strip: None,
+            objcopy: None,
+            search_directories: std::cell::OnceCell::new(),
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ToolchainError> {
+        if path.exists() {
+            log::debug!("Parsing toolchain at {}", path.display());
+            let raw_toolchain = Toolchain::new(path)?;
+            raw_toolchain.to_toolchain()
+        } else {
+            Err(ToolchainError::ToolchainNotFound(path.to_path_buf()))
+        }
+    }
+
+    /// The identity of the toolchain, from its compilers and what they are used with.
+    pub fn identity(&self) -> Result<identity::ToolchainIdentity, ToolchainError> {
+        identity::ToolchainIdentity::new(self)
+    }
+
+    /// The directories the C compiler searches for libraries and headers. The compiler is only
+    /// asked once per toolchain.
+    pub fn search_directories(&self) -> Result<&SearchDirectories, SystemError> {
+        if let Some(search_directories) = self.search_directories.get() {
+            return Ok(search_directories);
+        }
+        let search_directories = SearchDirectories::from_compiler(
+            &self.cc.compiler.compiler_exe,
+            &self.cc.compiler.target_flags(),
+        )?;
+        Ok(self.search_directories.get_or_init(|| search_directories))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ToolchainError {
+    #[error("Error occured with locating archiver")]
+    Archiver(#[source] ArchiverError),
+    #[error("Error occured with locating launcher")]
+    Launcher(#[source] LauncherError),
+    #[error("Path to toolchain file is not a file")]
+    NotAFile,
+    #[error(
+        "File name for toolchain file is incorrect. Toolchain file shall be a TOML file, like {}",
+        TOOLCHAIN_FILE_NAME
+    )]
+    IncorrectFilename,
+    #[error("Failed to get information about compiler")]
+    CouldNotGetCompiler(#[source] CompilerError),
+    #[error("Failed to retrieve file name from toolchain file")]
+    CouldNotGetFilename,
+    #[error("Failed to parse TOML toolchain file {1}")]
+    FailedToParseTomlFile(#[source] std::io::Error, PathBuf),
+    #[error("Failed to parse toolchain file {1}")]
+    FailedToParseToolchainFile(#[source] toml::de::Error, PathBuf),
+    #[error("Failed to convert UTF-8 bytes to string")]
+    FailedToConvertUtf8(#[source] std::string::FromUtf8Error),
+    #[error("Toolchain not found at {0}")]
+    ToolchainNotFound(PathBuf),
+    #[error("Could not find toolchain {0}. Looked for it in {1:?}")]
+    NamedToolchainNotFound(String, Vec<PathBuf>),
+    #[error("Failed to store toolchain identity in {0}")]
+    FailedToStoreIdentity(PathBuf, #[source] std::io::Error),
+}
+
+/// A toolchain selected with `--toolchain`, either by name or by path.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct NamedToolchain {
+    /// Name of the toolchain, which names its subdirectory of the build directory.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl NamedToolchain {
+    /// Locates `toolchain`, which is either a path to a toolchain file or to a directory with a
+    /// `toolchain.toml`, or the name of a toolchain in `.yambs/toolchains` of the project or of
+    /// the home directory. A named toolchain is either `<name>.toml` or `<name>/toolchain.toml`.
+    ///
+    /// Only arguments with a path separator or a `.toml` extension are paths. A bare name is never
+    /// looked up in the current directory, which has a subdirectory of the same name as soon as
+    /// the toolchain has been built with in it.
+    pub fn locate(toolchain: &str, manifest_dir: &Path) -> Result<Self, ToolchainError> {
+        let path = Path::new(toolchain);
+        if Self::is_path(toolchain) {
+            let path = if path.is_dir() {
+                path.join(TOOLCHAIN_FILE_NAME)
+            } else {
+                path.to_path_buf()
+            };
+            if !path.is_file() {
+                return Err(ToolchainError::NamedToolchainNotFound(
+                    toolchain.to_string(),
+                    vec![path],
+                ));
+            }
+            let name = match path.file_name() {
+                Some(file_name) if file_name == TOOLCHAIN_FILE_NAME => path
+                    .parent()
+                    .and_then(|parent| parent.canonicalize().ok())
+                    .and_then(|parent| parent.file_name().map(|name| name.to_owned())),
+                _ => path.file_stem().map(|stem| stem.to_owned()),
+            }
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                ToolchainError::NamedToolchainNotFound(toolchain.to_string(), vec![path.clone()])
+            })?;
+            return Ok(Self { name, path });
+        }
+
+        let mut toolchain_directories =
+            vec![manifest_dir.join(".yambs").join(TOOLCHAINS_DIRECTORY)];
+        if let Some(home_dir) = home::home_dir() {
+            toolchain_directories.push(home_dir.join(".yambs").join(TOOLCHAINS_DIRECTORY));
+        }
+        let candidates = toolchain_directories
+            .iter()
+            .flat_map(|directory| {
+                [
+                    directory.join(format!("{}.toml", toolchain)),
+                    directory.join(toolchain).join(TOOLCHAIN_FILE_NAME),
+                ]
+            })
+            .collect::<Vec<PathBuf>>();
+        match candidates.iter().find(|candidate| candidate.is_file()) {
+            Some(path) => Ok(Self {
+                name: toolchain.to_string(),
+                path: path.clone(),
+            }),
+            None => Err(ToolchainError::NamedToolchainNotFound(
+                toolchain.to_string(),
+                candidates,
+            )),
+        }
+    }
+
+    fn is_path(toolchain: &str) -> bool {
+        let path = Path::new(toolchain);
+        path.components().count() > 1
+            || toolchain.ends_with(std::path::MAIN_SEPARATOR)
+            || path
+                .components()
+                .any(|component| !matches!(component, std::path::Component::Normal(_)))
+            || path.extension() == Some(std::ffi::OsStr::new("toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launcher_from_path_requires_existing_program() {
+        let dir = tempdir::TempDir::new("launcher").unwrap();
+        let sccache = dir.path().join("sccache");
+        std::fs::write(&sccache, "").unwrap();
+
+        let launcher = Launcher::from_path(&sccache).unwrap();
+        assert_eq!(launcher.path, sccache);
+        assert!(!launcher.is_ccache());
+        assert!(matches!(
+            Launcher::from_path(&dir.path().join("ccache")),
+            Err(LauncherError::LauncherDoesNotExist(ref path)) if path == &dir.path().join("ccache")
+        ));
+        assert!(matches!(
+            Launcher::from_path(Path::new("yambs-test-launcher-that-does-not-exist")),
+            Err(LauncherError::LauncherDoesNotExist(..))
+        ));
+    }
+
+    #[test]
+    fn locates_named_toolchains() {
+        let dir = tempdir::TempDir::new("toolchain").unwrap();
+        let toolchains = dir.path().join(".yambs").join(TOOLCHAINS_DIRECTORY);
+        std::fs::create_dir_all(toolchains.join("gcc13")).unwrap();
+        std::fs::write(toolchains.join("clang17.toml"), "").unwrap();
+        std::fs::write(toolchains.join("gcc13").join(TOOLCHAIN_FILE_NAME), "").unwrap();
+
+        let clang = NamedToolchain::locate("clang17", dir.path()).unwrap();
+        assert_eq!(clang.name, "clang17");
+        assert_eq!(clang.path, toolchains.join("clang17.toml"));
+        let gcc = NamedToolchain::locate("gcc13", dir.path()).unwrap();
+        assert_eq!(gcc.path, toolchains.join("gcc13").join(TOOLCHAIN_FILE_NAME));
+
+        let by_path = toolchains.join("clang17.toml");
+        let by_path = NamedToolchain::locate(by_path.to_str().unwrap(), dir.path()).unwrap();
+        assert_eq!(by_path.name, "clang17");
+        let by_directory = toolchains.join("gcc13");
+        let by_directory =
+            NamedToolchain::locate(by_directory.to_str().unwrap(), dir.path()).unwrap();
+        assert_eq!(by_directory.name, "gcc13");
+
+        assert!(matches!(
+            NamedToolchain::locate("msvc", dir.path()),
+            Err(ToolchainError::NamedToolchainNotFound(..))
+        ));
+    }
+
+    #[test]
+    fn parses_cross_compilation_settings() {
+        let toolchain: Toolchain = toml::from_str(
+            r#"
+            [CXX]
+            compiler = "/usr/bin/clang++"
+
+            [CC]
+            compiler = "/usr/bin/clang"
+
+            [common]
+            target = "aarch64-linux-gnu"
+            sysroot = "/opt/sysroots/aarch64"
+            ranlib = "/usr/bin/llvm-ranlib"
+            strip = "/usr/bin/llvm-strip"
+            objcopy = "/usr/bin/llvm-objcopy"
+            pkg_config_sysroot_dir = "/opt/sysroots/aarch64-pkgconfig"
+            "#,
+        )
+        .unwrap();
+        let common = toolchain.common;
+        assert_eq!(common.target.as_deref(), Some("aarch64-linux-gnu"));
+        assert_eq!(common.sysroot, Some(PathBuf::from("/opt/sysroots/aarch64")));
+        assert_eq!(common.ranlib, Some(PathBuf::from("/usr/bin/llvm-ranlib")));
+        assert_eq!(common.strip, Some(PathBuf::from("/usr/bin/llvm-strip")));
+        assert_eq!(common.objcopy, Some(PathBuf::from("/usr/bin/llvm-objcopy")));
+        assert_eq!(
+            common.pkg_config_sysroot_dir,
+            Some(PathBuf::from("/opt/sysroots/aarch64-pkgconfig"))
+        );
+
+        let toolchain: Toolchain =
+            toml::from_str("[CXX]\ncompiler = \"g++\"\n[CC]\ncompiler = \"gcc\"\n[common]\n")
+                .unwrap();
+        assert_eq!(toolchain.common.target, None);
+        assert_eq!(toolchain.common.sysroot, None);
+    }
+
+    #[test]
+    fn only_arguments_with_separator_or_extension_are_paths() {
+        assert!(!NamedToolchain::is_path("gcc13"));
+        assert!(NamedToolchain::is_path("."));
+        assert!(NamedToolchain::is_path("./gcc13"));
+        assert!(NamedToolchain::is_path("toolchains/gcc13"));
+        assert!(NamedToolchain::is_path("gcc13/"));
+        assert!(NamedToolchain::is_path("arm.toml"));
+    }
+}
@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use crate::errors::FsError;
 pub mod shell;
@@ -93,6 +93,85 @@ pub fn read_file(file_path: &Path) -> Result<String, FsError> {
         .map_err(|err| FsError::ReadFromFile(file_path.to_path_buf(), err))
 }
 
+pub fn copy_file(source: &Path, destination: &Path) -> Result<(), FsError> {
+    std::fs::copy(source, destination)
+        .map_err(|err| FsError::CopyFile(source.to_path_buf(), destination.to_path_buf(), err))?;
+    Ok(())
+}
+
+pub fn copy_dir_all(source: &Path, destination: &Path) -> Result<(), FsError> {
+    create_dir(destination)?;
+    let entries = std::fs::read_dir(source)
+        .map_err(|err| FsError::ReadFromFile(source.to_path_buf(), err))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| FsError::ReadFromFile(source.to_path_buf(), err))?;
+        let path = entry.path();
+        let destination_path = destination.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir_all(&path, &destination_path)?;
+        } else {
+            copy_file(&path, &destination_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The SHA-256 digest of `bytes` as a lowercase hexadecimal string.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::Digest;
+    sha2::Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Removes `.` components and resolves `..` components of `path` lexically, without touching the
+/// file system. `..` components that would go above the start of a relative path are kept.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` relative to the directory `base`. Both are expected to be absolute and normalized.
+/// There is no relative path if the two only have the root in common.
+pub fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path_components = path.components().collect::<Vec<Component>>();
+    let base_components = base.components().collect::<Vec<Component>>();
+    let common = path_components
+        .iter()
+        .zip(base_components.iter())
+        .take_while(|(path_component, base_component)| path_component == base_component)
+        .count();
+    let shares_more_than_root = path_components[..common]
+        .iter()
+        .any(|component| matches!(component, Component::Normal(_)));
+    if !shares_more_than_root {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push(Component::ParentDir);
+    }
+    relative.extend(&path_components[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    Some(relative)
+}
+
 #[cfg(test)]
 #[path = "./mod_test.rs"]
 mod lib_test;
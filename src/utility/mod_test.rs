@@ -94,3 +94,45 @@ fn print_full_path_with_newline_test() {
     print_full_path(&mut formatted_string, dir_path, filename, no_newline);
     assert_eq!(formatted_string, expected);
 }
+
+#[test]
+fn normalize_path_resolves_parent_directories_lexically() {
+    assert_eq!(
+        normalize_path(Path::new("/project/./src/../include/x.h")),
+        PathBuf::from("/project/include/x.h")
+    );
+    assert_eq!(
+        normalize_path(Path::new("/project/../../x.cpp")),
+        PathBuf::from("/x.cpp")
+    );
+    assert_eq!(
+        normalize_path(Path::new("../src/./x.cpp")),
+        PathBuf::from("../src/x.cpp")
+    );
+}
+
+#[test]
+fn relative_path_walks_up_to_common_ancestor() {
+    assert_eq!(
+        relative_path(Path::new("/home/me/greet"), Path::new("/home/me/project")),
+        Some(PathBuf::from("../greet"))
+    );
+    assert_eq!(
+        relative_path(
+            Path::new("/home/me/project/include"),
+            Path::new("/home/me/project")
+        ),
+        Some(PathBuf::from("include"))
+    );
+    assert_eq!(
+        relative_path(Path::new("/home/me/project"), Path::new("/home/me/project")),
+        Some(PathBuf::from("."))
+    );
+    assert_eq!(
+        relative_path(
+            Path::new("/opt/catch2/include"),
+            Path::new("/home/me/project")
+        ),
+        None
+    );
+}
@@ -1 +1,228 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+fn write_script(path: &Path, content: &str) -> PathBuf {
+    std::fs::write(path, content).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_path_buf()
+}
+
+/// A compiler that answers `--version` and `-dumpmachine`, enough for yambs to identify it, and
+/// otherwise creates the file given with `-o`.
+fn stub_compiler(path: &Path) -> PathBuf {
+    write_script(
+        path,
+        "#!/bin/sh\n\
+         case \"$*\" in\n\
+         *-dumpmachine*) echo x86_64-linux-gnu ;;\n\
+         *--version*) echo 'g++ (GCC) 13.2.0' ;;\n\
+         *) while [ $# -gt 0 ]; do [ \"$1\" = -o ] && touch \"$2\"; shift; done ;;\n\
+         esac\n",
+    )
+}
+
+/// A compiler cache that logs `CCACHE_BASEDIR` and the command it runs to `launches` next to it.
+fn stub_ccache(directory: &Path) -> PathBuf {
+    std::fs::create_dir_all(directory).unwrap();
+    write_script(
+        &directory.join("ccache"),
+        "#!/bin/sh\n\
+         echo \"$CCACHE_BASEDIR $*\" >> \"$(dirname \"$0\")/launches\"\n\
+         exec \"$@\"\n",
+    )
+}
+
+/// A project with a named toolchain `gcc13`, made of stub compilers.
+fn project_with_named_toolchain(directory: &Path) {
+    project_with_toolchain(directory, "");
+}
+
+/// A project with a named toolchain `gcc13`, made of stub compilers, with `common` added to the
+/// `common` table of the toolchain file.
+fn project_with_toolchain(directory: &Path, common: &str) {
+    std::fs::write(
+        directory.join("yambs.toml"),
+        "[executable.x]\nsources = [\"main.cpp\"]\n",
+    )
+    .unwrap();
+    std::fs::write(directory.join("main.cpp"), "int main() {}\n").unwrap();
+    let compiler = stub_compiler(&directory.join("stub-g++"));
+    let toolchains = directory.join(".yambs").join("toolchains");
+    std::fs::create_dir_all(&toolchains).unwrap();
+    std::fs::write(
+        toolchains.join("gcc13.toml"),
+        format!(
+            "[CXX]\ncompiler = {0:?}\n\n[CC]\ncompiler = {0:?}\n\n[common]\narchiver = {0:?}\n{1}",
+            compiler, common
+        ),
+    )
+    .unwrap();
+}
+
+fn yambs(directory: &Path, args: &[&str]) -> std::process::Output {
+    yambs_with_path(directory, args, &[])
+}
+
+/// Runs yambs with `path` in front of `PATH`.
+fn yambs_with_path(directory: &Path, args: &[&str], path: &[PathBuf]) -> std::process::Output {
+    let path_env = std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default())
+        .collect::<Vec<PathBuf>>();
+    Command::new(env!("CARGO_BIN_EXE_yambs"))
+        .args(args)
+        .current_dir(directory)
+        .env_remove("CXX")
+        .env_remove("CC")
+        .env(
+            "PATH",
+            std::env::join_paths(path.iter().chain(path_env.iter())).unwrap(),
+        )
+        .output()
+        .unwrap()
+}
+
+fn build(directory: &Path, args: &[&str], path: &[PathBuf]) -> String {
+    let args = ["build", "--toolchain", "gcc13", "--std", "c++17"]
+        .iter()
+        .chain(args)
+        .copied()
+        .collect::<Vec<&str>>();
+    let output = yambs_with_path(directory, &args, path);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    std::fs::read_to_string(directory.join("gcc13").join("debug").join("Makefile")).unwrap()
+}
+
+fn launches(directory: &Path) -> Option<String> {
+    std::fs::read_to_string(directory.join("launches")).ok()
+}
+
+#[test]
+fn named_toolchain_is_found_when_its_build_directory_exists() {
+    let dir = tempdir::TempDir::new("named_toolchain").unwrap();
+    project_with_named_toolchain(dir.path());
+
+    let output = yambs(dir.path(), &["tree", "--toolchain", "gcc13"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The build files of the toolchain are put in ./gcc13, which must not be taken for a path to
+    // the toolchain when running again.
+    std::fs::create_dir_all(dir.path().join("gcc13")).unwrap();
+    let output = yambs(dir.path(), &["tree", "--toolchain", "gcc13"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains('x'));
+}
+
+#[test]
+fn compile_commands_are_run_through_launcher_of_toolchain() {
+    let dir = tempdir::TempDir::new("launcher").unwrap();
+    let launcher_directory = dir.path().join("launcher");
+    let ccache = stub_ccache(&launcher_directory);
+    project_with_toolchain(dir.path(), &format!("launcher = {:?}\n", ccache));
+
+    let makefile = build(dir.path(), &[], &[]);
+    assert!(makefile.contains(&format!(
+        "LAUNCHER := {}\n\
+         export CCACHE_BASEDIR ?= {}\n\
+         export CCACHE_NOHASHDIR ?= true\n",
+        ccache.display(),
+        dir.path().display()
+    )));
+    let launches = launches(&launcher_directory).unwrap();
+    assert!(launches.starts_with(&format!(
+        "{} {}",
+        dir.path().display(),
+        dir.path().join("stub-g++").display()
+    )));
+    assert!(launches.contains(" -c -o "));
+}
+
+#[test]
+fn launcher_is_not_used_with_no_launcher() {
+    let dir = tempdir::TempDir::new("no_launcher").unwrap();
+    let launcher_directory = dir.path().join("launcher");
+    let ccache = stub_ccache(&launcher_directory);
+    project_with_toolchain(dir.path(), &format!("launcher = {:?}\n", ccache));
+
+    let makefile = build(dir.path(), &["--no-launcher"], &[]);
+    assert!(makefile.contains("LAUNCHER :=\n"));
+    assert!(!makefile.contains("CCACHE_BASEDIR"));
+    assert_eq!(launches(&launcher_directory), None);
+}
+
+#[test]
+fn launcher_is_looked_up_in_path() {
+    let dir = tempdir::TempDir::new("launcher_in_path").unwrap();
+    let launcher_directory = dir.path().join("launcher");
+    let ccache = stub_ccache(&launcher_directory);
+    project_with_named_toolchain(dir.path());
+
+    let makefile = build(dir.path(), &[], std::slice::from_ref(&launcher_directory));
+    assert!(makefile.contains(&format!("LAUNCHER := {}\n", ccache.display())));
+    assert!(launches(&launcher_directory).is_some());
+}
+
+#[test]
+fn launcher_of_toolchain_is_looked_up_in_path_by_name() {
+    let dir = tempdir::TempDir::new("launcher_by_name").unwrap();
+    let launcher_directory = dir.path().join("launcher");
+    let ccache = stub_ccache(&launcher_directory);
+    project_with_toolchain(dir.path(), "launcher = \"ccache\"\n");
+
+    let makefile = build(dir.path(), &[], &[launcher_directory]);
+    assert!(makefile.contains(&format!("LAUNCHER := {}\n", ccache.display())));
+}
+
+#[test]
+fn link_flag_changes_leave_command_signature_untouched() {
+    let dir = tempdir::TempDir::new("signature").unwrap();
+    project_with_named_toolchain(dir.path());
+    let pc_directory = dir.path().join("pc");
+    std::fs::create_dir_all(&pc_directory).unwrap();
+    let write_pc_file = |libs: &str| {
+        std::fs::write(
+            pc_directory.join("foo.pc"),
+            format!("Name: foo\nVersion: 1.0\nLibs: {}\nCflags: -DFOO\n", libs),
+        )
+        .unwrap()
+    };
+    write_pc_file("-L/opt/foo/lib -lfoo");
+    std::fs::write(
+        dir.path().join("yambs.toml"),
+        format!(
+            "[executable.x]\nsources = [\"main.cpp\"]\n\n\
+             [executable.x.dependencies.foo]\npkg_config_search_dir = {:?}\n",
+            pc_directory
+        ),
+    )
+    .unwrap();
+
+    build(dir.path(), &[], &[]);
+    let signature = std::fs::read_dir(dir.path().join("gcc13").join("debug").join("deps"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path().join("command.sig"))
+        .find(|path| path.is_file())
+        .unwrap();
+    let content = std::fs::read_to_string(&signature).unwrap();
+    let modified = std::fs::metadata(&signature).unwrap().modified().unwrap();
+
+    write_pc_file("-L/opt/foo/lib -lfoo -lbar");
+    let makefile = build(dir.path(), &[], &[]);
+    assert!(makefile.contains("-L/opt/foo/lib -lfoo -lbar"));
+    assert_eq!(std::fs::read_to_string(&signature).unwrap(), content);
+    assert_eq!(
+        std::fs::metadata(&signature).unwrap().modified().unwrap(),
+        modified
+    );
+}